@@ -2,12 +2,19 @@
 
 extern crate zip_container;
 #[cfg(not(target_arch = "wasm32"))]
-use zip_container::{ZipContainer, ZipContainerResult, ZipContainerTrait};
+use zip_container::{http_cache::HttpCache, HttpOptions, LoaderRegistry, ZipContainer, ZipContainerResult, ZipContainerTrait};
 
 #[cfg(not(target_arch = "wasm32"))]
+#[allow(clippy::clone_on_copy)]
 fn main() -> ZipContainerResult<()> {
+    // Cache downloads, so repeated runs only revalidate the container
+    let mut loader = LoaderRegistry::default();
+    loader.register_http(HttpOptions {
+        cache: Some(HttpCache::new(std::env::temp_dir().join("zip_container_cache"))),
+        ..Default::default()
+    });
     // Create a new ZipContainer instance
-    let zip_container = ZipContainer::new("https://raw.githubusercontent.com/holg/gldf-rs/refs/heads/master/tests/data/test.gldf".to_string(), Some("product.xml".to_string()));
+    let zip_container = ZipContainer::with_loader("https://raw.githubusercontent.com/holg/gldf-rs/refs/heads/master/tests/data/test.gldf".to_string(), Some("product.xml".to_string()), loader);
     for file in zip_container.get_zip_files()?.iter(){
             println!("Loaded file name: {}, size: {}, path: {}, file_id: {}",
                 file.name.clone().expect("Failed to get file name"),
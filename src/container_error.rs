@@ -26,6 +26,7 @@ pub enum ZipContainerError {
     },
     /// Error variant for Reqwest errors.
     /// Contains the module path where the error occurred and the source error.
+    #[cfg(feature = "http")]
     ReqwestError {
         module_path: &'static str,
         source: reqwest::Error,
@@ -34,9 +35,220 @@ pub enum ZipContainerError {
         module_path: &'static str,
         message: String,
     },
+    /// Error variant for operations aborted through a `CancellationToken`.
+    /// Contains the module path where the error occurred and a message.
+    Cancelled {
+        module_path: &'static str,
+        message: String,
+    },
+    /// Error variant for downloads that do not match the expected digest or size.
+    /// Contains the module path, the loaded location and the expected and actual values.
+    IntegrityMismatch {
+        module_path: &'static str,
+        location: String,
+        expected: String,
+        actual: String,
+    },
+    /// Error variant for entries that are not part of the archive.
+    /// Contains the module path, the container source and the entry name.
+    EntryNotFound {
+        module_path: &'static str,
+        container: String,
+        entry: String,
+    },
+    /// Error variant for data that is not a readable ZIP archive.
+    /// Contains the module path, the container source, the entry if one was read and a message.
+    InvalidArchive {
+        module_path: &'static str,
+        container: String,
+        entry: Option<String>,
+        message: String,
+    },
+    /// Error variant for entries stored with a compression method that is not compiled in.
+    /// Contains the module path, the container source, the entry and a message.
+    UnsupportedCompression {
+        module_path: &'static str,
+        container: String,
+        entry: Option<String>,
+        message: String,
+    },
+    /// Error variant for encrypted entries, which cannot be read without a password.
+    /// Contains the module path, the container source and the entry.
+    Encrypted {
+        module_path: &'static str,
+        container: String,
+        entry: Option<String>,
+    },
+    /// Error variant for data exceeding a configured limit, e.g. `HttpOptions::max_body_size`.
+    /// Contains the module path, the container source or URL, the entry and a message.
+    LimitExceeded {
+        module_path: &'static str,
+        container: String,
+        entry: Option<String>,
+        message: String,
+    },
+    /// Error variant for definition files that cannot be decoded or parsed.
+    /// Contains the module path, the container source, the definition entry and a message.
+    DefinitionParse {
+        module_path: &'static str,
+        container: String,
+        entry: String,
+        message: String,
+    },
+    /// Error variant for definitions that do not match their schema.
+    /// Contains the module path, the container source, the definition entry and the problems found.
+    ValidationFailed {
+        module_path: &'static str,
+        container: String,
+        entry: String,
+        diagnostics: Vec<Diagnostic>,
+    },
     Utf8Error(FromUtf8Error),
 }
 
+/// A problem found while validating a definition against a schema.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Location of the problem in the definition, e.g. `/Root/Header/Manufacturer` for XML
+    /// or the JSON pointer `/products/0/name` for JSON, YAML and TOML.
+    pub path: String,
+    pub message: String,
+    /// 1-based line in the definition source, if known.
+    pub line: Option<usize>,
+    /// 1-based column in the definition source, if known.
+    pub column: Option<usize>,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let (Some(line), Some(column)) = (self.line, self.column) {
+            write!(f, "{}:{}: ", line, column)?;
+        }
+        if !self.path.is_empty() {
+            write!(f, "{}: ", self.path)?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+impl ZipContainerError {
+    /// Returns a stable, machine readable code for the kind of error, e.g. `ENTRY_NOT_FOUND`.
+    /// The codes are exposed unchanged by the Python and WASM bindings.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ZipContainerError::MissingValue { .. } => "MISSING_VALUE",
+            ZipContainerError::IOError { .. } => "IO_ERROR",
+            ZipContainerError::InvalidData { .. } => "INVALID_DATA",
+            #[cfg(feature = "http")]
+            ZipContainerError::ReqwestError { .. } => "HTTP_ERROR",
+            ZipContainerError::UnsupportedOperation { .. } => "UNSUPPORTED_OPERATION",
+            ZipContainerError::Cancelled { .. } => "CANCELLED",
+            ZipContainerError::IntegrityMismatch { .. } => "INTEGRITY_MISMATCH",
+            ZipContainerError::EntryNotFound { .. } => "ENTRY_NOT_FOUND",
+            ZipContainerError::InvalidArchive { .. } => "INVALID_ARCHIVE",
+            ZipContainerError::UnsupportedCompression { .. } => "UNSUPPORTED_COMPRESSION",
+            ZipContainerError::Encrypted { .. } => "ENCRYPTED",
+            ZipContainerError::LimitExceeded { .. } => "LIMIT_EXCEEDED",
+            ZipContainerError::DefinitionParse { .. } => "DEFINITION_PARSE",
+            ZipContainerError::ValidationFailed { .. } => "VALIDATION_FAILED",
+            ZipContainerError::Utf8Error(_) => "UTF8_ERROR",
+        }
+    }
+
+    /// Returns the module path where the error occurred, if recorded.
+    pub fn module_path(&self) -> Option<&'static str> {
+        match self {
+            ZipContainerError::MissingValue { module_path, .. }
+            | ZipContainerError::IOError { module_path, .. }
+            | ZipContainerError::InvalidData { module_path, .. }
+            | ZipContainerError::UnsupportedOperation { module_path, .. }
+            | ZipContainerError::Cancelled { module_path, .. }
+            | ZipContainerError::IntegrityMismatch { module_path, .. }
+            | ZipContainerError::EntryNotFound { module_path, .. }
+            | ZipContainerError::InvalidArchive { module_path, .. }
+            | ZipContainerError::UnsupportedCompression { module_path, .. }
+            | ZipContainerError::Encrypted { module_path, .. }
+            | ZipContainerError::LimitExceeded { module_path, .. }
+            | ZipContainerError::DefinitionParse { module_path, .. }
+            | ZipContainerError::ValidationFailed { module_path, .. } => Some(module_path),
+            #[cfg(feature = "http")]
+            ZipContainerError::ReqwestError { module_path, .. } => Some(module_path),
+            ZipContainerError::Utf8Error(_) => None,
+        }
+    }
+
+    /// Returns the name of the entry the error refers to, if any.
+    pub fn entry(&self) -> Option<&str> {
+        match self {
+            ZipContainerError::EntryNotFound { entry, .. }
+            | ZipContainerError::DefinitionParse { entry, .. }
+            | ZipContainerError::ValidationFailed { entry, .. } => Some(entry),
+            ZipContainerError::InvalidArchive { entry, .. }
+            | ZipContainerError::UnsupportedCompression { entry, .. }
+            | ZipContainerError::Encrypted { entry, .. }
+            | ZipContainerError::LimitExceeded { entry, .. } => entry.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Returns the source (path or URL) of the container the error refers to, if any.
+    pub fn container(&self) -> Option<&str> {
+        match self {
+            ZipContainerError::EntryNotFound { container, .. }
+            | ZipContainerError::InvalidArchive { container, .. }
+            | ZipContainerError::UnsupportedCompression { container, .. }
+            | ZipContainerError::Encrypted { container, .. }
+            | ZipContainerError::LimitExceeded { container, .. }
+            | ZipContainerError::DefinitionParse { container, .. }
+            | ZipContainerError::ValidationFailed { container, .. } => Some(container),
+            ZipContainerError::IntegrityMismatch { location, .. } => Some(location),
+            _ => None,
+        }
+    }
+
+    /// Returns the problems found by schema validation; empty for other errors.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        match self {
+            ZipContainerError::ValidationFailed { diagnostics, .. } => diagnostics,
+            _ => &[],
+        }
+    }
+
+    /// Maps an error of the `zip` crate to the matching variant, by its `ZipError` variant.
+    /// Unsupported compression methods are detected before reading, from the entry's metadata.
+    pub fn from_zip(
+        error: zip::result::ZipError,
+        module_path: &'static str,
+        container: &str,
+        entry: Option<&str>,
+    ) -> ZipContainerError {
+        use zip::result::ZipError;
+        let container = container.to_string();
+        let entry = entry.map(str::to_string);
+        match error {
+            ZipError::Io(source) => ZipContainerError::IOError { module_path, source },
+            ZipError::FileNotFound => ZipContainerError::EntryNotFound {
+                module_path,
+                container,
+                entry: entry.unwrap_or_default(),
+            },
+            ZipError::InvalidPassword | ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED) => {
+                ZipContainerError::Encrypted { module_path, container, entry }
+            }
+            ZipError::UnsupportedArchive(message) => ZipContainerError::UnsupportedOperation {
+                module_path,
+                message: format!("{}: unsupported ZIP archive: {}", container, message),
+            },
+            other => ZipContainerError::InvalidArchive {
+                module_path,
+                container,
+                entry,
+                message: other.to_string(),
+            },
+        }
+    }
+}
+
 impl fmt::Display for ZipContainerError {
     /// Formats the error for display.
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -50,12 +262,51 @@ impl fmt::Display for ZipContainerError {
             ZipContainerError::InvalidData { module_path, message } => {
                 write!(f, "{}: {}", module_path, message)
             }
+            #[cfg(feature = "http")]
             ZipContainerError::ReqwestError { module_path, source } => {
                 write!(f, "{}: {}", module_path, source)
             }
             ZipContainerError::UnsupportedOperation { module_path, message } => {
                 write!(f, "{}: {}", module_path, message)
             }
+            ZipContainerError::Cancelled { module_path, message } => {
+                write!(f, "{}: {}", module_path, message)
+            }
+            ZipContainerError::IntegrityMismatch { module_path, location, expected, actual } => {
+                write!(
+                    f,
+                    "{}: integrity mismatch for {}: expected {}, got {}",
+                    module_path, location, expected, actual
+                )
+            }
+            ZipContainerError::EntryNotFound { module_path, container, entry } => {
+                write!(f, "{}: entry '{}' not found in {}", module_path, entry, container)
+            }
+            ZipContainerError::InvalidArchive { module_path, container, entry, message } => {
+                write!(f, "{}: invalid ZIP archive {}{}: {}", module_path, container, entry_suffix(entry), message)
+            }
+            ZipContainerError::UnsupportedCompression { module_path, container, entry, message } => {
+                write!(f, "{}: unsupported compression in {}{}: {}", module_path, container, entry_suffix(entry), message)
+            }
+            ZipContainerError::Encrypted { module_path, container, entry } => {
+                write!(f, "{}: encrypted entry in {}{}", module_path, container, entry_suffix(entry))
+            }
+            ZipContainerError::LimitExceeded { module_path, container, entry, message } => {
+                write!(f, "{}: limit exceeded for {}{}: {}", module_path, container, entry_suffix(entry), message)
+            }
+            ZipContainerError::DefinitionParse { module_path, container, entry, message } => {
+                write!(f, "{}: cannot parse definition '{}' in {}: {}", module_path, entry, container, message)
+            }
+            ZipContainerError::ValidationFailed { module_path, container, entry, diagnostics } => {
+                write!(f, "{}: definition '{}' in {} does not match its schema", module_path, entry, container)?;
+                if let Some(first) = diagnostics.first() {
+                    write!(f, ": {}", first)?;
+                }
+                if diagnostics.len() > 1 {
+                    write!(f, " (and {} more)", diagnostics.len() - 1)?;
+                }
+                Ok(())
+            }
             ZipContainerError::Utf8Error(e) => {
                 write!(f, "UTF-8 error: {}", e)
             }
@@ -66,11 +317,16 @@ impl fmt::Display for ZipContainerError {
     }
 }
 
+fn entry_suffix(entry: &Option<String>) -> String {
+    entry.as_ref().map(|entry| format!(" (entry '{}')", entry)).unwrap_or_default()
+}
+
 impl Error for ZipContainerError {
     /// Returns the source of the error if available.
     fn source(&self) -> Option<&(dyn Error + 'static)> {
         match self {
             ZipContainerError::IOError { source, .. } => Some(source),
+            #[cfg(feature = "http")]
             ZipContainerError::ReqwestError { source, .. } => Some(source),
             ZipContainerError::Utf8Error(e) => Some(e),
             _ => None,
@@ -102,7 +358,20 @@ macro_rules! io_err {
     };
 }
 
+/// Macro to map a `zip::result::ZipError` to the matching variant of `ZipContainerError`,
+/// recording the container source and, optionally, the entry name.
+#[macro_export]
+macro_rules! zip_err {
+    ($expr:expr, $container:expr) => {
+        $expr.map_err(|e| $crate::ZipContainerError::from_zip(e, function_path!(), $container, None))
+    };
+    ($expr:expr, $container:expr, $entry:expr) => {
+        $expr.map_err(|e| $crate::ZipContainerError::from_zip(e, function_path!(), $container, Some($entry)))
+    };
+}
+
 /// Macro to map an expression to a `ReqwestError` variant of `ZipContainerError`.
+#[cfg(feature = "http")]
 #[macro_export]
 macro_rules! reqwest_err {
     ($expr:expr) => {
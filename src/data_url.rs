@@ -0,0 +1,133 @@
+// src/data_url.rs
+//! Decoding of RFC 2397 `data:` URLs, e.g. `data:image/png;base64,iVBORw0KGgo...`.
+use crate::zip_container_trait::{AsyncFileLoader, FileLoader, ZipContainerFuture};
+use crate::{function_path, BufFile, ZipContainerError, ZipContainerResult};
+use base64::Engine;
+
+/// Media type assumed by RFC 2397 when a `data:` URL does not declare one.
+pub const DEFAULT_MEDIA_TYPE: &str = "text/plain;charset=US-ASCII";
+
+/// A decoded `data:` URL.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DataUrl {
+    /// The declared media type including its parameters (without `;base64`),
+    /// or `DEFAULT_MEDIA_TYPE` if none was given.
+    pub media_type: String,
+    /// The decoded payload.
+    pub data: Vec<u8>,
+}
+
+impl DataUrl {
+    /// Parses and decodes a `data:` URL.
+    pub fn parse(url: &str) -> ZipContainerResult<DataUrl> {
+        let rest = url
+            .get(..5)
+            .filter(|scheme| scheme.eq_ignore_ascii_case("data:"))
+            .map(|_| &url[5..])
+            .ok_or_else(|| ZipContainerError::InvalidData {
+                module_path: function_path!(),
+                message: format!("Not a data URL: {}", truncate(url)),
+            })?;
+        let (header, payload) = rest.split_once(',').ok_or_else(|| ZipContainerError::InvalidData {
+            module_path: function_path!(),
+            message: format!("Data URL without ',' separator: {}", truncate(url)),
+        })?;
+
+        let mut is_base64 = false;
+        let mut parameters: Vec<&str> = Vec::new();
+        for (i, part) in header.split(';').enumerate() {
+            let part = part.trim();
+            if part.eq_ignore_ascii_case("base64") {
+                is_base64 = true;
+            } else if i == 0 || !part.is_empty() {
+                parameters.push(part);
+            }
+        }
+        let media_type = match parameters.first() {
+            Some(mime) if !mime.is_empty() => parameters.join(";"),
+            // ";charset=..." without a type keeps the RFC default type
+            _ if parameters.len() > 1 => format!("text/plain;{}", parameters[1..].join(";")),
+            _ => DEFAULT_MEDIA_TYPE.to_string(),
+        };
+
+        let decoded = percent_decode(payload);
+        let data = if is_base64 {
+            let compact: Vec<u8> = decoded.into_iter().filter(|b| !b.is_ascii_whitespace()).collect();
+            base64::engine::general_purpose::STANDARD
+                .decode(&compact)
+                .or_else(|_| base64::engine::general_purpose::STANDARD_NO_PAD.decode(&compact))
+                .or_else(|_| base64::engine::general_purpose::URL_SAFE_NO_PAD.decode(&compact))
+                .map_err(|e| ZipContainerError::InvalidData {
+                    module_path: function_path!(),
+                    message: format!("Invalid base64 in data URL: {}", e),
+                })?
+        } else {
+            decoded
+        };
+        Ok(DataUrl { media_type, data })
+    }
+
+    /// Converts the decoded URL into a `BufFile` carrying the bytes and the media type.
+    pub fn into_buf_file(self) -> BufFile {
+        BufFile {
+            size: Some(self.data.len() as u64),
+            content: Some(self.data),
+            content_type: Some(self.media_type),
+            ..Default::default()
+        }
+    }
+}
+
+/// Loader for `data:` URLs; available on every target including WASM.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DataUrlLoader;
+
+impl FileLoader for DataUrlLoader {
+    fn load(&self, path_or_url: &str) -> ZipContainerResult<Vec<u8>> {
+        Ok(DataUrl::parse(path_or_url)?.data)
+    }
+
+    fn load_buf_file(&self, path_or_url: &str) -> ZipContainerResult<BufFile> {
+        Ok(DataUrl::parse(path_or_url)?.into_buf_file())
+    }
+}
+
+impl AsyncFileLoader for DataUrlLoader {
+    fn load_async(&self, path_or_url: &str) -> ZipContainerFuture<Vec<u8>> {
+        let result = self.load(path_or_url);
+        Box::pin(async move { result })
+    }
+
+    fn load_buf_file_async(&self, path_or_url: &str) -> ZipContainerFuture<BufFile> {
+        let result = self.load_buf_file(path_or_url);
+        Box::pin(async move { result })
+    }
+}
+
+/// Decodes `%XX` escapes, leaving malformed escapes untouched.
+pub(crate) fn percent_decode(input: &str) -> Vec<u8> {
+    let bytes = input.as_bytes();
+    let mut output = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() && bytes[i + 1..i + 3].iter().all(u8::is_ascii_hexdigit) {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+            if let Some(value) = hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                output.push(value);
+                i += 3;
+                continue;
+            }
+        }
+        output.push(bytes[i]);
+        i += 1;
+    }
+    output
+}
+
+// Keeps error messages readable for large inline payloads
+fn truncate(url: &str) -> &str {
+    match url.char_indices().nth(64) {
+        Some((index, _)) => &url[..index],
+        None => url,
+    }
+}
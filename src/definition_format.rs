@@ -0,0 +1,114 @@
+// src/definition_format.rs
+//! Format dispatch for `Definition`: the `DefinitionKind` of a definition and typed
+//! (de)serialization through serde. Each format is behind its cargo feature
+//! (`json`, `yaml`, `toml`, `xml`).
+use crate::{function_path, Definition, ZipContainerError, ZipContainerResult};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+/// The format of a `Definition` without its content.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DefinitionKind {
+    Xml,
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl DefinitionKind {
+    /// Returns the usual file extension, e.g. `"json"`.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            DefinitionKind::Xml => "xml",
+            DefinitionKind::Json => "json",
+            DefinitionKind::Yaml => "yaml",
+            DefinitionKind::Toml => "toml",
+        }
+    }
+}
+
+impl Definition {
+    /// Creates a definition of `kind` holding `content`.
+    pub fn new(kind: DefinitionKind, content: String) -> Definition {
+        match kind {
+            DefinitionKind::Xml => Definition::XML(content),
+            DefinitionKind::Json => Definition::JSON(content),
+            DefinitionKind::Yaml => Definition::YAML(content),
+            DefinitionKind::Toml => Definition::TOML(content),
+        }
+    }
+
+    pub fn kind(&self) -> DefinitionKind {
+        match self {
+            Definition::XML(_) => DefinitionKind::Xml,
+            Definition::JSON(_) => DefinitionKind::Json,
+            Definition::YAML(_) => DefinitionKind::Yaml,
+            Definition::TOML(_) => DefinitionKind::Toml,
+        }
+    }
+
+    /// Deserializes the content with the deserializer matching the variant.
+    pub fn parse_as<T: DeserializeOwned>(&self) -> ZipContainerResult<T> {
+        match self {
+            Definition::XML(content) => from_xml(content),
+            Definition::JSON(content) => from_json(content),
+            Definition::YAML(content) => from_yaml(content),
+            Definition::TOML(content) => from_toml(content),
+        }
+    }
+
+    /// Serializes `value` into a definition of `kind`. For XML the root element is
+    /// named after the type of `value`.
+    pub fn serialize_from<T: Serialize>(kind: DefinitionKind, value: &T) -> ZipContainerResult<Definition> {
+        let content = match kind {
+            DefinitionKind::Xml => to_xml(value)?,
+            DefinitionKind::Json => to_json(value)?,
+            DefinitionKind::Yaml => to_yaml(value)?,
+            DefinitionKind::Toml => to_toml(value)?,
+        };
+        Ok(Definition::new(kind, content))
+    }
+}
+
+#[cfg(not(all(feature = "json", feature = "yaml", feature = "toml", feature = "xml")))]
+pub(crate) fn feature_disabled(feature: &str) -> ZipContainerError {
+    ZipContainerError::UnsupportedOperation {
+        module_path: function_path!(),
+        message: format!("zip_container was built without the `{}` feature", feature),
+    }
+}
+
+macro_rules! format_functions {
+    ($feature:literal, $from:ident, $to:ident, $deserialize:expr, $serialize:expr) => {
+        #[cfg(feature = $feature)]
+        fn $from<T: DeserializeOwned>(content: &str) -> ZipContainerResult<T> {
+            $deserialize(content).map_err(|e| ZipContainerError::InvalidData {
+                module_path: function_path!(),
+                message: e.to_string(),
+            })
+        }
+
+        #[cfg(feature = $feature)]
+        fn $to<T: Serialize>(value: &T) -> ZipContainerResult<String> {
+            $serialize(value).map_err(|e| ZipContainerError::InvalidData {
+                module_path: function_path!(),
+                message: e.to_string(),
+            })
+        }
+
+        #[cfg(not(feature = $feature))]
+        fn $from<T: DeserializeOwned>(_content: &str) -> ZipContainerResult<T> {
+            Err(feature_disabled($feature))
+        }
+
+        #[cfg(not(feature = $feature))]
+        fn $to<T: Serialize>(_value: &T) -> ZipContainerResult<String> {
+            Err(feature_disabled($feature))
+        }
+    };
+}
+
+format_functions!("json", from_json, to_json, serde_json::from_str::<T>, serde_json::to_string_pretty::<T>);
+format_functions!("yaml", from_yaml, to_yaml, serde_yaml::from_str::<T>, serde_yaml::to_string::<T>);
+format_functions!("toml", from_toml, to_toml, toml::from_str::<T>, toml::to_string_pretty::<T>);
+format_functions!("xml", from_xml, to_xml, quick_xml::de::from_str::<T>, quick_xml::se::to_string::<T>);
@@ -0,0 +1,123 @@
+// src/detection.rs
+//! Detection of the `Definition` format from the file name or, as a fallback, from
+//! the content of the definition file, and discovery of the definition file itself.
+use crate::Definition;
+
+/// How the format of the definition file was determined.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DetectionMethod {
+    /// From the extension of `definition_path`, e.g. `.xml` or `.gldf.json`.
+    Extension,
+    /// From the content, for unknown extensions and extensionless names such as `manifest`.
+    Content,
+}
+
+/// Well-known definition files, tried in this order when `definition_path` is not set:
+/// GLDF, L3D, generic manifests and the EPUB and ODF pointers, which are followed to
+/// the definition they name (see `pointer`).
+pub const DEFAULT_DEFINITION_CANDIDATES: &[&str] = &[
+    "product.xml",
+    "structure.xml",
+    "manifest.json",
+    "META-INF/container.xml",
+    "META-INF/manifest.xml",
+];
+
+/// Picks the definition file among the archive `entries`: the first of `candidates`
+/// that is present (compared case-insensitively) or else the only top-level XML or
+/// JSON file.
+pub fn discover_definition<S: AsRef<str>>(entries: &[String], candidates: &[S]) -> Option<String> {
+    for candidate in candidates {
+        let candidate = candidate.as_ref();
+        if let Some(entry) = entries.iter().find(|entry| entry.eq_ignore_ascii_case(candidate)) {
+            return Some(entry.clone());
+        }
+    }
+    let mut top_level = entries.iter().filter(|entry| {
+        !entry.contains('/')
+            && matches!(
+                definition_from_extension(entry),
+                Some(Definition::XML(_)) | Some(Definition::JSON(_))
+            )
+    });
+    match (top_level.next(), top_level.next()) {
+        (Some(entry), None) => Some(entry.clone()),
+        _ => None,
+    }
+}
+
+/// Returns the definition format for the extension of `path`, ignoring case.
+pub fn definition_from_extension(path: &str) -> Option<Definition> {
+    let name = path.rsplit(['/', '\\']).next().unwrap_or(path);
+    let (stem, extension) = name.rsplit_once('.')?;
+    if stem.is_empty() {
+        return None;
+    }
+    match extension.to_ascii_lowercase().as_str() {
+        "xml" => Some(Definition::XML(String::new())),
+        "json" => Some(Definition::JSON(String::new())),
+        "yaml" | "yml" => Some(Definition::YAML(String::new())),
+        "toml" => Some(Definition::TOML(String::new())),
+        _ => None,
+    }
+}
+
+/// Returns the definition format recognised from `content`: an XML prolog or root
+/// element, a JSON object or array, YAML document markers or mappings, or TOML
+/// tables and key/value pairs.
+pub fn sniff_definition(content: &[u8]) -> Option<Definition> {
+    let content = content.strip_prefix(b"\xef\xbb\xbf").unwrap_or(content);
+    // Only the beginning is inspected; multi-byte characters cut at the end are dropped
+    let head = &content[..content.len().min(4096)];
+    let head = match std::str::from_utf8(head) {
+        Ok(head) => head,
+        Err(e) => std::str::from_utf8(&head[..e.valid_up_to()]).ok()?,
+    };
+    let first_line = head
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with('#'))?;
+
+    if first_line.starts_with('<') {
+        return Some(Definition::XML(String::new()));
+    }
+    if first_line.starts_with('{') || (first_line.starts_with('[') && !is_toml_table(first_line)) {
+        return Some(Definition::JSON(String::new()));
+    }
+    if first_line.starts_with("---") || first_line.starts_with("%YAML") {
+        return Some(Definition::YAML(String::new()));
+    }
+    if is_toml_table(first_line) || is_toml_key_value(first_line) {
+        return Some(Definition::TOML(String::new()));
+    }
+    if first_line.starts_with("- ") || first_line.split_once(": ").is_some() || first_line.ends_with(':') {
+        return Some(Definition::YAML(String::new()));
+    }
+    None
+}
+
+/// `[table]` or `[[array.of.tables]]` with bare keys; a quoted name such as `["a"]` is
+/// taken for a JSON array
+fn is_toml_table(line: &str) -> bool {
+    let inner = line
+        .strip_prefix("[[")
+        .and_then(|rest| rest.strip_suffix("]]"))
+        .or_else(|| line.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')));
+    inner.is_some_and(|name| {
+        !name.trim().is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | ' '))
+    })
+}
+
+/// `key = value` with a bare or quoted key
+fn is_toml_key_value(line: &str) -> bool {
+    line.split_once('=').is_some_and(|(key, _)| {
+        let key = key.trim();
+        !key.is_empty()
+            && key
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '"'))
+    })
+}
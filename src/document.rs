@@ -0,0 +1,405 @@
+// src/document.rs
+//! Conversion between definition formats through a generic document tree
+//! (`serde_json::Value` with insertion order preserved). Requires the `json` feature,
+//! plus the feature of every other format involved.
+//!
+//! XML is mapped like xmltodict does it:
+//!
+//! * the document is an object with a single key, the root element name;
+//! * attributes become keys prefixed with `@` (`@id`), namespace prefixes are kept
+//!   (`@xmlns:xsi`, `@xsi:noNamespaceSchemaLocation`);
+//! * an element with only text becomes a string, an empty element `null`;
+//! * text of an element that also has attributes is stored under `#text`;
+//! * repeated child elements become an array.
+//!
+//! Where this would lose information, the content of an element is kept in order under
+//! `#children` instead, a list of single-key objects: child elements by name, `#text`,
+//! `#comment` and `?target` for processing instructions. That is the case for same-name
+//! siblings separated by others, text between child elements (mixed content, kept
+//! verbatim) and comments or processing instructions. Comments, processing
+//! instructions and a `#doctype` around the root element turn the document itself into
+//! such a `#children` object. Whitespace between child elements is formatting and
+//! dropped; converting back to XML writes an `<?xml version="1.0" encoding="UTF-8"?>`
+//! declaration and indents with four spaces, except inside mixed content.
+//!
+//! Converting to XML fails for keys that are not XML names. TOML has no `null` and
+//! requires a table at the root, so such documents cannot be converted to TOML.
+use crate::encoding::escape_xml;
+use crate::{function_path, Definition, DefinitionKind, ZipContainerError, ZipContainerResult};
+#[cfg(feature = "xml")]
+use crate::xml_tree::{Content, Tree};
+#[cfg(feature = "xml")]
+use serde_json::Map;
+use serde_json::Value;
+
+impl Definition {
+    /// Converts the definition into `kind`, see the module documentation for the XML mapping.
+    pub fn convert_to(&self, kind: DefinitionKind) -> ZipContainerResult<Definition> {
+        if self.kind() == kind {
+            return Ok(self.clone());
+        }
+        Definition::from_document(kind, &self.to_document()?)
+    }
+
+    /// Parses the definition into the generic document tree.
+    pub fn to_document(&self) -> ZipContainerResult<Value> {
+        match self {
+            Definition::XML(content) => xml_to_document(content),
+            Definition::JSON(_) | Definition::YAML(_) | Definition::TOML(_) => self.parse_as(),
+        }
+    }
+
+    /// Serializes a document tree into a definition of `kind`.
+    pub fn from_document(kind: DefinitionKind, document: &Value) -> ZipContainerResult<Definition> {
+        match kind {
+            DefinitionKind::Xml => Ok(Definition::XML(document_to_xml(document)?)),
+            DefinitionKind::Toml if contains_null(document) => Err(ZipContainerError::InvalidData {
+                module_path: function_path!(),
+                message: "TOML cannot represent null values".to_string(),
+            }),
+            _ => Definition::serialize_from(kind, document),
+        }
+    }
+}
+
+fn contains_null(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::Array(items) => items.iter().any(contains_null),
+        Value::Object(map) => map.values().any(contains_null),
+        _ => false,
+    }
+}
+
+#[cfg(feature = "xml")]
+fn xml_to_document(content: &str) -> ZipContainerResult<Value> {
+    let tree = Tree::parse(content).map_err(|diagnostic| ZipContainerError::InvalidData {
+        module_path: function_path!(),
+        message: format!("Invalid XML: {}", diagnostic),
+    })?;
+    let mut document = Map::new();
+    match tree.prolog.as_slice() {
+        [Content::Element(root)] => document.insert(tree.nodes[*root].name.clone(), element_value(&tree, *root)),
+        prolog => document.insert(CHILDREN.to_string(), ordered_content(&tree, prolog, false)),
+    };
+    Ok(Value::Object(document))
+}
+
+/// Key of the ordered content of an element, see the module documentation.
+const CHILDREN: &str = "#children";
+const TEXT: &str = "#text";
+const COMMENT: &str = "#comment";
+const DOCTYPE: &str = "#doctype";
+
+/// Maps an element to its value in the document tree, see the module documentation.
+#[cfg(feature = "xml")]
+pub(crate) fn element_value(tree: &Tree, node: usize) -> Value {
+    let element = &tree.nodes[node];
+    let mut map = Map::new();
+    for (name, value) in &element.attributes {
+        map.insert(format!("@{}", name), Value::String(value.clone()));
+    }
+    let mixed = !element.children.is_empty() && !element.text.trim().is_empty();
+    if mixed || needs_order(tree, node) {
+        map.insert(CHILDREN.to_string(), ordered_content(tree, &element.content, mixed));
+        return Value::Object(map);
+    }
+    for &child in &element.children {
+        insert_child(&mut map, tree.nodes[child].name.clone(), element_value(tree, child));
+    }
+    // Text next to child elements is whitespace here, which is formatting
+    let text = match element.children.is_empty() {
+        true => element.text.as_str(),
+        false => "",
+    };
+    match (map.is_empty(), text.is_empty()) {
+        (true, true) => Value::Null,
+        (true, false) => Value::String(text.to_string()),
+        (false, true) => Value::Object(map),
+        (false, false) => {
+            map.insert(TEXT.to_string(), Value::String(text.to_string()));
+            Value::Object(map)
+        }
+    }
+}
+
+/// Returns whether grouping the children of `node` by name would lose their order or
+/// drop comments and processing instructions.
+#[cfg(feature = "xml")]
+fn needs_order(tree: &Tree, node: usize) -> bool {
+    let element = &tree.nodes[node];
+    let mut seen: Vec<&str> = Vec::new();
+    for content in &element.content {
+        match content {
+            Content::Element(child) => {
+                let name = tree.nodes[*child].name.as_str();
+                if seen.last() != Some(&name) && seen.contains(&name) {
+                    return true;
+                }
+                seen.push(name);
+            }
+            Content::Text(_) => {}
+            Content::Comment(_) | Content::Instruction(..) | Content::Doctype(_) => return true,
+        }
+    }
+    false
+}
+
+/// Maps content to the `#children` list; text is kept only in `mixed` content.
+#[cfg(feature = "xml")]
+fn ordered_content(tree: &Tree, content: &[Content], mixed: bool) -> Value {
+    let item = |key: String, value: Value| Value::Object(Map::from_iter([(key, value)]));
+    let items = content.iter().filter_map(|content| match content {
+        Content::Element(child) => Some(item(tree.nodes[*child].name.clone(), element_value(tree, *child))),
+        Content::Text(text) if mixed => Some(item(TEXT.to_string(), Value::String(text.clone()))),
+        Content::Text(_) => None,
+        Content::Comment(comment) => Some(item(COMMENT.to_string(), Value::String(comment.clone()))),
+        Content::Instruction(target, data) => Some(item(format!("?{}", target), Value::String(data.clone()))),
+        Content::Doctype(doctype) => Some(item(DOCTYPE.to_string(), Value::String(doctype.clone()))),
+    });
+    Value::Array(items.collect())
+}
+
+/// Adds a child element, turning repeated names into an array.
+#[cfg(feature = "xml")]
+fn insert_child(map: &mut Map<String, Value>, name: String, value: Value) {
+    match map.get_mut(&name) {
+        Some(Value::Array(items)) => items.push(value),
+        Some(existing) => {
+            let first = existing.take();
+            *existing = Value::Array(vec![first, value]);
+        }
+        None => {
+            map.insert(name, value);
+        }
+    }
+}
+
+#[cfg(not(feature = "xml"))]
+fn xml_to_document(_content: &str) -> ZipContainerResult<Value> {
+    Err(crate::definition_format::feature_disabled("xml"))
+}
+
+fn document_to_xml(document: &Value) -> ZipContainerResult<String> {
+    let root = match document {
+        Value::Object(map) if map.len() == 1 => map.iter().next(),
+        _ => None,
+    };
+    let (name, value) = root.ok_or_else(|| invalid("An XML document needs an object with exactly one key, the root element"))?;
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    if name != CHILDREN {
+        write_element(&mut out, name, value, 0, false)?;
+        return Ok(out);
+    }
+    let items = value.as_array().ok_or_else(|| invalid("#children must be a list"))?;
+    let mut roots = 0;
+    for item in items {
+        let (key, value) = single_key(item)?;
+        match key {
+            TEXT => return Err(invalid("text is not allowed outside the root element")),
+            COMMENT | DOCTYPE => {}
+            _ if key.starts_with('?') => {}
+            _ => roots += 1,
+        }
+        write_item(&mut out, key, value, 0, false)?;
+    }
+    match roots {
+        1 => Ok(out),
+        _ => Err(invalid("An XML document needs exactly one root element")),
+    }
+}
+
+/// Writes `name` with `value`; `inline` content, inside mixed content, is written without
+/// indentation or line breaks.
+fn write_element(out: &mut String, name: &str, value: &Value, depth: usize, inline: bool) -> ZipContainerResult<()> {
+    if let Value::Array(items) = value {
+        for item in items {
+            write_element(out, name, item, depth, inline)?;
+        }
+        return Ok(());
+    }
+    check_name(name, "element")?;
+    let (indent, newline) = match inline {
+        true => (String::new(), ""),
+        false => ("    ".repeat(depth), "\n"),
+    };
+    out.push_str(&indent);
+    out.push('<');
+    out.push_str(name);
+    let map = match value {
+        Value::Object(map) => map,
+        Value::Null => {
+            out.push_str("/>");
+            out.push_str(newline);
+            return Ok(());
+        }
+        scalar => {
+            out.push_str(&format!(">{}</{}>{}", escape_xml(&scalar_text(scalar)), name, newline));
+            return Ok(());
+        }
+    };
+    for (key, attribute) in map.iter().filter(|(key, _)| key.starts_with('@')) {
+        check_name(&key[1..], "attribute")?;
+        out.push_str(&format!(" {}=\"{}\"", &key[1..], escape_xml(&scalar_text(attribute))));
+    }
+    let text = map.get(TEXT).map(scalar_text);
+    let children: Vec<(&String, &Value)> =
+        map.iter().filter(|(key, _)| !key.starts_with('@') && key.as_str() != TEXT).collect();
+    if let Some(ordered) = map.get(CHILDREN) {
+        if children.len() > 1 || text.is_some() {
+            return Err(invalid(&format!("<{}> has #children next to other content", name)));
+        }
+        let items = ordered.as_array().ok_or_else(|| invalid("#children must be a list"))?;
+        let items: Vec<(&str, &Value)> = items.iter().map(single_key).collect::<ZipContainerResult<_>>()?;
+        let mixed = inline || items.iter().any(|(key, _)| *key == TEXT);
+        out.push('>');
+        if !mixed {
+            out.push('\n');
+        }
+        for (key, value) in items {
+            write_item(out, key, value, depth + 1, mixed)?;
+        }
+        if !mixed {
+            out.push_str(&indent);
+        }
+        out.push_str(&format!("</{}>{}", name, newline));
+        return Ok(());
+    }
+    match (children.is_empty(), text) {
+        (true, None) => out.push_str(&format!("/>{}", newline)),
+        (true, Some(text)) => out.push_str(&format!(">{}</{}>{}", escape_xml(&text), name, newline)),
+        (false, text) => {
+            out.push('>');
+            out.push_str(newline);
+            if let Some(text) = text {
+                match inline {
+                    true => out.push_str(&escape_xml(&text)),
+                    false => out.push_str(&format!("{}    {}\n", indent, escape_xml(&text))),
+                }
+            }
+            for (child, child_value) in children {
+                write_element(out, child, child_value, depth + 1, inline)?;
+            }
+            out.push_str(&format!("{}</{}>{}", indent, name, newline));
+        }
+    }
+    Ok(())
+}
+
+/// Writes an item of a `#children` list.
+fn write_item(out: &mut String, key: &str, value: &Value, depth: usize, inline: bool) -> ZipContainerResult<()> {
+    let (indent, newline) = match inline {
+        true => (String::new(), ""),
+        false => ("    ".repeat(depth), "\n"),
+    };
+    match key {
+        TEXT => out.push_str(&escape_xml(&scalar_text(value))),
+        COMMENT => {
+            let comment = scalar_text(value);
+            if comment.contains("--") || comment.ends_with('-') {
+                return Err(invalid(&format!("'{}' is not a valid XML comment", comment)));
+            }
+            out.push_str(&format!("{}<!--{}-->{}", indent, comment, newline));
+        }
+        DOCTYPE => out.push_str(&format!("{}<!DOCTYPE {}>{}", indent, scalar_text(value), newline)),
+        _ if key.starts_with('?') => {
+            let (target, data) = (&key[1..], scalar_text(value));
+            check_name(target, "processing instruction target")?;
+            if target.eq_ignore_ascii_case("xml") || data.contains("?>") {
+                return Err(invalid(&format!("'{}' is not a valid processing instruction", key)));
+            }
+            match data.is_empty() {
+                true => out.push_str(&format!("{}<?{}?>{}", indent, target, newline)),
+                false => out.push_str(&format!("{}<?{} {}?>{}", indent, target, data, newline)),
+            }
+        }
+        _ => write_element(out, key, value, depth, inline)?,
+    }
+    Ok(())
+}
+
+/// Returns the key and value of an item of a `#children` list.
+fn single_key(item: &Value) -> ZipContainerResult<(&str, &Value)> {
+    match item {
+        Value::Object(map) if map.len() == 1 => Ok(map.iter().next().map(|(key, value)| (key.as_str(), value)).unwrap_or_default()),
+        _ => Err(invalid("#children items must be objects with a single key")),
+    }
+}
+
+/// Fails unless `name` matches the `Name` production of XML 1.0.
+fn check_name(name: &str, what: &str) -> ZipContainerResult<()> {
+    let mut chars = name.chars();
+    let valid = chars.next().is_some_and(is_name_start_char) && chars.all(is_name_char);
+    match valid {
+        true => Ok(()),
+        false => Err(invalid(&format!("'{}' is not a valid XML {} name", name, what))),
+    }
+}
+
+fn is_name_start_char(c: char) -> bool {
+    matches!(c,
+        ':' | 'A'..='Z' | '_' | 'a'..='z' | '\u{C0}'..='\u{D6}' | '\u{D8}'..='\u{F6}' | '\u{F8}'..='\u{2FF}'
+        | '\u{370}'..='\u{37D}' | '\u{37F}'..='\u{1FFF}' | '\u{200C}'..='\u{200D}' | '\u{2070}'..='\u{218F}'
+        | '\u{2C00}'..='\u{2FEF}' | '\u{3001}'..='\u{D7FF}' | '\u{F900}'..='\u{FDCF}' | '\u{FDF0}'..='\u{FFFD}'
+        | '\u{10000}'..='\u{EFFFF}')
+}
+
+fn is_name_char(c: char) -> bool {
+    is_name_start_char(c)
+        || matches!(c, '-' | '.' | '0'..='9' | '\u{B7}' | '\u{300}'..='\u{36F}' | '\u{203F}'..='\u{2040}')
+}
+
+fn invalid(message: &str) -> ZipContainerError {
+    ZipContainerError::InvalidData {
+        module_path: function_path!(),
+        message: message.to_string(),
+    }
+}
+
+fn scalar_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+/// A step of the path to a value in a document tree.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/// Finds the byte offset of the innermost key of `path` in a JSON, YAML or TOML source by
+/// searching each key, in order, where it is followed by `:`, `=`, `.` or `]`.
+pub(crate) fn locate_key(content: &str, path: &[Segment]) -> Option<usize> {
+    let mut cursor = 0;
+    let mut found = None;
+    for segment in path {
+        let Segment::Key(key) = segment else { continue };
+        match find_key(content, cursor, key) {
+            Some(offset) => {
+                cursor = offset + key.len();
+                found = Some(offset);
+            }
+            None => break,
+        }
+    }
+    found
+}
+
+fn find_key(content: &str, from: usize, key: &str) -> Option<usize> {
+    if key.is_empty() {
+        return None;
+    }
+    let is_word = |c: char| c.is_alphanumeric() || c == '_' || c == '-';
+    content[from..].match_indices(key).map(|(offset, _)| from + offset).find(|&offset| {
+        let before = content[..offset].chars().next_back();
+        if before.is_some_and(is_word) {
+            return false;
+        }
+        let after = content[offset + key.len()..].trim_start_matches(['"', '\'']);
+        matches!(after.trim_start_matches([' ', '\t']).chars().next(), Some(':' | '=' | '.' | ']'))
+    })
+}
@@ -0,0 +1,126 @@
+// src/encoding.rs
+//! Decoding of definition files that are not UTF-8.
+//!
+//! The encoding is taken, in this order, from a byte order mark (UTF-8, UTF-16LE/BE), from
+//! UTF-16 `<?` without one, from the `encoding` of an XML declaration or, if the data is
+//! valid UTF-8, UTF-8. Anything else is decoded as ISO-8859-1 (as `windows-1252`, its
+//! superset), which older lighting tools write without declaring it.
+use crate::{function_path, ZipContainerError, ZipContainerResult};
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252};
+
+/// The encoding a text was stored in, so it can be written back the same way.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SourceEncoding {
+    encoding: &'static Encoding,
+    bom: bool,
+}
+
+impl Default for SourceEncoding {
+    fn default() -> Self {
+        SourceEncoding { encoding: UTF_8, bom: false }
+    }
+}
+
+impl SourceEncoding {
+    /// Returns the encoding for a label such as `ISO-8859-1` or `utf-16le`.
+    pub fn for_label(label: &str, bom: bool) -> Option<SourceEncoding> {
+        Encoding::for_label(label.trim().as_bytes()).map(|encoding| SourceEncoding { encoding, bom })
+    }
+
+    /// Returns the canonical name, e.g. `UTF-8`, `UTF-16LE` or `windows-1252`.
+    pub fn name(&self) -> &'static str {
+        self.encoding.name()
+    }
+
+    /// Returns whether the text started with a byte order mark.
+    pub fn has_bom(&self) -> bool {
+        self.bom
+    }
+
+    pub fn is_utf8(&self) -> bool {
+        self.encoding == UTF_8
+    }
+
+    /// Encodes `text` in this encoding, with the byte order mark if the source had one.
+    /// Fails with `InvalidData` for characters the encoding cannot represent.
+    pub fn encode(&self, text: &str) -> ZipContainerResult<Vec<u8>> {
+        let mut bytes = Vec::with_capacity(text.len() + 3);
+        if self.encoding == UTF_16LE || self.encoding == UTF_16BE {
+            let little_endian = self.encoding == UTF_16LE;
+            let units = self.bom.then_some(0xfeff).into_iter().chain(text.encode_utf16());
+            for unit in units {
+                bytes.extend_from_slice(&if little_endian { unit.to_le_bytes() } else { unit.to_be_bytes() });
+            }
+            return Ok(bytes);
+        }
+        if self.encoding == UTF_8 {
+            if self.bom {
+                bytes.extend_from_slice(b"\xef\xbb\xbf");
+            }
+            bytes.extend_from_slice(text.as_bytes());
+            return Ok(bytes);
+        }
+        let (encoded, _, had_errors) = self.encoding.encode(text);
+        if had_errors {
+            let unmappable = text.chars().find(|c| {
+                let mut buffer = [0; 4];
+                self.encoding.encode(c.encode_utf8(&mut buffer)).2
+            });
+            return Err(ZipContainerError::InvalidData {
+                module_path: function_path!(),
+                message: format!("'{}' cannot be encoded in {}", unmappable.unwrap_or_default(), self.name()),
+            });
+        }
+        bytes.extend_from_slice(&encoded);
+        Ok(bytes)
+    }
+}
+
+/// Decodes `bytes` to UTF-8 and returns the encoding they were stored in.
+/// Fails with `InvalidData` if they are malformed for a declared or marked encoding.
+pub fn decode(bytes: &[u8]) -> ZipContainerResult<(String, SourceEncoding)> {
+    let (source, content) = match Encoding::for_bom(bytes) {
+        Some((encoding, bom_length)) => (SourceEncoding { encoding, bom: true }, &bytes[bom_length..]),
+        None => (detect_without_bom(bytes), bytes),
+    };
+    match source.encoding.decode_without_bom_handling_and_without_replacement(content) {
+        Some(text) => Ok((text.into_owned(), source)),
+        None => Err(ZipContainerError::InvalidData {
+            module_path: function_path!(),
+            message: format!("malformed {} data", source.name()),
+        }),
+    }
+}
+
+fn detect_without_bom(bytes: &[u8]) -> SourceEncoding {
+    let encoding = match bytes {
+        [b'<', 0, b'?', 0, ..] => UTF_16LE,
+        [0, b'<', 0, b'?', ..] => UTF_16BE,
+        _ => match xml_declared_encoding(bytes) {
+            Some(encoding) => encoding,
+            None if std::str::from_utf8(bytes).is_ok() => UTF_8,
+            None => WINDOWS_1252,
+        },
+    };
+    SourceEncoding { encoding, bom: false }
+}
+
+/// Returns the encoding named in `<?xml ... encoding="..."?>` at the start of `bytes`.
+/// UTF-16 labels are ignored, a declaration readable as ASCII cannot be UTF-16.
+fn xml_declared_encoding(bytes: &[u8]) -> Option<&'static Encoding> {
+    let declaration = bytes.strip_prefix(b"<?xml")?;
+    let declaration = &declaration[..declaration.windows(2).position(|window| window == b"?>")?];
+    let declaration = std::str::from_utf8(declaration).ok()?;
+    let value = declaration.split_once("encoding")?.1.trim_start().strip_prefix('=')?.trim_start();
+    let quote = value.chars().next().filter(|quote| *quote == '"' || *quote == '\'')?;
+    let label = value[1..].split(quote).next()?;
+    Encoding::for_label(label.as_bytes())
+        .filter(|encoding| *encoding != UTF_16LE && *encoding != UTF_16BE)
+        .map(Encoding::output_encoding)
+}
+
+/// Escapes `&`, `<`, `>` and `"` for XML text and attribute values.
+#[cfg(any(feature = "json", feature = "xml"))]
+pub(crate) fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
@@ -0,0 +1,62 @@
+// src/file_table.rs
+//! The file table of a GLDF definition: `<File id=".." contentType=".." type="..">` elements
+//! under `GeneralDefinitions/Files`, which give the files of the container their IDs and
+//! content types. Requires the `json` and `xml` features.
+use crate::references;
+use crate::{BufFile, Definition, QueryMatch, ZipContainerResult};
+
+/// XPath of the file elements of a GLDF definition.
+pub const GLDF_FILE_TABLE: &str = "//GeneralDefinitions/Files/File";
+
+/// A file of the definition's file table.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FileTableEntry {
+    pub id: String,
+    /// The `contentType`, e.g. `ldc/eulumdat`.
+    pub content_type: Option<String>,
+    /// The `type`: `localFileName` or `url`.
+    pub file_type: Option<String>,
+    /// The file name, path or URL as written in the definition.
+    pub reference: String,
+    /// The archive entry the reference resolves to, if it is in the archive.
+    pub entry: Option<String>,
+}
+
+impl FileTableEntry {
+    /// Returns the archive entry or, for files outside the archive, the reference.
+    pub fn location(&self) -> &str {
+        self.entry.as_deref().unwrap_or(&self.reference)
+    }
+}
+
+/// Reads the file table of `definition` and resolves each file against the archive `entries`
+/// with `directories` as in `check_references`. Files without an ID are skipped.
+pub(crate) fn read(definition: &Definition, entries: &[String], directories: &[String]) -> ZipContainerResult<Vec<FileTableEntry>> {
+    let attribute = |file: &serde_json::Value, name: &str| file.get(name).and_then(|v| v.as_str()).map(String::from);
+    let mut table = Vec::new();
+    for found in definition.query(GLDF_FILE_TABLE)? {
+        let QueryMatch::Tree(file) = found else { continue };
+        let Some(id) = attribute(&file, "@id") else { continue };
+        let reference = attribute(&file, "#text").unwrap_or_default().trim().to_string();
+        let entry = references::resolve(&reference, entries, directories).cloned();
+        table.push(FileTableEntry {
+            id,
+            content_type: attribute(&file, "@contentType"),
+            file_type: attribute(&file, "@type"),
+            reference,
+            entry,
+        });
+    }
+    Ok(table)
+}
+
+/// Sets `file_id` and `content_type` of the `files` listed in `table`; the others keep theirs.
+pub(crate) fn apply(table: &[FileTableEntry], files: &mut [BufFile]) {
+    for file in files {
+        let Some(listed) = table.iter().find(|listed| listed.entry.is_some() && listed.entry == file.name) else { continue };
+        file.file_id = Some(listed.id.clone());
+        if listed.content_type.is_some() {
+            file.content_type = listed.content_type.clone();
+        }
+    }
+}
@@ -0,0 +1,128 @@
+// src/http_cache.rs
+//! On-disk cache for containers downloaded by the native `HttpFileLoader`.
+//!
+//! Every cached URL is stored as two files named after the SHA-256 of the URL:
+//! `<key>.bin` with the response body and `<key>.meta` with the URL and the
+//! `ETag`/`Last-Modified` validators used for revalidation.
+use crate::integrity::sha256_hex;
+use crate::{function_path, io_err, ZipContainerError, ZipContainerResult};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A response body read from the cache together with its validators.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CachedResponse {
+    pub data: Vec<u8>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+/// Cache directory used by `HttpFileLoader` when set in `HttpOptions::cache`.
+///
+/// Cached entries are revalidated with `If-None-Match`/`If-Modified-Since`;
+/// in `offline` mode no request is made and only cached entries are served.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HttpCache {
+    pub dir: PathBuf,
+    pub offline: bool,
+}
+
+impl HttpCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        HttpCache {
+            dir: dir.into(),
+            offline: false,
+        }
+    }
+
+    /// Returns a cache that serves only from disk without touching the network.
+    pub fn offline(dir: impl Into<PathBuf>) -> Self {
+        HttpCache {
+            dir: dir.into(),
+            offline: true,
+        }
+    }
+
+    /// Returns the cache key for `url`, the hex encoded SHA-256 of the URL.
+    pub fn key(url: &str) -> String {
+        sha256_hex(url.as_bytes())
+    }
+
+    fn body_path(&self, url: &str) -> PathBuf {
+        self.dir.join(format!("{}.bin", Self::key(url)))
+    }
+
+    fn meta_path(&self, url: &str) -> PathBuf {
+        self.dir.join(format!("{}.meta", Self::key(url)))
+    }
+
+    /// Returns the cached response for `url`, if any.
+    pub fn lookup(&self, url: &str) -> Option<CachedResponse> {
+        let meta = fs::read_to_string(self.meta_path(url)).ok()?;
+        let mut cached = CachedResponse::default();
+        let mut cached_url = None;
+        for line in meta.lines() {
+            match line.split_once(": ") {
+                Some(("url", value)) => cached_url = Some(value),
+                Some(("etag", value)) => cached.etag = Some(value.to_string()),
+                Some(("last-modified", value)) => cached.last_modified = Some(value.to_string()),
+                _ => {}
+            }
+        }
+        // Guards against (unlikely) key collisions and foreign files in the directory
+        if cached_url != Some(url) {
+            return None;
+        }
+        cached.data = fs::read(self.body_path(url)).ok()?;
+        Some(cached)
+    }
+
+    /// Stores a response body and its validators for `url`.
+    pub fn store(
+        &self,
+        url: &str,
+        data: &[u8],
+        etag: Option<&str>,
+        last_modified: Option<&str>,
+    ) -> ZipContainerResult<()> {
+        io_err!(fs::create_dir_all(&self.dir))?;
+        let mut meta = format!("url: {}\n", url);
+        if let Some(etag) = etag {
+            meta.push_str(&format!("etag: {}\n", etag));
+        }
+        if let Some(last_modified) = last_modified {
+            meta.push_str(&format!("last-modified: {}\n", last_modified));
+        }
+        // Write the body first and the metadata last, so a crash never leaves
+        // metadata pointing at a partially written body.
+        write_atomically(&self.body_path(url), data)?;
+        write_atomically(&self.meta_path(url), meta.as_bytes())
+    }
+
+    /// Removes the cached entry for `url`.
+    pub fn remove(&self, url: &str) -> ZipContainerResult<()> {
+        for path in [self.meta_path(url), self.body_path(url)] {
+            if path.exists() {
+                io_err!(fs::remove_file(path))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the error reported when `url` is requested offline but not cached.
+    pub(crate) fn offline_miss(url: &str) -> ZipContainerError {
+        ZipContainerError::IOError {
+            module_path: function_path!(),
+            source: std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("Not available in the HTTP cache (offline mode): {}", url),
+            ),
+        }
+    }
+}
+
+fn write_atomically(path: &Path, data: &[u8]) -> ZipContainerResult<()> {
+    let temporary = path.with_extension(format!("tmp{}", std::process::id()));
+    io_err!(fs::write(&temporary, data))?;
+    io_err!(fs::rename(&temporary, path))
+}
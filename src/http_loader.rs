@@ -0,0 +1,370 @@
+// src/http_loader.rs
+//! `HttpFileLoader`, the reqwest based loader for `http://` and `https://` URLs.
+//! Only available with the `http` feature.
+use crate::http_options::{sleep, HttpOptions, RetryPolicy};
+use crate::logging::{log_probe, log_request, Stopwatch};
+use crate::progress::cancellable;
+use crate::zip_container_trait::{AsyncFileLoader, FileLoader, ZipContainerFuture};
+use crate::{function_path, reqwest_err, ZipContainerError, ZipContainerResult};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::http_cache::{CachedResponse, HttpCache};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::io_err;
+#[cfg(not(target_arch = "wasm32"))]
+use std::io::Read as StdIoRead;
+
+/// Loads `http://` and `https://` URLs with reqwest, applying `HttpOptions`
+/// (headers, authentication, timeouts, retries, proxy, user agent, body size limit
+/// and, on native targets, the on-disk `HttpCache`).
+#[derive(Clone, Debug, Default)]
+pub struct HttpFileLoader {
+    options: HttpOptions,
+}
+
+// Applies the per-request options; the builder API is shared by the blocking,
+// async and WASM clients of reqwest.
+macro_rules! with_request_options {
+    ($request:expr, $options:expr) => {{
+        let mut request = $request;
+        for (name, value) in $options.request_headers() {
+            request = request.header(name, value);
+        }
+        if let Some(timeout) = $options.timeout {
+            request = request.timeout(timeout);
+        }
+        request
+    }};
+}
+
+// Adds the revalidation headers for a cached response.
+#[cfg(not(target_arch = "wasm32"))]
+macro_rules! with_validators {
+    ($request:expr, $cached:expr) => {{
+        let mut request = $request;
+        if let Some(etag) = &$cached.etag {
+            request = request.header(reqwest::header::IF_NONE_MATCH, etag.as_str());
+        }
+        if let Some(last_modified) = &$cached.last_modified {
+            request = request.header(reqwest::header::IF_MODIFIED_SINCE, last_modified.as_str());
+        }
+        request
+    }};
+}
+
+/// `ETag` and `Last-Modified` of a response.
+#[cfg(not(target_arch = "wasm32"))]
+type Validators = (Option<String>, Option<String>);
+
+#[cfg(not(target_arch = "wasm32"))]
+fn validators_of(headers: &reqwest::header::HeaderMap) -> Validators {
+    let header = |name| {
+        headers
+            .get(name)
+            .and_then(|value: &reqwest::header::HeaderValue| value.to_str().ok())
+            .map(str::to_string)
+    };
+    (header(reqwest::header::ETAG), header(reqwest::header::LAST_MODIFIED))
+}
+
+impl HttpFileLoader {
+    pub fn new(options: HttpOptions) -> Self {
+        HttpFileLoader { options }
+    }
+
+    /// Returns the options applied to every request.
+    pub fn options(&self) -> &HttpOptions {
+        &self.options
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn blocking_client(&self) -> ZipContainerResult<reqwest::blocking::Client> {
+        let mut builder = reqwest::blocking::Client::builder();
+        if let Some(connect_timeout) = self.options.connect_timeout {
+            builder = builder.connect_timeout(connect_timeout);
+        }
+        if let Some(proxy) = &self.options.proxy {
+            builder = builder.proxy(reqwest_err!(reqwest::Proxy::all(proxy))?);
+        }
+        reqwest_err!(builder.build())
+    }
+
+    fn client(&self) -> ZipContainerResult<reqwest::Client> {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let mut builder = reqwest::Client::builder();
+            if let Some(connect_timeout) = self.options.connect_timeout {
+                builder = builder.connect_timeout(connect_timeout);
+            }
+            if let Some(proxy) = &self.options.proxy {
+                builder = builder.proxy(reqwest_err!(reqwest::Proxy::all(proxy))?);
+            }
+            reqwest_err!(builder.build())
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            reqwest_err!(reqwest::Client::builder().build())
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn is_offline(&self) -> bool {
+        self.options.cache.as_ref().is_some_and(|cache| cache.offline)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn lookup_cache(&self, url: &str) -> Option<CachedResponse> {
+        self.options.cache.as_ref().and_then(|cache| cache.lookup(url))
+    }
+
+    // A cache that cannot be written must not fail the download itself
+    #[cfg(not(target_arch = "wasm32"))]
+    fn store_in_cache(&self, url: &str, data: &[u8], (etag, last_modified): Validators) {
+        if let Some(cache) = &self.options.cache {
+            if let Err(e) = cache.store(url, data, etag.as_deref(), last_modified.as_deref()) {
+                log::warn!("Failed to cache {}: {}", url, e);
+            }
+        }
+    }
+
+    fn should_retry(&self, error: &reqwest::Error, attempt: u32) -> bool {
+        if attempt >= self.options.retry.max_retries || error.is_builder() {
+            return false;
+        }
+        match error.status() {
+            Some(status) => RetryPolicy::is_retryable_status(status.as_u16()),
+            // Transport errors such as timeouts or refused connections
+            None => true,
+        }
+    }
+
+    fn check_body_size(&self, size: u64, url: &str) -> ZipContainerResult<()> {
+        if self.options.exceeds_max_body_size(size) {
+            return Err(ZipContainerError::LimitExceeded {
+                module_path: function_path!(),
+                container: url.to_string(),
+                entry: None,
+                message: format!(
+                    "response body of {} bytes exceeds max_body_size of {} bytes",
+                    size,
+                    self.options.max_body_size.unwrap_or_default()
+                ),
+            });
+        }
+        Ok(())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn read_blocking_body(&self, mut response: reqwest::blocking::Response, url: &str) -> ZipContainerResult<Vec<u8>> {
+        let total = response.content_length();
+        self.check_body_size(total.unwrap_or_default(), url)?;
+        let mut buffer = Vec::new();
+        let mut chunk = vec![0u8; 64 * 1024];
+        loop {
+            if let Some(token) = &self.options.cancellation {
+                token.check(url)?;
+            }
+            let read = io_err!(response.read(&mut chunk))?;
+            if read == 0 {
+                break;
+            }
+            buffer.extend_from_slice(&chunk[..read]);
+            // Checked per chunk to detect oversized bodies without a Content-Length
+            self.check_body_size(buffer.len() as u64, url)?;
+            self.report_progress(buffer.len() as u64, total);
+        }
+        Ok(buffer)
+    }
+
+    async fn read_body(&self, response: reqwest::Response, url: &str) -> ZipContainerResult<Vec<u8>> {
+        let total = response.content_length();
+        self.check_body_size(total.unwrap_or_default(), url)?;
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let mut response = response;
+            let mut buffer = Vec::new();
+            let token = self.options.cancellation.as_ref();
+            while let Some(chunk) = reqwest_err!(cancellable(token, response.chunk(), url).await?)? {
+                buffer.extend_from_slice(&chunk);
+                self.check_body_size(buffer.len() as u64, url)?;
+                self.report_progress(buffer.len() as u64, total);
+            }
+            Ok(buffer)
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            let token = self.options.cancellation.as_ref();
+            let bytes = reqwest_err!(cancellable(token, response.bytes(), url).await?)?;
+            self.check_body_size(bytes.len() as u64, url)?;
+            self.report_progress(bytes.len() as u64, total);
+            Ok(bytes.to_vec())
+        }
+    }
+
+    fn report_progress(&self, received: u64, total: Option<u64>) {
+        if let Some(progress) = &self.options.progress {
+            progress.report(received, total);
+        }
+    }
+}
+
+impl HttpFileLoader {
+    /// Requests `path_or_url` with the blocking client, revalidating a cached copy.
+    fn fetch_blocking(&self, path_or_url: &str) -> ZipContainerResult<Vec<u8>> {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            // Synchronous HTTP requests using reqwest::blocking
+            if let Some(token) = &self.options.cancellation {
+                token.check(path_or_url)?;
+            }
+            let cached = self.lookup_cache(path_or_url);
+            if self.is_offline() {
+                return cached.map(|cached| cached.data).ok_or_else(|| HttpCache::offline_miss(path_or_url));
+            }
+            let client = self.blocking_client()?;
+            let mut attempt = 0;
+            loop {
+                let mut request = with_request_options!(client.get(path_or_url), self.options);
+                if let Some(cached) = &cached {
+                    request = with_validators!(request, cached);
+                }
+                let result = request.send().and_then(|response| response.error_for_status());
+                match result {
+                    Ok(response) => {
+                        if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+                            if let Some(cached) = cached {
+                                return Ok(cached.data);
+                            }
+                        }
+                        let validators = validators_of(response.headers());
+                        let data = self.read_blocking_body(response, path_or_url)?;
+                        self.store_in_cache(path_or_url, &data, validators);
+                        return Ok(data);
+                    }
+                    Err(e) if self.should_retry(&e, attempt) => {
+                        std::thread::sleep(self.options.retry.backoff(attempt));
+                        if let Some(token) = &self.options.cancellation {
+                            token.check(path_or_url)?;
+                        }
+                        attempt += 1;
+                    }
+                    Err(e) => return reqwest_err!(Err(e)),
+                }
+            }
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            Err(ZipContainerError::UnsupportedOperation {
+                module_path: function_path!(),
+                message: format!("Synchronous file loading is not supported in WASM: {}", path_or_url),
+            })
+        }
+    }
+
+    /// Checks with a `HEAD` request whether `path_or_url` can be loaded. Servers that do
+    /// not answer `HEAD` are asked with `GET`; offline, only the cache is consulted.
+    fn probe_blocking(&self, path_or_url: &str) -> ZipContainerResult<bool> {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            if let Some(token) = &self.options.cancellation {
+                token.check(path_or_url)?;
+            }
+            if self.is_offline() {
+                return Ok(self.lookup_cache(path_or_url).is_some());
+            }
+            let client = self.blocking_client()?;
+            let response = reqwest_err!(with_request_options!(client.head(path_or_url), self.options).send())?;
+            match response.status() {
+                status if status.is_success() => Ok(true),
+                reqwest::StatusCode::METHOD_NOT_ALLOWED | reqwest::StatusCode::NOT_IMPLEMENTED => {
+                    Ok(self.fetch_blocking(path_or_url).is_ok())
+                }
+                _ => Ok(false),
+            }
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            Err(ZipContainerError::UnsupportedOperation {
+                module_path: function_path!(),
+                message: format!("Synchronous file loading is not supported in WASM: {}", path_or_url),
+            })
+        }
+    }
+
+    /// Requests `url` with the asynchronous client, revalidating a cached copy.
+    async fn fetch(&self, url: &str) -> ZipContainerResult<Vec<u8>> {
+        // Load from URL using asynchronous HTTP client
+        #[cfg(not(target_arch = "wasm32"))]
+        let cached = self.lookup_cache(url);
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.is_offline() {
+            return cached.map(|cached| cached.data).ok_or_else(|| HttpCache::offline_miss(url));
+        }
+        let client = self.client()?;
+        let mut attempt = 0;
+        loop {
+            #[allow(unused_mut)]
+            let mut request = with_request_options!(client.get(url), self.options);
+            #[cfg(not(target_arch = "wasm32"))]
+            if let Some(cached) = &cached {
+                request = with_validators!(request, cached);
+            }
+            let token = self.options.cancellation.as_ref();
+            let result = match cancellable(token, request.send(), url).await? {
+                Ok(response) => response.error_for_status(),
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(response) => {
+                    #[cfg(not(target_arch = "wasm32"))]
+                    {
+                        if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+                            if let Some(cached) = cached {
+                                return Ok(cached.data);
+                            }
+                        }
+                        let validators = validators_of(response.headers());
+                        let data = self.read_body(response, url).await?;
+                        self.store_in_cache(url, &data, validators);
+                        return Ok(data);
+                    }
+                    #[cfg(target_arch = "wasm32")]
+                    return self.read_body(response, url).await;
+                }
+                Err(e) if self.should_retry(&e, attempt) => {
+                    cancellable(token, sleep(self.options.retry.backoff(attempt)), url).await?;
+                    attempt += 1;
+                }
+                Err(e) => return reqwest_err!(Err(e)),
+            }
+        }
+    }
+}
+
+impl FileLoader for HttpFileLoader {
+    fn load(&self, path_or_url: &str) -> ZipContainerResult<Vec<u8>> {
+        let stopwatch = Stopwatch::start();
+        let result = self.fetch_blocking(path_or_url);
+        log_request(path_or_url, &result, stopwatch);
+        result
+    }
+
+    fn exists(&self, path_or_url: &str) -> bool {
+        let stopwatch = Stopwatch::start();
+        let result = self.probe_blocking(path_or_url);
+        log_probe(path_or_url, &result, stopwatch);
+        result.unwrap_or(false)
+    }
+}
+
+impl AsyncFileLoader for HttpFileLoader {
+    fn load_async(&self, path_or_url: &str) -> ZipContainerFuture<Vec<u8>> {
+        let loader = self.clone();
+        let url = path_or_url.to_string(); // Own the data
+        Box::pin(async move {
+            let stopwatch = Stopwatch::start();
+            let result = loader.fetch(&url).await;
+            log_request(&url, &result, stopwatch);
+            result
+        })
+    }
+}
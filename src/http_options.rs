@@ -0,0 +1,153 @@
+// src/http_options.rs
+//! Configuration shared by the HTTP code paths: `HttpFileLoader` (blocking and
+//! async reqwest) and the `fetch` based loading in the WASM bindings.
+#[cfg(not(target_arch = "wasm32"))]
+use crate::http_cache::HttpCache;
+use crate::progress::{CancellationToken, ProgressHandler};
+use std::time::Duration;
+
+/// Credentials sent with every request.
+#[derive(Clone, Debug, PartialEq)]
+pub enum HttpAuth {
+    /// `Authorization: Bearer <token>`
+    Bearer(String),
+    /// `Authorization: Basic <base64(username:password)>`
+    Basic {
+        username: String,
+        password: Option<String>,
+    },
+}
+
+impl HttpAuth {
+    /// Returns the value of the `Authorization` header for these credentials.
+    pub fn header_value(&self) -> String {
+        use base64::Engine;
+        match self {
+            HttpAuth::Bearer(token) => format!("Bearer {}", token),
+            HttpAuth::Basic { username, password } => {
+                let credentials = format!("{}:{}", username, password.as_deref().unwrap_or(""));
+                format!(
+                    "Basic {}",
+                    base64::engine::general_purpose::STANDARD.encode(credentials)
+                )
+            }
+        }
+    }
+}
+
+/// Retry with exponential backoff for transport errors and retryable status codes
+/// (408, 425, 429, 500, 502, 503, 504).
+#[derive(Clone, Debug, PartialEq)]
+pub struct RetryPolicy {
+    /// Number of retries after the first attempt; `0` disables retrying.
+    pub max_retries: u32,
+    /// Delay before the first retry; doubled for every further retry.
+    pub initial_backoff: Duration,
+    /// Upper bound for the delay between two attempts.
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 0,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(10),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Returns the delay before retry number `attempt` (starting at 0).
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt);
+        self.initial_backoff
+            .checked_mul(factor)
+            .unwrap_or(self.max_backoff)
+            .min(self.max_backoff)
+    }
+
+    /// Returns `true` if a response with `status` is worth retrying.
+    pub fn is_retryable_status(status: u16) -> bool {
+        matches!(status, 408 | 425 | 429 | 500 | 502 | 503 | 504)
+    }
+}
+
+/// Options applied to every HTTP request made by the loaders.
+///
+/// `proxy` and `connect_timeout` are only honoured by the native client; browsers
+/// manage proxies themselves and do not allow overriding the user agent, so
+/// `user_agent` is best effort on WASM.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HttpOptions {
+    /// Additional request headers.
+    pub headers: Vec<(String, String)>,
+    /// Credentials for the `Authorization` header.
+    pub auth: Option<HttpAuth>,
+    /// Timeout for the whole request including the body.
+    pub timeout: Option<Duration>,
+    /// Timeout for establishing the connection (native only).
+    pub connect_timeout: Option<Duration>,
+    /// Retry policy for failed requests.
+    pub retry: RetryPolicy,
+    /// Proxy URL used for all requests, e.g. `http://proxy:3128` (native only).
+    pub proxy: Option<String>,
+    /// Value of the `User-Agent` header.
+    pub user_agent: Option<String>,
+    /// Maximum accepted size of a response body in bytes.
+    pub max_body_size: Option<u64>,
+    /// Called with the number of received bytes after every chunk of the body.
+    pub progress: Option<ProgressHandler>,
+    /// Aborts requests in flight and pending retries when cancelled.
+    pub cancellation: Option<CancellationToken>,
+    /// On-disk cache with revalidation and an optional offline mode (native only).
+    #[cfg(not(target_arch = "wasm32"))]
+    pub cache: Option<HttpCache>,
+}
+
+impl HttpOptions {
+    /// Returns all headers to send, including `Authorization` and `User-Agent`.
+    pub fn request_headers(&self) -> Vec<(String, String)> {
+        let mut headers = self.headers.clone();
+        if let Some(auth) = &self.auth {
+            headers.push(("Authorization".to_string(), auth.header_value()));
+        }
+        if let Some(user_agent) = &self.user_agent {
+            headers.push(("User-Agent".to_string(), user_agent.clone()));
+        }
+        headers
+    }
+
+    /// Returns `true` if `size` bytes exceed `max_body_size`.
+    pub fn exceeds_max_body_size(&self, size: u64) -> bool {
+        self.max_body_size.is_some_and(|max| size > max)
+    }
+}
+
+/// Waits for `duration` between two attempts.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) async fn sleep(duration: Duration) {
+    tokio::time::sleep(duration).await;
+}
+
+/// Waits for `duration` between two attempts using the global `setTimeout`.
+#[cfg(target_arch = "wasm32")]
+pub(crate) async fn sleep(duration: Duration) {
+    use wasm_bindgen::{JsCast, JsValue};
+    let promise = js_sys::Promise::new(&mut |resolve, _reject| {
+        let global = js_sys::global();
+        let set_timeout = js_sys::Reflect::get(&global, &JsValue::from_str("setTimeout"))
+            .ok()
+            .and_then(|f| f.dyn_into::<js_sys::Function>().ok());
+        match set_timeout {
+            Some(set_timeout) => {
+                let millis = JsValue::from_f64(duration.as_millis() as f64);
+                let _ = set_timeout.call2(&global, &resolve, &millis);
+            }
+            None => {
+                let _ = resolve.call0(&JsValue::UNDEFINED);
+            }
+        }
+    });
+    let _ = wasm_bindgen_futures::JsFuture::from(promise).await;
+}
@@ -0,0 +1,81 @@
+// src/integrity.rs
+//! Verification of downloaded containers against a pinned SHA-256 digest.
+use crate::{function_path, ZipContainerError, ZipContainerResult};
+use base64::Engine;
+use sha2::{Digest, Sha256};
+
+/// Expected SHA-256 digest and, optionally, size of a resource.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExpectedIntegrity {
+    /// Lowercase hex encoded SHA-256 digest.
+    pub sha256: String,
+    /// Expected size in bytes.
+    pub size: Option<u64>,
+}
+
+impl ExpectedIntegrity {
+    /// Creates the expectation from a digest given either as 64 hex characters
+    /// or in Subresource Integrity form (`sha256-<base64>`).
+    pub fn sha256(digest: &str) -> ZipContainerResult<Self> {
+        let digest = digest.trim();
+        let sha256 = match digest.strip_prefix("sha256-") {
+            Some(encoded) => {
+                let bytes = base64::engine::general_purpose::STANDARD
+                    .decode(encoded)
+                    .map_err(|e| ZipContainerError::InvalidData {
+                        module_path: function_path!(),
+                        message: format!("Invalid SRI digest '{}': {}", digest, e),
+                    })?;
+                hex(&bytes)
+            }
+            None => digest.to_ascii_lowercase(),
+        };
+        if sha256.len() != 64 || !sha256.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(ZipContainerError::InvalidData {
+                module_path: function_path!(),
+                message: format!("Not a SHA-256 digest: {}", digest),
+            });
+        }
+        Ok(ExpectedIntegrity { sha256, size: None })
+    }
+
+    /// Additionally expects the resource to be exactly `size` bytes long.
+    pub fn with_size(mut self, size: u64) -> Self {
+        self.size = Some(size);
+        self
+    }
+
+    /// Checks `data` loaded from `location` against the expectation and returns
+    /// `ZipContainerError::IntegrityMismatch` if it differs.
+    pub fn verify(&self, data: &[u8], location: &str) -> ZipContainerResult<()> {
+        if let Some(size) = self.size {
+            if data.len() as u64 != size {
+                return Err(ZipContainerError::IntegrityMismatch {
+                    module_path: function_path!(),
+                    location: location.to_string(),
+                    expected: format!("{} bytes", size),
+                    actual: format!("{} bytes", data.len()),
+                });
+            }
+        }
+        let actual = sha256_hex(data);
+        if actual != self.sha256 {
+            return Err(ZipContainerError::IntegrityMismatch {
+                module_path: function_path!(),
+                location: location.to_string(),
+                expected: format!("sha256:{}", self.sha256),
+                actual: format!("sha256:{}", actual),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Returns the lowercase hex encoded SHA-256 digest of `data`.
+pub fn sha256_hex(data: &[u8]) -> String {
+    hex(&Sha256::digest(data))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
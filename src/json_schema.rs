@@ -0,0 +1,280 @@
+// src/json_schema.rs
+//! The JSON Schema subset used by `validation`, see there for the supported keywords.
+//! Keywords of `UNSUPPORTED` are reported rather than ignored.
+use crate::document::Segment;
+use regex::Regex;
+use serde_json::{Map, Value};
+
+/// Applicator and validation keywords this validator does not implement.
+const UNSUPPORTED: [&str; 11] = [
+    "additionalItems",
+    "unevaluatedItems",
+    "unevaluatedProperties",
+    "dependencies",
+    "dependentRequired",
+    "dependentSchemas",
+    "propertyNames",
+    "minContains",
+    "maxContains",
+    "$dynamicRef",
+    "$recursiveRef",
+];
+
+/// Validates `instance` against `schema` and returns the path and message of each problem.
+pub(crate) fn validate(schema: &Value, instance: &Value) -> Vec<(Vec<Segment>, String)> {
+    let mut validator = Validator { root: schema, issues: Vec::new(), references: Vec::new() };
+    validator.validate(schema, instance, &mut Vec::new());
+    validator.issues
+}
+
+struct Validator<'a> {
+    root: &'a Value,
+    issues: Vec<(Vec<Segment>, String)>,
+    /// The `$ref`s being followed and the instance each applies to, to detect cycles.
+    references: Vec<(&'a str, *const Value)>,
+}
+
+impl<'a> Validator<'a> {
+    fn is_valid(&self, schema: &'a Value, instance: &Value) -> bool {
+        let mut validator = Validator { root: self.root, issues: Vec::new(), references: self.references.clone() };
+        validator.validate(schema, instance, &mut Vec::new());
+        validator.issues.is_empty()
+    }
+
+    fn issue(&mut self, path: &[Segment], message: String) {
+        self.issues.push((path.to_vec(), message));
+    }
+
+    fn validate(&mut self, schema: &'a Value, instance: &Value, path: &mut Vec<Segment>) {
+        let schema = match schema {
+            Value::Object(schema) => schema,
+            Value::Bool(false) => return self.issue(path, "no value is allowed here".to_string()),
+            _ => return,
+        };
+        for keyword in UNSUPPORTED.iter().filter(|keyword| schema.contains_key(**keyword)) {
+            self.issue(path, format!("the schema keyword '{}' is not supported", keyword));
+        }
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            let active = (reference, instance as *const Value);
+            match self.resolve(reference) {
+                // Following it again for the same value would never end
+                Some(_) if self.references.contains(&active) => {
+                    self.issue(path, format!("$ref '{}' refers back to itself for the same value", reference))
+                }
+                Some(target) => {
+                    self.references.push(active);
+                    self.validate(target, instance, path);
+                    self.references.pop();
+                }
+                None => self.issue(path, format!("cannot resolve $ref '{}', only local references are supported", reference)),
+            }
+        }
+        if let Some(types) = schema.get("type") {
+            let types: Vec<&str> = match types {
+                Value::String(name) => vec![name.as_str()],
+                Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+                _ => Vec::new(),
+            };
+            if !types.is_empty() && !types.iter().any(|name| type_matches(name, instance)) {
+                self.issue(path, format!("expected {}, found {}", types.join(" or "), type_name(instance)));
+            }
+        }
+        if let Some(Value::Array(allowed)) = schema.get("enum") {
+            if !allowed.contains(instance) {
+                self.issue(path, format!("{} is not one of {}", instance, Value::Array(allowed.clone())));
+            }
+        }
+        if let Some(constant) = schema.get("const") {
+            if constant != instance {
+                self.issue(path, format!("{} must be {}", instance, constant));
+            }
+        }
+        match instance {
+            Value::Number(number) => self.validate_number(schema, number.as_f64().unwrap_or_default(), path),
+            Value::String(string) => self.validate_string(schema, string, path),
+            Value::Array(items) => self.validate_array(schema, items, path),
+            Value::Object(object) => self.validate_object(schema, object, path),
+            _ => {}
+        }
+        if let Some(Value::Array(schemas)) = schema.get("allOf") {
+            for sub_schema in schemas {
+                self.validate(sub_schema, instance, path);
+            }
+        }
+        if let Some(Value::Array(schemas)) = schema.get("anyOf") {
+            if !schemas.iter().any(|sub_schema| self.is_valid(sub_schema, instance)) {
+                self.issue(path, "value does not match any schema of anyOf".to_string());
+            }
+        }
+        if let Some(Value::Array(schemas)) = schema.get("oneOf") {
+            let matches = schemas.iter().filter(|sub_schema| self.is_valid(sub_schema, instance)).count();
+            if matches != 1 {
+                self.issue(path, format!("value must match exactly one schema of oneOf, but matches {}", matches));
+            }
+        }
+        if let Some(sub_schema) = schema.get("not") {
+            if self.is_valid(sub_schema, instance) {
+                self.issue(path, "value must not match the schema of not".to_string());
+            }
+        }
+        if let Some(condition) = schema.get("if") {
+            let branch = if self.is_valid(condition, instance) { "then" } else { "else" };
+            if let Some(sub_schema) = schema.get(branch) {
+                self.validate(sub_schema, instance, path);
+            }
+        }
+    }
+
+    fn validate_number(&mut self, schema: &Map<String, Value>, value: f64, path: &[Segment]) {
+        let limit = |keyword: &str| schema.get(keyword).and_then(Value::as_f64);
+        if let Some(minimum) = limit("minimum").filter(|&minimum| value < minimum) {
+            self.issue(path, format!("{} is less than the minimum of {}", value, minimum));
+        }
+        if let Some(maximum) = limit("maximum").filter(|&maximum| value > maximum) {
+            self.issue(path, format!("{} is greater than the maximum of {}", value, maximum));
+        }
+        if let Some(minimum) = limit("exclusiveMinimum").filter(|&minimum| value <= minimum) {
+            self.issue(path, format!("{} must be greater than {}", value, minimum));
+        }
+        if let Some(maximum) = limit("exclusiveMaximum").filter(|&maximum| value >= maximum) {
+            self.issue(path, format!("{} must be less than {}", value, maximum));
+        }
+        if let Some(divisor) = limit("multipleOf").filter(|&divisor| divisor > 0.0) {
+            let quotient = value / divisor;
+            if (quotient - quotient.round()).abs() > 1e-9 {
+                self.issue(path, format!("{} is not a multiple of {}", value, divisor));
+            }
+        }
+    }
+
+    fn validate_string(&mut self, schema: &Map<String, Value>, value: &str, path: &[Segment]) {
+        let length = value.chars().count() as u64;
+        if let Some(minimum) = schema.get("minLength").and_then(Value::as_u64).filter(|&minimum| length < minimum) {
+            self.issue(path, format!("'{}' is shorter than {} characters", value, minimum));
+        }
+        if let Some(maximum) = schema.get("maxLength").and_then(Value::as_u64).filter(|&maximum| length > maximum) {
+            self.issue(path, format!("'{}' is longer than {} characters", value, maximum));
+        }
+        if let Some(pattern) = schema.get("pattern").and_then(Value::as_str) {
+            match Regex::new(pattern) {
+                Ok(regex) if !regex.is_match(value) => {
+                    self.issue(path, format!("'{}' does not match the pattern '{}'", value, pattern))
+                }
+                Ok(_) => {}
+                Err(e) => self.issue(path, format!("invalid pattern '{}': {}", pattern, e)),
+            }
+        }
+    }
+
+    fn validate_array(&mut self, schema: &'a Map<String, Value>, items: &[Value], path: &mut Vec<Segment>) {
+        let tuple = match (schema.get("prefixItems"), schema.get("items")) {
+            (Some(Value::Array(prefix)), _) | (None, Some(Value::Array(prefix))) => prefix.as_slice(),
+            _ => &[],
+        };
+        let rest = schema.get("items").filter(|items| !items.is_array());
+        for (index, item) in items.iter().enumerate() {
+            let item_schema = match tuple.get(index) {
+                Some(item_schema) => item_schema,
+                None => match rest {
+                    Some(item_schema) => item_schema,
+                    None => continue,
+                },
+            };
+            path.push(Segment::Index(index));
+            self.validate(item_schema, item, path);
+            path.pop();
+        }
+        let count = items.len() as u64;
+        if let Some(minimum) = schema.get("minItems").and_then(Value::as_u64).filter(|&minimum| count < minimum) {
+            self.issue(path, format!("expected at least {} items, found {}", minimum, count));
+        }
+        if let Some(maximum) = schema.get("maxItems").and_then(Value::as_u64).filter(|&maximum| count > maximum) {
+            self.issue(path, format!("expected at most {} items, found {}", maximum, count));
+        }
+        if schema.get("uniqueItems") == Some(&Value::Bool(true)) {
+            let duplicate = items.iter().enumerate().any(|(index, item)| items[..index].contains(item));
+            if duplicate {
+                self.issue(path, "items must be unique".to_string());
+            }
+        }
+        if let Some(contains) = schema.get("contains") {
+            if !items.iter().any(|item| self.is_valid(contains, item)) {
+                self.issue(path, "no item matches the schema of contains".to_string());
+            }
+        }
+    }
+
+    fn validate_object(&mut self, schema: &'a Map<String, Value>, object: &Map<String, Value>, path: &mut Vec<Segment>) {
+        if let Some(Value::Array(required)) = schema.get("required") {
+            for name in required.iter().filter_map(Value::as_str) {
+                if !object.contains_key(name) {
+                    self.issue(path, format!("missing required property '{}'", name));
+                }
+            }
+        }
+        let properties = schema.get("properties").and_then(Value::as_object);
+        let mut patterns: Vec<(Regex, &Value)> = Vec::new();
+        for (pattern, sub_schema) in schema.get("patternProperties").and_then(Value::as_object).into_iter().flatten() {
+            match Regex::new(pattern) {
+                Ok(regex) => patterns.push((regex, sub_schema)),
+                Err(e) => self.issue(path, format!("invalid pattern '{}': {}", pattern, e)),
+            }
+        }
+        for (name, value) in object {
+            path.push(Segment::Key(name.clone()));
+            let mut known = false;
+            if let Some(sub_schema) = properties.and_then(|properties| properties.get(name)) {
+                known = true;
+                self.validate(sub_schema, value, path);
+            }
+            for (regex, sub_schema) in &patterns {
+                if regex.is_match(name) {
+                    known = true;
+                    self.validate(sub_schema, value, path);
+                }
+            }
+            match schema.get("additionalProperties") {
+                Some(Value::Bool(false)) if !known => {
+                    self.issue(path, format!("property '{}' is not allowed", name))
+                }
+                Some(additional @ Value::Object(_)) if !known => self.validate(additional, value, path),
+                _ => {}
+            }
+            path.pop();
+        }
+        let count = object.len() as u64;
+        if let Some(minimum) = schema.get("minProperties").and_then(Value::as_u64).filter(|&minimum| count < minimum) {
+            self.issue(path, format!("expected at least {} properties, found {}", minimum, count));
+        }
+        if let Some(maximum) = schema.get("maxProperties").and_then(Value::as_u64).filter(|&maximum| count > maximum) {
+            self.issue(path, format!("expected at most {} properties, found {}", maximum, count));
+        }
+    }
+
+    /// Resolves `#` and `#/json/pointer` references within the schema.
+    fn resolve(&self, reference: &str) -> Option<&'a Value> {
+        match reference.strip_prefix('#')? {
+            "" => Some(self.root),
+            pointer => self.root.pointer(pointer),
+        }
+    }
+}
+
+fn type_matches(name: &str, value: &Value) -> bool {
+    match name {
+        "integer" => value.as_i64().is_some() || value.as_u64().is_some() || value.as_f64().is_some_and(|v| v.fract() == 0.0),
+        "number" => value.is_number(),
+        other => type_name(value) == other,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
@@ -0,0 +1,430 @@
+// src/jsonpath.rs
+//! The JSONPath subset used by `query`, see there for the supported syntax.
+use crate::document::Segment as PathSegment;
+use crate::query::QueryMatch;
+use crate::{function_path, ZipContainerError, ZipContainerResult};
+use serde_json::Value;
+
+/// Runs `expression` against a document tree.
+pub(crate) fn query(document: &Value, expression: &str) -> ZipContainerResult<Vec<QueryMatch>> {
+    let segments = Parser::new(expression).parse()?;
+    Ok(select(document, document, &segments).into_iter().map(QueryMatch::from_value).collect())
+}
+
+/// Runs `expression` against a document tree and returns the paths of the matches.
+pub(crate) fn query_paths(document: &Value, expression: &str) -> ZipContainerResult<Vec<Vec<PathSegment>>> {
+    let segments = Parser::new(expression).parse()?;
+    let mut paths = Vec::new();
+    collect_paths(document, &mut Vec::new(), &mut paths);
+    let selected = select(document, document, &segments);
+    Ok(selected
+        .into_iter()
+        .filter_map(|value| paths.iter().find(|(node, _)| std::ptr::eq(*node, value)).map(|(_, path)| path.clone()))
+        .collect())
+}
+
+fn collect_paths<'v>(node: &'v Value, path: &mut Vec<PathSegment>, paths: &mut Vec<(&'v Value, Vec<PathSegment>)>) {
+    paths.push((node, path.clone()));
+    match node {
+        Value::Array(items) => {
+            for (index, item) in items.iter().enumerate() {
+                path.push(PathSegment::Index(index));
+                collect_paths(item, path, paths);
+                path.pop();
+            }
+        }
+        Value::Object(members) => {
+            for (key, member) in members {
+                path.push(PathSegment::Key(key.clone()));
+                collect_paths(member, path, paths);
+                path.pop();
+            }
+        }
+        _ => {}
+    }
+}
+
+#[derive(Debug)]
+struct Segment {
+    descendant: bool,
+    selectors: Vec<Selector>,
+}
+
+#[derive(Debug)]
+enum Selector {
+    Name(String),
+    Index(i64),
+    Wildcard,
+    Slice(Option<i64>, Option<i64>, Option<i64>),
+    Filter(Filter),
+}
+
+#[derive(Debug)]
+enum Filter {
+    Or(Vec<Filter>),
+    And(Vec<Filter>),
+    Not(Box<Filter>),
+    Compare(Operand, &'static str, Operand),
+    Exists(Operand),
+}
+
+#[derive(Debug)]
+enum Operand {
+    Literal(Value),
+    /// A path from the current node (`@`) or from the root (`$`).
+    Path { root: bool, segments: Vec<Segment> },
+}
+
+struct Parser<'a> {
+    expression: &'a str,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(expression: &'a str) -> Parser<'a> {
+        Parser { expression, position: 0 }
+    }
+
+    fn parse(mut self) -> ZipContainerResult<Vec<Segment>> {
+        if !self.eat("$") {
+            return Err(self.error("expected '$'"));
+        }
+        let segments = self.segments()?;
+        self.skip_whitespace();
+        if self.position < self.expression.len() {
+            return Err(self.error("unexpected input"));
+        }
+        Ok(segments)
+    }
+
+    fn error(&self, message: &str) -> ZipContainerError {
+        ZipContainerError::InvalidData {
+            module_path: function_path!(),
+            message: format!("Invalid JSONPath '{}': {} at position {}", self.expression, message, self.position),
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.expression[self.position..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.position = self.expression.len() - self.rest().trim_start().len();
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        if self.rest().starts_with(token) {
+            self.position += token.len();
+            return true;
+        }
+        false
+    }
+
+    fn segments(&mut self) -> ZipContainerResult<Vec<Segment>> {
+        let mut segments = Vec::new();
+        loop {
+            // Whitespace is not allowed between the segments of a path.
+            let rest = self.rest();
+            if let Some(rest) = rest.strip_prefix("..") {
+                self.position += 2;
+                let selectors = if rest.starts_with('[') {
+                    self.position += 1;
+                    self.bracket()?
+                } else {
+                    vec![self.member()?]
+                };
+                segments.push(Segment { descendant: true, selectors });
+            } else if rest.starts_with('.') {
+                self.position += 1;
+                segments.push(Segment { descendant: false, selectors: vec![self.member()?] });
+            } else if rest.starts_with('[') {
+                self.position += 1;
+                segments.push(Segment { descendant: false, selectors: self.bracket()? });
+            } else {
+                return Ok(segments);
+            }
+        }
+    }
+
+    /// Parses the `*` or name after `.` or `..`.
+    fn member(&mut self) -> ZipContainerResult<Selector> {
+        let rest = self.rest();
+        if rest.starts_with('*') {
+            self.position += 1;
+            return Ok(Selector::Wildcard);
+        }
+        let length = rest.find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-' || c == '@')).unwrap_or(rest.len());
+        if length == 0 || rest.starts_with(|c: char| c.is_ascii_digit() || c == '-') {
+            return Err(self.error("expected a member name"));
+        }
+        self.position += length;
+        Ok(Selector::Name(rest[..length].to_string()))
+    }
+
+    /// Parses the selectors of `[...]` after the opening bracket.
+    fn bracket(&mut self) -> ZipContainerResult<Vec<Selector>> {
+        let mut selectors = vec![self.selector()?];
+        while self.eat(",") {
+            selectors.push(self.selector()?);
+        }
+        if !self.eat("]") {
+            return Err(self.error("expected ']'"));
+        }
+        Ok(selectors)
+    }
+
+    fn selector(&mut self) -> ZipContainerResult<Selector> {
+        if self.eat("*") {
+            return Ok(Selector::Wildcard);
+        }
+        if self.eat("?") {
+            return Ok(Selector::Filter(self.or()?));
+        }
+        self.skip_whitespace();
+        if self.rest().starts_with(['\'', '"']) {
+            return Ok(Selector::Name(self.string()?));
+        }
+        let start = self.integer()?;
+        if !self.eat(":") {
+            return start.map(Selector::Index).ok_or_else(|| self.error("expected a selector"));
+        }
+        let end = self.integer()?;
+        let step = if self.eat(":") { self.integer()? } else { None };
+        if step == Some(0) {
+            return Err(self.error("slice step must not be 0"));
+        }
+        Ok(Selector::Slice(start, end, step))
+    }
+
+    fn integer(&mut self) -> ZipContainerResult<Option<i64>> {
+        self.skip_whitespace();
+        let rest = self.rest();
+        let length = rest
+            .char_indices()
+            .find(|&(i, c)| !(c.is_ascii_digit() || (i == 0 && c == '-')))
+            .map_or(rest.len(), |(i, _)| i);
+        if length == 0 {
+            return Ok(None);
+        }
+        let value = rest[..length].parse().map_err(|_| self.error("invalid integer"))?;
+        self.position += length;
+        Ok(Some(value))
+    }
+
+    fn string(&mut self) -> ZipContainerResult<String> {
+        let rest = self.rest();
+        let quote = rest.chars().next().unwrap_or('\'');
+        let mut text = String::new();
+        let mut chars = rest.char_indices().skip(1);
+        while let Some((index, c)) = chars.next() {
+            match c {
+                '\\' => match chars.next() {
+                    Some((_, 'n')) => text.push('\n'),
+                    Some((_, 't')) => text.push('\t'),
+                    Some((_, escaped)) => text.push(escaped),
+                    None => break,
+                },
+                c if c == quote => {
+                    self.position += index + 1;
+                    return Ok(text);
+                }
+                c => text.push(c),
+            }
+        }
+        Err(self.error("unterminated string"))
+    }
+
+    fn or(&mut self) -> ZipContainerResult<Filter> {
+        let mut operands = vec![self.and()?];
+        while self.eat("||") {
+            operands.push(self.and()?);
+        }
+        Ok(if operands.len() == 1 { operands.remove(0) } else { Filter::Or(operands) })
+    }
+
+    fn and(&mut self) -> ZipContainerResult<Filter> {
+        let mut operands = vec![self.basic()?];
+        while self.eat("&&") {
+            operands.push(self.basic()?);
+        }
+        Ok(if operands.len() == 1 { operands.remove(0) } else { Filter::And(operands) })
+    }
+
+    fn basic(&mut self) -> ZipContainerResult<Filter> {
+        if self.eat("!") {
+            return Ok(Filter::Not(Box::new(self.basic()?)));
+        }
+        if self.eat("(") {
+            let inner = self.or()?;
+            if !self.eat(")") {
+                return Err(self.error("expected ')'"));
+            }
+            return Ok(inner);
+        }
+        let left = self.operand()?;
+        for operator in ["==", "!=", "<=", ">=", "<", ">"] {
+            if self.eat(operator) {
+                return Ok(Filter::Compare(left, operator, self.operand()?));
+            }
+        }
+        match left {
+            Operand::Path { .. } => Ok(Filter::Exists(left)),
+            Operand::Literal(_) => Err(self.error("expected a comparison")),
+        }
+    }
+
+    fn operand(&mut self) -> ZipContainerResult<Operand> {
+        self.skip_whitespace();
+        let rest = self.rest();
+        for (root, token) in [(false, "@"), (true, "$")] {
+            if rest.starts_with(token) {
+                self.position += 1;
+                return Ok(Operand::Path { root, segments: self.segments()? });
+            }
+        }
+        if rest.starts_with(['\'', '"']) {
+            return Ok(Operand::Literal(Value::String(self.string()?)));
+        }
+        let word = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(rest.len());
+        if word > 0 && rest[word..].trim_start().starts_with('(') {
+            return Err(self.error(&format!("the function '{}()' is not supported", &rest[..word])));
+        }
+        for (keyword, value) in [("true", Value::Bool(true)), ("false", Value::Bool(false)), ("null", Value::Null)] {
+            if self.eat(keyword) {
+                return Ok(Operand::Literal(value));
+            }
+        }
+        let length = rest
+            .find(|c: char| !(c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E')))
+            .unwrap_or(rest.len());
+        let number = serde_json::from_str::<serde_json::Number>(&rest[..length]).map_err(|_| self.error("expected a value"))?;
+        self.position += length;
+        Ok(Operand::Literal(Value::Number(number)))
+    }
+}
+
+fn select<'v>(root: &'v Value, node: &'v Value, segments: &[Segment]) -> Vec<&'v Value> {
+    let mut nodes = vec![node];
+    for segment in segments {
+        let mut selected = Vec::new();
+        for node in nodes {
+            if segment.descendant {
+                let mut descendants = Vec::new();
+                collect_descendants(node, &mut descendants);
+                for descendant in descendants {
+                    apply(root, descendant, &segment.selectors, &mut selected);
+                }
+            } else {
+                apply(root, node, &segment.selectors, &mut selected);
+            }
+        }
+        nodes = selected;
+    }
+    nodes
+}
+
+fn collect_descendants<'v>(node: &'v Value, descendants: &mut Vec<&'v Value>) {
+    descendants.push(node);
+    for child in children(node) {
+        collect_descendants(child, descendants);
+    }
+}
+
+fn children(node: &Value) -> Box<dyn Iterator<Item = &Value> + '_> {
+    match node {
+        Value::Array(items) => Box::new(items.iter()),
+        Value::Object(members) => Box::new(members.values()),
+        _ => Box::new(std::iter::empty()),
+    }
+}
+
+fn apply<'v>(root: &'v Value, node: &'v Value, selectors: &[Selector], selected: &mut Vec<&'v Value>) {
+    for selector in selectors {
+        match (selector, node) {
+            (Selector::Name(name), Value::Object(members)) => selected.extend(members.get(name)),
+            (Selector::Index(index), Value::Array(items)) => {
+                let index = if *index < 0 { items.len() as i64 + index } else { *index };
+                selected.extend(usize::try_from(index).ok().and_then(|index| items.get(index)));
+            }
+            (Selector::Wildcard, _) => selected.extend(children(node)),
+            (Selector::Slice(start, end, step), Value::Array(items)) => {
+                selected.extend(slice_indices(items.len() as i64, *start, *end, step.unwrap_or(1)).map(|i| &items[i]))
+            }
+            (Selector::Filter(filter), _) => {
+                selected.extend(children(node).filter(|child| matches_filter(root, child, filter)))
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Returns the indices of `[start:end:step]` on an array of `length` items, as in RFC 9535.
+fn slice_indices(length: i64, start: Option<i64>, end: Option<i64>, step: i64) -> impl Iterator<Item = usize> {
+    let normalize = |index: i64| if index < 0 { length + index } else { index };
+    let (lower, upper) = if step > 0 {
+        let lower = start.map_or(0, normalize).clamp(0, length);
+        let upper = end.map_or(length, normalize).clamp(0, length);
+        (lower, upper)
+    } else {
+        let upper = start.map_or(length - 1, normalize).clamp(-1, length - 1);
+        let lower = end.map_or(-1, normalize).clamp(-1, length - 1);
+        (lower, upper)
+    };
+    // A step past the end of the index range ends the slice rather than overflowing.
+    let mut index = Some(if step > 0 { lower } else { upper });
+    std::iter::from_fn(move || {
+        let current = index.filter(|&i| if step > 0 { i < upper } else { i > lower })?;
+        index = current.checked_add(step);
+        Some(current as usize)
+    })
+}
+
+fn matches_filter(root: &Value, node: &Value, filter: &Filter) -> bool {
+    match filter {
+        Filter::Or(operands) => operands.iter().any(|f| matches_filter(root, node, f)),
+        Filter::And(operands) => operands.iter().all(|f| matches_filter(root, node, f)),
+        Filter::Not(inner) => !matches_filter(root, node, inner),
+        Filter::Exists(operand) => !resolve(root, node, operand).is_empty(),
+        Filter::Compare(left, operator, right) => {
+            // Comparisons need singular queries, a path that matches several nodes compares as nothing.
+            let single = |operand| match resolve(root, node, operand).as_slice() {
+                [value] => Some(*value),
+                _ => None,
+            };
+            compare(single(left), operator, single(right))
+        }
+    }
+}
+
+fn resolve<'v>(root: &'v Value, node: &'v Value, operand: &'v Operand) -> Vec<&'v Value> {
+    match operand {
+        Operand::Literal(value) => vec![value],
+        Operand::Path { root: true, segments } => select(root, root, segments),
+        Operand::Path { root: false, segments } => select(root, node, segments),
+    }
+}
+
+fn compare(left: Option<&Value>, operator: &str, right: Option<&Value>) -> bool {
+    let equal = match (left, right) {
+        (Some(Value::Number(l)), Some(Value::Number(r))) => l.as_f64() == r.as_f64(),
+        (l, r) => l == r,
+    };
+    match operator {
+        "==" => equal,
+        "!=" => !equal,
+        "<" => less(left, right),
+        "<=" => less(left, right) || equal,
+        ">" => less(right, left),
+        _ => less(right, left) || equal,
+    }
+}
+
+/// `<` is defined for two numbers or two strings, anything else compares false.
+fn less(left: Option<&Value>, right: Option<&Value>) -> bool {
+    match (left, right) {
+        (Some(Value::Number(l)), Some(Value::Number(r))) => l.as_f64() < r.as_f64(),
+        (Some(Value::String(l)), Some(Value::String(r))) => l < r,
+        _ => false,
+    }
+}
@@ -1,15 +1,85 @@
 // src/lib.rs lib of zip_container
 pub mod container_error;
 pub mod zip_container_trait;
-pub use container_error::{ZipContainerError};
-pub use zip_container_trait::{ZipContainerTrait, UnifiedFileLoader, FileLoader};
+pub mod loader_registry;
+pub mod loaders;
+pub mod data_url;
+#[cfg(feature = "http")]
+pub mod http_options;
+#[cfg(feature = "http")]
+mod http_loader;
+#[cfg(feature = "http")]
+pub mod progress;
+pub mod integrity;
+pub mod logging;
+pub mod detection;
+pub mod encoding;
+pub mod definition_format;
+pub mod profile;
+#[cfg(feature = "json")]
+pub mod document;
+#[cfg(feature = "json")]
+pub mod query;
+#[cfg(feature = "json")]
+mod jsonpath;
+#[cfg(feature = "json")]
+pub mod references;
+#[cfg(all(feature = "json", feature = "xml"))]
+pub mod file_table;
+#[cfg(feature = "json")]
+pub mod localize;
+#[cfg(feature = "xml")]
+pub mod opc;
+#[cfg(feature = "xml")]
+pub mod pointer;
+mod writer;
+#[cfg(feature = "xml")]
+mod xml_tree;
+#[cfg(all(feature = "json", feature = "xml"))]
+mod xpath;
+#[cfg(feature = "validation")]
+pub mod validation;
+#[cfg(feature = "validation")]
+mod xsd;
+#[cfg(feature = "validation")]
+mod json_schema;
+#[cfg(all(feature = "http", not(target_arch = "wasm32")))]
+pub mod http_cache;
+pub use container_error::{Diagnostic, ZipContainerError};
+pub use zip_container_trait::{ZipContainerTrait, IN_MEMORY_SOURCE, UnifiedFileLoader, UnifiedAsyncFileLoader, FileLoader, AsyncFileLoader};
+pub use loader_registry::LoaderRegistry;
+#[cfg(feature = "http")]
+pub use http_options::{HttpAuth, HttpOptions, RetryPolicy};
+#[cfg(feature = "http")]
+pub use progress::{CancellationToken, Progress, ProgressHandler};
+pub use integrity::ExpectedIntegrity;
+pub use logging::{SharedAsyncLogger, SharedLogger};
+pub use detection::DetectionMethod;
+pub use encoding::SourceEncoding;
+pub use definition_format::DefinitionKind;
+pub use profile::{ContainerProfile, ProfileDescriptor};
+#[cfg(feature = "json")]
+pub use query::QueryMatch;
+#[cfg(feature = "json")]
+pub use references::{ReferenceReport, ReferenceRules};
+#[cfg(all(feature = "json", feature = "xml"))]
+pub use file_table::FileTableEntry;
+#[cfg(feature = "json")]
+pub use localize::LocalizedContainer;
+#[cfg(feature = "xml")]
+pub use opc::{ContentTypes, OpcChanges, OpcPackage, OpcPart, Relationship};
+#[cfg(feature = "validation")]
+pub use validation::{Schema, SchemaSource};
 pub type ZipContainerResult<T> = Result<T, ZipContainerError>;
 use serde::{Serialize, Deserialize};
-#[cfg(not(target_arch = "wasm32"))]
-use std::path::Path as StdPath;
+use std::sync::Arc;
+/// Receives the events of a `ZipContainer` it is attached to, see `ZipContainer::with_logger`.
+/// Closures `Fn(&str)` implement this trait.
 pub trait Logger {
     fn log(&self, message: &str);
 }
+/// Receives the events of the asynchronous loads of a `ZipContainer` it is attached to,
+/// see `ZipContainer::async_logger`.
 pub trait AsyncLogger {
     fn log(&self, message: &str) -> impl std::future::Future<Output = ()> + Send;
 }
@@ -27,6 +97,18 @@ impl Default for Definition {
     }
 }
 
+impl Definition {
+    /// Returns the name of the format, e.g. `"XML"`.
+    pub fn format_name(&self) -> &'static str {
+        match self {
+            Definition::XML(_) => "XML",
+            Definition::JSON(_) => "JSON",
+            Definition::YAML(_) => "YAML",
+            Definition::TOML(_) => "TOML",
+        }
+    }
+}
+
 impl AsRef<str> for Definition {
     fn as_ref(&self) -> &str {
         match self {
@@ -45,16 +127,39 @@ pub struct ZipContainer {
     pub definition_content: Option<Definition>,
     pub files: Option<Vec<BufFile>>,
     pub zip_path: Option<String>,
+    /// Loaders used for the container itself and for files that are not part of the ZIP data.
+    pub loader: LoaderRegistry,
+    /// Receives the events of this container in addition to the `log` crate.
+    pub logger: Option<SharedLogger>,
+    /// Receives the events of `load_file_async` and `load_buf_file_async`, awaited in turn.
+    pub async_logger: Option<SharedAsyncLogger>,
+    /// How the format in `definition_content` was determined.
+    pub definition_detection: Option<DetectionMethod>,
+    /// Names tried by `discover_definition` when `definition_path` is not set;
+    /// `None` uses `detection::DEFAULT_DEFINITION_CANDIDATES`.
+    pub definition_candidates: Option<Vec<String>>,
+    /// Encoding of the definition file, recorded when `set_definition` replaces it so that
+    /// `encode_definition` writes the new content back the same way.
+    pub definition_encoding: Option<SourceEncoding>,
+    /// Expected layout of the container, see `validate_layout`.
+    pub profile: Option<Arc<dyn ContainerProfile>>,
+    /// Rules of `check_references`; `None` uses those of `profile` or else
+    /// `ReferenceRules::default()`.
+    #[cfg(feature = "json")]
+    pub reference_rules: Option<ReferenceRules>,
 }
 impl ZipContainer {
     #[cfg(not(target_arch = "wasm32"))]
     pub fn load_zip_data(zip_path: &str) -> Option<Vec<u8>> {
-        let loader = UnifiedFileLoader;
+        ZipContainer::load_zip_data_with(LoaderRegistry::shared(), zip_path)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load_zip_data_with(loader: &LoaderRegistry, zip_path: &str) -> Option<Vec<u8>> {
         match loader.load(zip_path) {
             Ok(data) => Some(data),
             Err(e) => {
-                // Log the error
-                eprintln!("Failed to load ZIP data: {:?}", e);
+                log::error!(target: logging::LOG_TARGET, "{}: failed to load ZIP data: {}", zip_path, e);
                 None
             }
         }
@@ -62,22 +167,178 @@ impl ZipContainer {
 
     #[cfg(not(target_arch = "wasm32"))]
     pub fn new(zip_path: String, definition_path: Option<String>) -> Self {
-        ZipContainer {
-            zip_data: ZipContainer::load_zip_data(&zip_path, /* Option<&str> */),
-            definition_path: definition_path.clone(),
-            definition_content: definition_path.and_then(|path| {
-                match StdPath::new(&path.to_lowercase()).extension().and_then(|ext| ext.to_str()) {
-                    Some("xml") => Some(Definition::XML(String::new())),
-                    Some("json") => Some(Definition::JSON(String::new())),
-                    Some("yaml") => Some(Definition::YAML(String::new())),
-                    Some("toml") => Some(Definition::TOML(String::new())),
-                    _ => None,
-                }
-            }),
-            files: None,
+        ZipContainer::with_loader(zip_path, definition_path, LoaderRegistry::default())
+    }
+
+    /// Like `new`, but loads the container and any non-archive files through `loader`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_loader(zip_path: String, definition_path: Option<String>, loader: LoaderRegistry) -> Self {
+        ZipContainer::open(zip_path, definition_path, loader, None)
+    }
+
+    /// Opens a container of the format described by `profile` through `loader`, like
+    /// `with_loader`: the definition is the profile's and `validate_layout` checks against
+    /// it. Layout problems are reported as warnings, also to `logger` if given, but do not
+    /// prevent opening.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn open_with_profile(
+        zip_path: String,
+        profile: impl ContainerProfile + 'static,
+        loader: LoaderRegistry,
+        logger: Option<SharedLogger>,
+    ) -> Self {
+        let definition_path = Some(profile.definition_path().to_string());
+        let mut container = ZipContainer::open(zip_path, definition_path, loader, logger);
+        container.profile = Some(Arc::new(profile));
+        if let Ok(diagnostics) = container.validate_layout() {
+            for diagnostic in diagnostics {
+                container.log_event(log::Level::Warn, &diagnostic.to_string());
+            }
+        }
+        container
+    }
+
+    /// Like `with_loader`, but reports the events of the container, starting with
+    /// opening the source, to `logger` as well.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_logger(
+        zip_path: String,
+        definition_path: Option<String>,
+        loader: LoaderRegistry,
+        logger: impl Logger + Send + Sync + 'static,
+    ) -> Self {
+        ZipContainer::open(zip_path, definition_path, loader, Some(SharedLogger::new(logger)))
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn open(
+        zip_path: String,
+        definition_path: Option<String>,
+        loader: LoaderRegistry,
+        logger: Option<SharedLogger>,
+    ) -> Self {
+        let zip_data = match loader.load(&zip_path) {
+            Ok(data) => {
+                let message = format!("opened source ({} bytes)", data.len());
+                logging::emit(log::Level::Info, &zip_path, &message, logger.as_ref());
+                Some(data)
+            }
+            Err(e) => {
+                let message = format!("failed to load ZIP data: {}", e);
+                logging::emit(log::Level::Error, &zip_path, &message, logger.as_ref());
+                None
+            }
+        };
+        ZipContainer::assemble(zip_path, zip_data, definition_path, loader, logger)
+    }
+
+    /// Like `with_loader`, but fails instead of leaving `zip_data` unset and checks the
+    /// downloaded container against `expected`, so pinned packages cannot be swapped.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn new_verified(
+        zip_path: String,
+        definition_path: Option<String>,
+        loader: LoaderRegistry,
+        expected: &ExpectedIntegrity,
+    ) -> ZipContainerResult<Self> {
+        let zip_data = loader.load_verified(&zip_path, expected)?;
+        log::info!(target: logging::LOG_TARGET, "{}: opened verified source ({} bytes)", zip_path, zip_data.len());
+        Ok(ZipContainer::assemble(zip_path, Some(zip_data), definition_path, loader, None))
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn assemble(
+        zip_path: String,
+        zip_data: Option<Vec<u8>>,
+        definition_path: Option<String>,
+        loader: LoaderRegistry,
+        logger: Option<SharedLogger>,
+    ) -> Self {
+        let mut container = ZipContainer {
+            zip_data,
+            definition_path,
             zip_path: Some(zip_path),
+            loader,
+            logger,
+            ..Default::default()
+        };
+        container.resolve_definition();
+        container
+    }
+
+    /// Discovers `definition_path` if it is not set and detects the definition format.
+    pub fn resolve_definition(&mut self) -> Option<DetectionMethod> {
+        if self.definition_path.is_none() {
+            self.discover_definition();
         }
+        self.detect_definition()
     }
+
+    /// Sets `definition_path` to a well-known definition file of the archive (see
+    /// `definition_candidates`) or to its only top-level XML/JSON file, and returns it.
+    /// An EPUB or ODF pointer is replaced by the definition it names, if that is present.
+    pub fn discover_definition(&mut self) -> Option<String> {
+        let entries = self.get_file_names().ok()?;
+        let discovered = match &self.definition_candidates {
+            Some(candidates) => detection::discover_definition(&entries, candidates),
+            None => detection::discover_definition(&entries, detection::DEFAULT_DEFINITION_CANDIDATES),
+        };
+        #[cfg(feature = "xml")]
+        let discovered = match discovered {
+            Some(path) if pointer::POINTER_ENTRIES.iter().any(|entry| path.eq_ignore_ascii_case(entry)) => {
+                self.definition_from_pointer().ok().flatten().or(Some(path))
+            }
+            discovered => discovered,
+        };
+        match &discovered {
+            Some(path) => self.log_event(log::Level::Debug, &format!("discovered definition '{}'", path)),
+            None => self.log_event(log::Level::Debug, "no definition file discovered"),
+        }
+        self.definition_path = discovered.clone();
+        discovered
+    }
+
+    /// Determines `definition_content` from the extension of `definition_path` or, if that
+    /// is unknown, from the content of the definition file, and records the method used.
+    /// Only the archive is read; a definition that is not part of it is not detected.
+    pub fn detect_definition(&mut self) -> Option<DetectionMethod> {
+        let definition_path = self.definition_path.clone()?;
+        let (definition, method) = match detection::definition_from_extension(&definition_path) {
+            Some(definition) => (definition, DetectionMethod::Extension),
+            None => {
+                let content = self.load_file_from_zip(&definition_path).ok()?;
+                let (content, _) = encoding::decode(&content).ok()?;
+                (detection::sniff_definition(content.as_bytes())?, DetectionMethod::Content)
+            }
+        };
+        self.log_event(
+            log::Level::Debug,
+            &format!("detected {} definition '{}' by {:?}", definition.format_name(), definition_path, method),
+        );
+        self.definition_content = Some(definition);
+        self.definition_detection = Some(method);
+        Some(method)
+    }
+
+    /// Downloads the external references of the definition into `folder` (by default
+    /// `localize::DEFAULT_LOCALIZE_FOLDER`) and replaces `zip_data` and the definition with
+    /// the self-contained result. Returns each localized reference with its new entry.
+    #[cfg(feature = "json")]
+    pub fn localize(&mut self, folder: Option<&str>) -> ZipContainerResult<Vec<(String, String)>> {
+        let localized = self.localize_references(folder.unwrap_or(localize::DEFAULT_LOCALIZE_FOLDER))?;
+        if !localized.localized.is_empty() {
+            self.zip_data = Some(localized.zip_data);
+            self.definition_content = Some(localized.definition);
+        }
+        Ok(localized.localized)
+    }
+
+    /// Checks the loaded `zip_data` against `expected`.
+    pub fn verify_integrity(&self, expected: &ExpectedIntegrity) -> ZipContainerResult<()> {
+        let location = self.zip_path.as_deref().unwrap_or("zip_data");
+        expected.verify(self.zip_data()?, location)
+    }
+
     pub async fn read_file_async(&self, file_name: &str) -> ZipContainerResult<Vec<u8>> {
         self.load_file_async(file_name).await
     }
@@ -97,6 +358,66 @@ impl ZipContainerTrait for ZipContainer {
         self.files = Some(files);
     }
 
+    fn loader(&self) -> &LoaderRegistry {
+        &self.loader
+    }
+
+    fn container_source(&self) -> &str {
+        self.zip_path.as_deref().unwrap_or(IN_MEMORY_SOURCE)
+    }
+
+    fn origin(&self) -> Option<&str> {
+        self.zip_path.as_deref()
+    }
+
+    fn log_event(&self, level: log::Level, message: &str) {
+        logging::emit(level, self.container_source(), message, self.logger.as_ref());
+    }
+
+    fn log_event_async(&self, level: log::Level, message: &str) -> logging::LogFuture {
+        logging::emit_async(level, self.container_source(), message, self.logger.as_ref(), self.async_logger.as_ref())
+    }
+
+    /// Returns the content set through `set_definition` or else reads the definition file.
+    fn load_definition_file_str(&self) -> ZipContainerResult<String> {
+        match &self.definition_content {
+            Some(definition) if !definition.as_ref().is_empty() => Ok(definition.as_ref().to_string()),
+            _ => zip_container_trait::read_definition_file(self).map(|(content, _)| content),
+        }
+    }
+
+    fn definition_encoding(&self) -> ZipContainerResult<SourceEncoding> {
+        match self.definition_encoding {
+            Some(encoding) => Ok(encoding),
+            None => zip_container_trait::read_definition_file(self).map(|(_, encoding)| encoding),
+        }
+    }
+
+    fn definition_kind(&self) -> Option<DefinitionKind> {
+        match &self.definition_content {
+            Some(definition) => Some(definition.kind()),
+            None => self.definition_path.as_deref().and_then(detection::definition_from_extension).map(|d| d.kind()),
+        }
+    }
+
+    fn profile(&self) -> Option<&dyn ContainerProfile> {
+        self.profile.as_deref()
+    }
+
+    #[cfg(feature = "json")]
+    fn reference_rules(&self) -> ReferenceRules {
+        let profile_rules = || self.profile().and_then(|profile| profile.reference_rules());
+        self.reference_rules.clone().or_else(profile_rules).unwrap_or_default()
+    }
+
+    fn set_definition(&mut self, definition: Definition) -> ZipContainerResult<()> {
+        if self.definition_encoding.is_none() {
+            self.definition_encoding = zip_container_trait::read_definition_file(self).ok().map(|(_, encoding)| encoding);
+        }
+        self.definition_content = Some(definition);
+        Ok(())
+    }
+
     // No need to implement other methods; default implementations are used
 }
 
@@ -116,5 +437,7 @@ pub struct BufFile{
 pub mod python_bindings;
 #[cfg(target_arch = "wasm32")]
 pub mod wasm_bindings;
+#[cfg(all(target_arch = "wasm32", feature = "http"))]
+mod wasm_fetch;
 mod tests;
 // mod ::python_tests;
@@ -0,0 +1,223 @@
+// src/loader_registry.rs
+use crate::data_url::DataUrlLoader;
+#[cfg(feature = "http")]
+use crate::http_options::HttpOptions;
+#[cfg(feature = "http")]
+use crate::loaders::HttpFileLoader;
+use crate::loaders::LocalFileLoader;
+use crate::zip_container_trait::{AsyncFileLoader, FileLoader, ZipContainerFuture};
+use crate::{function_path, BufFile, ZipContainerError, ZipContainerResult};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, OnceLock};
+
+/// A synchronous loader that can be shared between containers and threads.
+pub type SharedFileLoader = Arc<dyn FileLoader + Send + Sync>;
+/// An asynchronous loader that can be shared between containers and threads.
+pub type SharedAsyncFileLoader = Arc<dyn AsyncFileLoader + Send + Sync>;
+
+/// The scheme used for paths that do not carry one, e.g. `data/product.xml` or `/tmp/x.gldf`.
+pub const DEFAULT_SCHEME: &str = "file";
+
+/// Registry of `FileLoader`/`AsyncFileLoader` implementations keyed by URI scheme.
+///
+/// The default registry handles `file://` (and plain local paths), inline `data:` URLs
+/// and, with the `http` feature, `http://` and `https://`.
+/// Additional schemes such as `mem://` or a custom `s3://` can be registered at runtime:
+///
+/// ```no_run
+/// use zip_container::{LoaderRegistry, ZipContainer};
+/// use zip_container::loaders::DirectoryFileLoader;
+///
+/// let mut loader = LoaderRegistry::default();
+/// loader.register("s3", DirectoryFileLoader::new("/srv/s3-standin"));
+/// let container = ZipContainer::with_loader(
+///     "s3://catalogue/x.gldf".to_string(),
+///     Some("product.xml".to_string()),
+///     loader,
+/// );
+/// ```
+#[derive(Clone)]
+pub struct LoaderRegistry {
+    loaders: HashMap<String, SharedFileLoader>,
+    async_loaders: HashMap<String, SharedAsyncFileLoader>,
+}
+
+impl LoaderRegistry {
+    /// Creates a registry without any loaders registered.
+    pub fn empty() -> Self {
+        LoaderRegistry {
+            loaders: HashMap::new(),
+            async_loaders: HashMap::new(),
+        }
+    }
+
+    /// Returns the process wide default registry used by `UnifiedFileLoader`
+    /// and by `ZipContainerTrait` implementations that do not provide their own.
+    pub fn shared() -> &'static LoaderRegistry {
+        static SHARED: OnceLock<LoaderRegistry> = OnceLock::new();
+        SHARED.get_or_init(LoaderRegistry::default)
+    }
+
+    /// Registers a loader for both synchronous and asynchronous access.
+    /// An existing loader for the same scheme is replaced.
+    pub fn register<L>(&mut self, scheme: &str, loader: L) -> &mut Self
+    where
+        L: FileLoader + AsyncFileLoader + Send + Sync + 'static,
+    {
+        let loader = Arc::new(loader);
+        self.loaders.insert(normalize_scheme(scheme), loader.clone());
+        self.async_loaders.insert(normalize_scheme(scheme), loader);
+        self
+    }
+
+    /// Registers a synchronous only loader. Asynchronous loads for this scheme
+    /// fall back to it unless an asynchronous loader is registered as well.
+    pub fn register_sync(&mut self, scheme: &str, loader: SharedFileLoader) -> &mut Self {
+        self.loaders.insert(normalize_scheme(scheme), loader);
+        self
+    }
+
+    /// Registers an asynchronous only loader.
+    pub fn register_async(&mut self, scheme: &str, loader: SharedAsyncFileLoader) -> &mut Self {
+        self.async_loaders.insert(normalize_scheme(scheme), loader);
+        self
+    }
+
+    /// Registers an `HttpFileLoader` with `options` for `http://` and `https://`,
+    /// e.g. to send an auth token to a catalogue server.
+    #[cfg(feature = "http")]
+    pub fn register_http(&mut self, options: HttpOptions) -> &mut Self {
+        let loader = HttpFileLoader::new(options);
+        self.register("http", loader.clone());
+        self.register("https", loader)
+    }
+
+    /// Removes the loaders registered for `scheme`.
+    pub fn unregister(&mut self, scheme: &str) -> &mut Self {
+        let scheme = normalize_scheme(scheme);
+        self.loaders.remove(&scheme);
+        self.async_loaders.remove(&scheme);
+        self
+    }
+
+    /// Returns the schemes known to this registry, sorted.
+    pub fn schemes(&self) -> Vec<String> {
+        let mut schemes: Vec<String> = self
+            .loaders
+            .keys()
+            .chain(self.async_loaders.keys())
+            .cloned()
+            .collect();
+        schemes.sort();
+        schemes.dedup();
+        schemes
+    }
+
+    /// Returns `true` if a loader is registered for the scheme of `path_or_url`.
+    pub fn handles(&self, path_or_url: &str) -> bool {
+        let scheme = scheme_of(path_or_url).unwrap_or_else(|| DEFAULT_SCHEME.to_string());
+        self.loaders.contains_key(&scheme) || self.async_loaders.contains_key(&scheme)
+    }
+
+    fn sync_loader_for(&self, path_or_url: &str) -> ZipContainerResult<&SharedFileLoader> {
+        let scheme = scheme_of(path_or_url).unwrap_or_else(|| DEFAULT_SCHEME.to_string());
+        self.loaders
+            .get(&scheme)
+            .ok_or_else(|| ZipContainerError::UnsupportedOperation {
+                module_path: function_path!(),
+                message: format!("No loader registered for scheme '{}': {}", scheme, path_or_url),
+            })
+    }
+}
+
+impl Default for LoaderRegistry {
+    fn default() -> Self {
+        let mut registry = LoaderRegistry::empty();
+        registry.register(DEFAULT_SCHEME, LocalFileLoader);
+        #[cfg(feature = "http")]
+        registry.register_http(HttpOptions::default());
+        registry.register("data", DataUrlLoader);
+        registry
+    }
+}
+
+impl fmt::Debug for LoaderRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LoaderRegistry")
+            .field("schemes", &self.schemes())
+            .finish()
+    }
+}
+
+impl FileLoader for LoaderRegistry {
+    fn load(&self, path_or_url: &str) -> ZipContainerResult<Vec<u8>> {
+        self.sync_loader_for(path_or_url)?.load(path_or_url)
+    }
+
+    fn load_buf_file(&self, path_or_url: &str) -> ZipContainerResult<BufFile> {
+        self.sync_loader_for(path_or_url)?.load_buf_file(path_or_url)
+    }
+
+    fn exists(&self, path_or_url: &str) -> bool {
+        self.sync_loader_for(path_or_url).is_ok_and(|loader| loader.exists(path_or_url))
+    }
+}
+
+impl AsyncFileLoader for LoaderRegistry {
+    fn load_async(&self, path_or_url: &str) -> ZipContainerFuture<Vec<u8>> {
+        let scheme = scheme_of(path_or_url).unwrap_or_else(|| DEFAULT_SCHEME.to_string());
+        if let Some(loader) = self.async_loaders.get(&scheme) {
+            return loader.load_async(path_or_url);
+        }
+        // Fall back to a synchronous loader registered for the same scheme
+        let result = self.load(path_or_url);
+        Box::pin(async move { result })
+    }
+
+    fn load_buf_file_async(&self, path_or_url: &str) -> ZipContainerFuture<BufFile> {
+        let scheme = scheme_of(path_or_url).unwrap_or_else(|| DEFAULT_SCHEME.to_string());
+        if let Some(loader) = self.async_loaders.get(&scheme) {
+            return loader.load_buf_file_async(path_or_url);
+        }
+        let result = self.load_buf_file(path_or_url);
+        Box::pin(async move { result })
+    }
+}
+
+/// Extracts the lowercase URI scheme of `path_or_url`, if it has one.
+///
+/// Single letter schemes are treated as Windows drive letters (`C:\...`) and ignored.
+pub fn scheme_of(path_or_url: &str) -> Option<String> {
+    let (scheme, _) = path_or_url.split_once(':')?;
+    let mut chars = scheme.chars();
+    let first = chars.next()?;
+    if scheme.len() < 2
+        || !first.is_ascii_alphabetic()
+        || !chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+    {
+        return None;
+    }
+    Some(scheme.to_ascii_lowercase())
+}
+
+/// Resolves `reference` against `origin`, the URL a container was loaded from, the way
+/// links in a page are: `../shared/logo.png` in `https://host/catalog/x.gldf` becomes
+/// `https://host/shared/logo.png`. URLs, and any reference if `origin` is a filesystem
+/// path, are returned unchanged.
+pub fn resolve_against(origin: &str, reference: &str) -> String {
+    if scheme_of(reference).is_some() || scheme_of(origin).is_none() {
+        return reference.to_string();
+    }
+    match url::Url::parse(origin).and_then(|base| base.join(reference)) {
+        Ok(resolved) => resolved.to_string(),
+        Err(_) => reference.to_string(),
+    }
+}
+
+fn normalize_scheme(scheme: &str) -> String {
+    scheme
+        .trim_end_matches("://")
+        .trim_end_matches(':')
+        .to_ascii_lowercase()
+}
@@ -0,0 +1,197 @@
+// src/loaders.rs
+//! Built-in `FileLoader`/`AsyncFileLoader` implementations that can be
+//! registered in a `LoaderRegistry`.
+use crate::loader_registry::scheme_of;
+use crate::zip_container_trait::{AsyncFileLoader, FileLoader, ZipContainerFuture};
+use crate::{function_path, ZipContainerError, ZipContainerResult};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::io_err;
+#[cfg(not(target_arch = "wasm32"))]
+use std::{fs::File as StdFile, io::Read as StdIoRead, path::Path as StdPath};
+#[cfg(feature = "http")]
+pub use crate::http_loader::HttpFileLoader;
+
+/// Returns `path_or_url` without its `scheme://` (or `scheme:`) prefix.
+pub(crate) fn strip_scheme(path_or_url: &str) -> &str {
+    match scheme_of(path_or_url) {
+        Some(scheme) => {
+            let rest = &path_or_url[scheme.len() + 1..];
+            rest.strip_prefix("//").unwrap_or(rest)
+        }
+        None => path_or_url,
+    }
+}
+
+/// Loads plain local paths and `file://` URLs from the local filesystem.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LocalFileLoader;
+
+impl LocalFileLoader {
+    /// Converts a `file://` URL into a local path, leaving plain paths untouched.
+    pub fn local_path(path_or_url: &str) -> &str {
+        match scheme_of(path_or_url).as_deref() {
+            Some("file") => {
+                let rest = strip_scheme(path_or_url);
+                rest.strip_prefix("localhost").unwrap_or(rest)
+            }
+            _ => path_or_url,
+        }
+    }
+}
+
+impl FileLoader for LocalFileLoader {
+    fn load(&self, path_or_url: &str) -> ZipContainerResult<Vec<u8>> {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let mut file = io_err!(StdFile::open(StdPath::new(Self::local_path(path_or_url))))?;
+            let mut buffer = Vec::new();
+            io_err!(file.read_to_end(&mut buffer))?;
+            Ok(buffer)
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            // Return an error indicating that synchronous loading isn't supported in WASM
+            Err(ZipContainerError::UnsupportedOperation {
+                module_path: function_path!(),
+                message: format!("Synchronous file loading is not supported in WASM: {}", path_or_url),
+            })
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn exists(&self, path_or_url: &str) -> bool {
+        StdPath::new(Self::local_path(path_or_url)).is_file()
+    }
+}
+
+impl AsyncFileLoader for LocalFileLoader {
+    fn load_async(&self, path_or_url: &str) -> ZipContainerFuture<Vec<u8>> {
+        let path = Self::local_path(path_or_url).to_string(); // Own the data
+        Box::pin(async move {
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                // Load from local filesystem asynchronously using tokio::fs
+                use tokio::fs::File;
+                use tokio::io::AsyncReadExt;
+
+                let mut file = io_err!(File::open(StdPath::new(&path)).await)?;
+                let mut buffer = Vec::new();
+                io_err!(file.read_to_end(&mut buffer).await)?;
+                Ok(buffer)
+            }
+            #[cfg(target_arch = "wasm32")]
+            {
+                // Return an error since local file access is not available in the browser
+                Err(ZipContainerError::UnsupportedOperation {
+                    module_path: function_path!(),
+                    message: format!("Local file access is not supported in this environment: {}", path),
+                })
+            }
+        })
+    }
+}
+
+/// In-memory loader for `mem://name` URLs, useful for tests and for data
+/// that is produced by the application itself.
+///
+/// Clones share the same storage, so a loader can be registered in a
+/// `LoaderRegistry` and still be filled afterwards.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryFileLoader {
+    entries: Arc<RwLock<HashMap<String, Vec<u8>>>>,
+}
+
+impl MemoryFileLoader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores `data` under `name`; `name` may be given with or without the `mem://` prefix.
+    pub fn insert(&self, name: &str, data: Vec<u8>) {
+        let mut entries = self.entries.write().unwrap_or_else(|e| e.into_inner());
+        entries.insert(strip_scheme(name).to_string(), data);
+    }
+
+    /// Removes the entry stored under `name` and returns its data.
+    pub fn remove(&self, name: &str) -> Option<Vec<u8>> {
+        let mut entries = self.entries.write().unwrap_or_else(|e| e.into_inner());
+        entries.remove(strip_scheme(name))
+    }
+}
+
+impl FileLoader for MemoryFileLoader {
+    fn load(&self, path_or_url: &str) -> ZipContainerResult<Vec<u8>> {
+        let entries = self.entries.read().unwrap_or_else(|e| e.into_inner());
+        entries
+            .get(strip_scheme(path_or_url))
+            .cloned()
+            .ok_or_else(|| ZipContainerError::IOError {
+                module_path: function_path!(),
+                source: std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    format!("No in-memory entry: {}", path_or_url),
+                ),
+            })
+    }
+
+    fn exists(&self, path_or_url: &str) -> bool {
+        let entries = self.entries.read().unwrap_or_else(|e| e.into_inner());
+        entries.contains_key(strip_scheme(path_or_url))
+    }
+}
+
+impl AsyncFileLoader for MemoryFileLoader {
+    fn load_async(&self, path_or_url: &str) -> ZipContainerFuture<Vec<u8>> {
+        let result = self.load(path_or_url);
+        Box::pin(async move { result })
+    }
+}
+
+/// Serves `scheme://bucket/key` URLs from a local directory, e.g. as a stand-in
+/// for object storage such as `s3://` in tests or on-premise installations.
+/// `scheme://bucket/key` is read from `<root>/bucket/key`.
+#[derive(Clone, Debug)]
+pub struct DirectoryFileLoader {
+    root: PathBuf,
+}
+
+impl DirectoryFileLoader {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        DirectoryFileLoader { root: root.into() }
+    }
+
+    /// Maps `path_or_url` to a path below the root directory.
+    pub fn resolve(&self, path_or_url: &str) -> ZipContainerResult<PathBuf> {
+        let key = strip_scheme(path_or_url).trim_start_matches('/');
+        if key.split(['/', '\\']).any(|segment| segment == "..") {
+            return Err(ZipContainerError::InvalidData {
+                module_path: function_path!(),
+                message: format!("Path escapes the loader root: {}", path_or_url),
+            });
+        }
+        Ok(self.root.join(key))
+    }
+}
+
+impl FileLoader for DirectoryFileLoader {
+    fn load(&self, path_or_url: &str) -> ZipContainerResult<Vec<u8>> {
+        let path = self.resolve(path_or_url)?;
+        LocalFileLoader.load(&path.to_string_lossy())
+    }
+
+    fn exists(&self, path_or_url: &str) -> bool {
+        self.resolve(path_or_url).is_ok_and(|path| LocalFileLoader.exists(&path.to_string_lossy()))
+    }
+}
+
+impl AsyncFileLoader for DirectoryFileLoader {
+    fn load_async(&self, path_or_url: &str) -> ZipContainerFuture<Vec<u8>> {
+        match self.resolve(path_or_url) {
+            Ok(path) => LocalFileLoader.load_async(&path.to_string_lossy()),
+            Err(e) => Box::pin(async move { Err(e) }),
+        }
+    }
+}
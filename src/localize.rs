@@ -0,0 +1,210 @@
+// src/localize.rs
+//! Localizing a container: external references of the definition are downloaded into the
+//! archive and the definition is rewritten to point at the local copies, so the container
+//! no longer needs network access. Requires the `json` feature.
+use crate::document::{locate_key, Segment};
+use crate::jsonpath;
+#[cfg(feature = "xml")]
+use crate::encoding::escape_xml;
+#[cfg(feature = "xml")]
+use crate::xml_tree::{local, Tree};
+#[cfg(feature = "xml")]
+use crate::xpath::{self, ValueNode};
+#[cfg(feature = "xml")]
+use crate::{function_path, ZipContainerError};
+use crate::{Definition, DefinitionKind, ZipContainerResult};
+use serde_json::Value;
+use std::ops::Range;
+
+/// Folder that `ZipContainer::localize` stores downloaded files in by default.
+pub const DEFAULT_LOCALIZE_FOLDER: &str = "external";
+
+/// The result of `localize_references`.
+#[derive(Clone, Debug)]
+pub struct LocalizedContainer {
+    /// The archive with the downloaded files and the rewritten definition.
+    pub zip_data: Vec<u8>,
+    /// The rewritten definition.
+    pub definition: Definition,
+    /// Each localized reference with the entry it now points at.
+    pub localized: Vec<(String, String)>,
+}
+
+/// Returns an entry name in `folder` for the file `reference` points at that is not among
+/// `entries`, numbering it (`logo-2.png`) if needed.
+pub(crate) fn entry_name(folder: &str, reference: &str, entries: &[String]) -> String {
+    let path = reference.split(['?', '#']).next().unwrap_or_default();
+    let file_name = path.rsplit(['/', '\\']).next().filter(|name| !name.is_empty()).unwrap_or("file");
+    let (stem, extension) = match file_name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => (stem, format!(".{}", extension)),
+        _ => (file_name, String::new()),
+    };
+    let folder = folder.trim_matches('/');
+    let in_folder = |name: &str| match folder.is_empty() {
+        true => name.to_string(),
+        false => format!("{}/{}", folder, name),
+    };
+    let mut candidate = in_folder(file_name);
+    let mut number = 2;
+    while entries.contains(&candidate) {
+        candidate = in_folder(&format!("{}-{}{}", stem, number, extension));
+        number += 1;
+    }
+    candidate
+}
+
+/// A replacement of a byte range of the definition.
+type Edit = (Range<usize>, String);
+
+/// Replaces each reference of `localized` with its entry where one of the reference
+/// `expressions` matches it in `definition`: in XML the text or attribute an XPath selects,
+/// otherwise the value at the path a JSONPath selects. In XML, a GLDF `<File type="url">`
+/// whose text is rewritten becomes `type="localFileName"`.
+pub(crate) fn rewrite_references(
+    definition: &Definition,
+    expressions: &[String],
+    localized: &[(String, String)],
+) -> ZipContainerResult<String> {
+    let mut edits = match definition {
+        #[cfg(feature = "xml")]
+        Definition::XML(content) => xml_edits(content, expressions, localized)?,
+        #[cfg(not(feature = "xml"))]
+        Definition::XML(_) => Vec::new(),
+        _ => document_edits(definition, expressions, localized)?,
+    };
+    let content = definition.as_ref();
+    edits.sort_by_key(|(range, _)| range.start);
+    let mut rewritten = String::with_capacity(content.len());
+    let mut last = 0;
+    for (range, replacement) in edits {
+        if range.start < last {
+            continue;
+        }
+        rewritten.push_str(&content[last..range.start]);
+        rewritten.push_str(&replacement);
+        last = range.end;
+    }
+    rewritten.push_str(&content[last..]);
+    Ok(rewritten)
+}
+
+fn entry_for<'l>(localized: &'l [(String, String)], value: &str) -> Option<&'l str> {
+    localized.iter().find(|(reference, _)| reference == value.trim()).map(|(_, entry)| entry.as_str())
+}
+
+#[cfg(feature = "xml")]
+fn xml_edits(content: &str, expressions: &[String], localized: &[(String, String)]) -> ZipContainerResult<Vec<Edit>> {
+    let tree = Tree::parse(content).map_err(|diagnostic| ZipContainerError::InvalidData {
+        module_path: function_path!(),
+        message: format!("Invalid XML: {}", diagnostic),
+    })?;
+    let mut values = Vec::new();
+    for expression in expressions {
+        match expression.trim_start().starts_with('$') {
+            // A JSONPath runs on the document mapping, any text or attribute may be meant
+            true => values.extend(all_values(&tree)),
+            false => values.extend(xpath::query_values(&tree, expression)?),
+        }
+    }
+    let mut edits = Vec::new();
+    for value in values {
+        match value {
+            ValueNode::Text(node) => {
+                let element = &tree.nodes[node];
+                let Some(entry) = entry_for(localized, &element.text) else { continue };
+                let raw = &content[element.inner.clone()];
+                let start = element.inner.start + raw.len() - raw.trim_start().len();
+                let end = element.inner.end - (raw.len() - raw.trim_end().len());
+                edits.push((start..end, escape_xml(entry)));
+                if local(&element.name) == "File" {
+                    let url_type = attribute_span(content, &element.tag, "type").filter(|span| &content[span.clone()] == "url");
+                    edits.extend(url_type.map(|span| (span, "localFileName".to_string())));
+                }
+            }
+            ValueNode::Attribute(node, index) => {
+                let element = &tree.nodes[node];
+                let (name, value) = &element.attributes[index];
+                let Some(entry) = entry_for(localized, value) else { continue };
+                edits.extend(attribute_span(content, &element.tag, name).map(|span| (span, escape_xml(entry))));
+            }
+        }
+    }
+    Ok(edits)
+}
+
+#[cfg(feature = "xml")]
+fn all_values(tree: &Tree) -> Vec<ValueNode> {
+    let mut values = Vec::new();
+    for (node, element) in tree.nodes.iter().enumerate() {
+        values.extend((0..element.attributes.len()).map(|index| ValueNode::Attribute(node, index)));
+        if element.children.is_empty() {
+            values.push(ValueNode::Text(node));
+        }
+    }
+    values
+}
+
+/// Returns the byte range of the value of the attribute `name` in the start `tag`.
+#[cfg(feature = "xml")]
+fn attribute_span(content: &str, tag: &Range<usize>, name: &str) -> Option<Range<usize>> {
+    let text = &content[tag.clone()];
+    for (offset, _) in text.match_indices(name) {
+        if !text[..offset].ends_with(char::is_whitespace) {
+            continue;
+        }
+        let Some(rest) = text[offset + name.len()..].trim_start().strip_prefix('=') else { continue };
+        let rest = rest.trim_start();
+        let Some(quote) = rest.chars().next().filter(|c| matches!(c, '"' | '\'')) else { continue };
+        let start = tag.start + text.len() - rest.len() + 1;
+        let length = rest[1..].find(quote)?;
+        return Some(start..start + length);
+    }
+    None
+}
+
+/// Edits for a JSON, YAML or TOML definition: each value is searched after the innermost
+/// key of its path, as a whole quoted or unquoted value.
+fn document_edits(definition: &Definition, expressions: &[String], localized: &[(String, String)]) -> ZipContainerResult<Vec<Edit>> {
+    let content = definition.as_ref();
+    let document = definition.to_document()?;
+    let mut edits: Vec<Edit> = Vec::new();
+    for expression in expressions.iter().filter(|expression| expression.trim_start().starts_with('$')) {
+        for path in jsonpath::query_paths(&document, expression)? {
+            let Some(value) = value_at(&document, &path).and_then(Value::as_str) else { continue };
+            let Some(entry) = entry_for(localized, value) else { continue };
+            let (reference, entry) = match definition.kind() {
+                DefinitionKind::Json => (escape_json(value.trim()), escape_json(entry)),
+                _ => (value.trim().to_string(), entry.to_string()),
+            };
+            let from = locate_key(content, &path).unwrap_or_default();
+            if let Some(start) = find_value(content, from, &reference, &edits) {
+                edits.push((start..start + reference.len(), entry));
+            }
+        }
+    }
+    Ok(edits)
+}
+
+fn value_at<'v>(document: &'v Value, path: &[Segment]) -> Option<&'v Value> {
+    path.iter().try_fold(document, |value, segment| match segment {
+        Segment::Key(key) => value.get(key),
+        Segment::Index(index) => value.get(index),
+    })
+}
+
+/// Finds `value` from `from` on where it is a whole value, e.g. quoted or followed by a line
+/// break, and not already edited.
+fn find_value(content: &str, from: usize, value: &str, edits: &[Edit]) -> Option<usize> {
+    content[from..].match_indices(value).map(|(offset, _)| from + offset).find(|&offset| {
+        let before = content[..offset].chars().next_back();
+        let after = content[offset + value.len()..].chars().next();
+        let opens = before.is_none_or(|c| c.is_whitespace() || matches!(c, '"' | '\'' | '=' | ':' | '-' | '['));
+        let closes = after.is_none_or(|c| c.is_whitespace() || matches!(c, '"' | '\'' | ',' | ']' | '}'));
+        opens && closes && !edits.iter().any(|(range, _)| range.start == offset)
+    })
+}
+
+fn escape_json(text: &str) -> String {
+    let quoted = serde_json::Value::String(text.to_string()).to_string();
+    quoted[1..quoted.len() - 1].to_string()
+}
@@ -0,0 +1,137 @@
+// src/logging.rs
+//! Routing of container events (source opened, entries listed, bytes read, loader
+//! fallbacks, each load with its duration) to the `log` crate and to a `Logger` or
+//! `AsyncLogger` attached to a `ZipContainer`.
+use crate::{AsyncLogger, Logger};
+use log::Level;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Target of all `log` records emitted by this crate.
+pub const LOG_TARGET: &str = "zip_container";
+
+impl<F> Logger for F
+where
+    F: Fn(&str),
+{
+    fn log(&self, message: &str) {
+        self(message)
+    }
+}
+
+/// A `Logger` that can be attached to a `ZipContainer` and shared between clones.
+#[derive(Clone)]
+pub struct SharedLogger(Arc<dyn Logger + Send + Sync>);
+
+impl SharedLogger {
+    pub fn new(logger: impl Logger + Send + Sync + 'static) -> Self {
+        SharedLogger(Arc::new(logger))
+    }
+}
+
+impl fmt::Debug for SharedLogger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SharedLogger")
+    }
+}
+
+impl PartialEq for SharedLogger {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// Future returned by `SharedAsyncLogger::log`.
+pub type LogFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// An `AsyncLogger` that can be attached to a `ZipContainer` and shared between clones.
+/// It receives the events of the asynchronous loads, e.g. `load_file_async`.
+#[derive(Clone)]
+pub struct SharedAsyncLogger(Arc<dyn Fn(String) -> LogFuture + Send + Sync>);
+
+impl SharedAsyncLogger {
+    pub fn new(logger: impl AsyncLogger + Send + Sync + 'static) -> Self {
+        let logger = Arc::new(logger);
+        SharedAsyncLogger(Arc::new(move |message: String| {
+            let logger = logger.clone();
+            Box::pin(async move { logger.log(&message).await })
+        }))
+    }
+
+    pub fn log(&self, message: &str) -> LogFuture {
+        (self.0)(message.to_string())
+    }
+}
+
+impl fmt::Debug for SharedAsyncLogger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SharedAsyncLogger")
+    }
+}
+
+/// Measures how long a load or request takes. `std::time::Instant` panics on WASM, where
+/// `Date.now()` is used instead.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Stopwatch {
+    #[cfg(not(target_arch = "wasm32"))]
+    started: std::time::Instant,
+    #[cfg(target_arch = "wasm32")]
+    started: f64,
+}
+
+impl Stopwatch {
+    pub(crate) fn start() -> Self {
+        Stopwatch {
+            #[cfg(not(target_arch = "wasm32"))]
+            started: std::time::Instant::now(),
+            #[cfg(target_arch = "wasm32")]
+            started: js_sys::Date::now(),
+        }
+    }
+
+    pub(crate) fn elapsed(&self) -> Duration {
+        #[cfg(not(target_arch = "wasm32"))]
+        return self.started.elapsed();
+        #[cfg(target_arch = "wasm32")]
+        return Duration::from_secs_f64((js_sys::Date::now() - self.started).max(0.0) / 1000.0);
+    }
+}
+
+/// Logs the outcome of the HTTP request for `url` with its duration.
+#[cfg(feature = "http")]
+pub(crate) fn log_request<T: AsRef<[u8]>, E: fmt::Display>(url: &str, result: &Result<T, E>, stopwatch: Stopwatch) {
+    match result {
+        Ok(data) => log::debug!(target: LOG_TARGET, "GET {}: {} bytes in {:.1?}", url, data.as_ref().len(), stopwatch.elapsed()),
+        Err(e) => log::debug!(target: LOG_TARGET, "GET {} failed after {:.1?}: {}", url, stopwatch.elapsed(), e),
+    }
+}
+
+/// Logs the outcome of the HTTP `HEAD` request for `url` with its duration.
+#[cfg(feature = "http")]
+pub(crate) fn log_probe<E: fmt::Display>(url: &str, result: &Result<bool, E>, stopwatch: Stopwatch) {
+    match result {
+        Ok(found) => log::debug!(target: LOG_TARGET, "HEAD {}: found {} in {:.1?}", url, found, stopwatch.elapsed()),
+        Err(e) => log::debug!(target: LOG_TARGET, "HEAD {} failed after {:.1?}: {}", url, stopwatch.elapsed(), e),
+    }
+}
+
+/// Emits `message` about the container loaded from `source` as a `log` record and,
+/// if present, to `logger` as `"[LEVEL] source: message"`.
+pub(crate) fn emit(level: Level, source: &str, message: &str, logger: Option<&SharedLogger>) {
+    log::log!(target: LOG_TARGET, level, "{}: {}", source, message);
+    if let Some(logger) = logger {
+        logger.0.log(&format!("[{}] {}: {}", level, source, message));
+    }
+}
+
+/// Like `emit`, also passing the message to the asynchronous `logger`, if present.
+pub(crate) fn emit_async(level: Level, source: &str, message: &str, logger: Option<&SharedLogger>, async_logger: Option<&SharedAsyncLogger>) -> LogFuture {
+    emit(level, source, message, logger);
+    match async_logger {
+        Some(async_logger) => async_logger.log(&format!("[{}] {}: {}", level, source, message)),
+        None => Box::pin(async {}),
+    }
+}
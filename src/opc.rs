@@ -0,0 +1,292 @@
+// src/opc.rs
+//! Open Packaging Conventions (ECMA-376 part 2), the container of DOCX, XLSX and PPTX
+//! files, on top of `ZipContainerTrait`. Requires the `xml` feature.
+//!
+//! Parts are named like `/word/document.xml`, their archive entries without the leading
+//! slash. `[Content_Types].xml` gives each part its content type, by `Override` for the
+//! part or `Default` for its extension (both compared case-insensitively). The
+//! relationships of a part are in `_rels/<name>.rels` next to it, those of the package
+//! in `/_rels/.rels`.
+use crate::data_url::percent_decode;
+use crate::encoding::escape_xml;
+use crate::xml_tree::Tree;
+use crate::{function_path, BufFile, ZipContainerError, ZipContainerResult, ZipContainerTrait};
+
+/// Entry of the content types of a package.
+pub const CONTENT_TYPES_ENTRY: &str = "[Content_Types].xml";
+/// Content type of relationship parts.
+pub const RELATIONSHIPS_CONTENT_TYPE: &str = "application/vnd.openxmlformats-package.relationships+xml";
+const CONTENT_TYPES_NAMESPACE: &str = "http://schemas.openxmlformats.org/package/2006/content-types";
+const RELATIONSHIPS_NAMESPACE: &str = "http://schemas.openxmlformats.org/package/2006/relationships";
+
+/// The parsed `[Content_Types].xml`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ContentTypes {
+    /// Pairs of an extension (without the dot) and its content type.
+    pub defaults: Vec<(String, String)>,
+    /// Pairs of a part name and its content type.
+    pub overrides: Vec<(String, String)>,
+}
+
+impl ContentTypes {
+    pub fn parse(content: &str) -> ZipContainerResult<ContentTypes> {
+        let tree = parse_xml(CONTENT_TYPES_ENTRY, content)?;
+        let mut content_types = ContentTypes::default();
+        for node in 0..tree.nodes.len() {
+            let content_type = tree.attribute(node, "ContentType").unwrap_or_default().to_string();
+            match tree.kind(node) {
+                "Default" => {
+                    let extension = tree.attribute(node, "Extension").unwrap_or_default();
+                    content_types.defaults.push((extension.to_string(), content_type));
+                }
+                "Override" => {
+                    let part_name = tree.attribute(node, "PartName").unwrap_or_default();
+                    content_types.overrides.push((part_name.to_string(), content_type));
+                }
+                _ => {}
+            }
+        }
+        Ok(content_types)
+    }
+
+    /// Returns the content type of the part `part_name`.
+    pub fn content_type(&self, part_name: &str) -> Option<&str> {
+        let part_name = part_name_of(part_name);
+        let overridden = self.overrides.iter().find(|(name, _)| name.eq_ignore_ascii_case(&part_name));
+        let by_extension = || {
+            let extension = part_name.rsplit_once('.').map(|(_, extension)| extension).filter(|e| !e.contains('/'))?;
+            self.defaults.iter().find(|(default, _)| default.eq_ignore_ascii_case(extension))
+        };
+        overridden.or_else(by_extension).map(|(_, content_type)| content_type.as_str())
+    }
+
+    /// Makes `part_name` have `content_type`, adding an `Override` unless the `Default` of
+    /// its extension already gives it.
+    pub fn set(&mut self, part_name: &str, content_type: &str) {
+        let part_name = part_name_of(part_name);
+        self.overrides.retain(|(name, _)| !name.eq_ignore_ascii_case(&part_name));
+        if self.content_type(&part_name) != Some(content_type) {
+            self.overrides.push((part_name, content_type.to_string()));
+        }
+    }
+
+    /// Adds a `Default` for `extension` unless there is one.
+    pub fn set_default(&mut self, extension: &str, content_type: &str) {
+        if !self.defaults.iter().any(|(default, _)| default.eq_ignore_ascii_case(extension)) {
+            self.defaults.push((extension.to_string(), content_type.to_string()));
+        }
+    }
+
+    pub fn to_xml(&self) -> String {
+        let mut xml = xml_declaration();
+        xml.push_str(&format!("<Types xmlns=\"{}\">", CONTENT_TYPES_NAMESPACE));
+        for (extension, content_type) in &self.defaults {
+            xml.push_str(&format!(
+                "<Default Extension=\"{}\" ContentType=\"{}\"/>",
+                escape_xml(extension),
+                escape_xml(content_type)
+            ));
+        }
+        for (part_name, content_type) in &self.overrides {
+            xml.push_str(&format!(
+                "<Override PartName=\"{}\" ContentType=\"{}\"/>",
+                escape_xml(part_name),
+                escape_xml(content_type)
+            ));
+        }
+        xml.push_str("</Types>");
+        xml
+    }
+}
+
+/// A relationship from a part, or the package, to a part or an external resource.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Relationship {
+    pub id: String,
+    /// The relationship type, a URI such as
+    /// `http://schemas.openxmlformats.org/officeDocument/2006/relationships/image`.
+    pub relationship_type: String,
+    pub target: String,
+    /// Whether `target` is an external resource (`TargetMode="External"`) rather than a part.
+    pub external: bool,
+}
+
+impl Relationship {
+    /// Returns the name of the part the relationship of `source_part` points at, `None` for
+    /// external targets.
+    pub fn target_part(&self, source_part: &str) -> Option<String> {
+        (!self.external).then(|| resolve_part_uri(source_part, &self.target))
+    }
+}
+
+/// Parses a relationships part, `entry` naming it in errors.
+pub fn parse_relationships(entry: &str, content: &str) -> ZipContainerResult<Vec<Relationship>> {
+    let tree = parse_xml(entry, content)?;
+    let relationships = (0..tree.nodes.len())
+        .filter(|&node| tree.kind(node) == "Relationship")
+        .map(|node| Relationship {
+            id: tree.attribute(node, "Id").unwrap_or_default().to_string(),
+            relationship_type: tree.attribute(node, "Type").unwrap_or_default().to_string(),
+            target: tree.attribute(node, "Target").unwrap_or_default().to_string(),
+            external: tree.attribute(node, "TargetMode") == Some("External"),
+        })
+        .collect();
+    Ok(relationships)
+}
+
+pub fn relationships_to_xml(relationships: &[Relationship]) -> String {
+    let mut xml = xml_declaration();
+    xml.push_str(&format!("<Relationships xmlns=\"{}\">", RELATIONSHIPS_NAMESPACE));
+    for relationship in relationships {
+        xml.push_str(&format!(
+            "<Relationship Id=\"{}\" Type=\"{}\" Target=\"{}\"{}/>",
+            escape_xml(&relationship.id),
+            escape_xml(&relationship.relationship_type),
+            escape_xml(&relationship.target),
+            if relationship.external { " TargetMode=\"External\"" } else { "" }
+        ));
+    }
+    xml.push_str("</Relationships>");
+    xml
+}
+
+/// Resolves the relative or absolute part URI `target` of a relationship of `source_part`
+/// (`/` for the package) to a part name: `media/a.png` in `/word/document.xml` is
+/// `/word/media/a.png`.
+pub fn resolve_part_uri(source_part: &str, target: &str) -> String {
+    let resolved = url::Url::parse("http://package/")
+        .and_then(|base| base.join(&part_name_of(source_part)))
+        .and_then(|source| source.join(target));
+    match resolved {
+        Ok(resolved) => String::from_utf8_lossy(&percent_decode(resolved.path())).into_owned(),
+        Err(_) => part_name_of(target),
+    }
+}
+
+/// Returns the entry of the relationships of `source_part`, `_rels/.rels` for the package.
+pub fn relationships_entry(source_part: &str) -> String {
+    let part_name = part_name_of(source_part);
+    let (folder, name) = part_name.rsplit_once('/').unwrap_or(("", &part_name));
+    format!("{}/_rels/{}.rels", folder, name).trim_start_matches('/').to_string()
+}
+
+/// Returns the archive entry of the part `part_name`.
+pub fn part_entry(part_name: &str) -> &str {
+    part_name.trim_start_matches('/')
+}
+
+fn part_name_of(name: &str) -> String {
+    format!("/{}", name.trim_start_matches('/'))
+}
+
+/// A part to write with `save_opc`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct OpcPart {
+    pub name: String,
+    pub content_type: String,
+    pub data: Vec<u8>,
+}
+
+/// Parts and relationships to write with `save_opc`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct OpcChanges {
+    /// Parts added or replaced.
+    pub parts: Vec<OpcPart>,
+    /// Pairs of a source part (`/` for the package) and its complete relationships.
+    pub relationships: Vec<(String, Vec<Relationship>)>,
+}
+
+/// The OPC layer, available on every container.
+pub trait OpcPackage: ZipContainerTrait {
+    /// Returns the parsed `[Content_Types].xml`; empty if the archive has none.
+    fn content_types(&self) -> ZipContainerResult<ContentTypes> {
+        if !self.get_file_names()?.iter().any(|entry| entry == CONTENT_TYPES_ENTRY) {
+            return Ok(ContentTypes::default());
+        }
+        ContentTypes::parse(&String::from_utf8(self.load_file_from_zip(CONTENT_TYPES_ENTRY)?)?)
+    }
+
+    /// Like `get_zip_files` without `[Content_Types].xml` and folders, with the content type
+    /// of each part.
+    fn get_parts(&self) -> ZipContainerResult<Vec<BufFile>> {
+        let content_types = self.content_types()?;
+        let mut parts = self.get_zip_files()?;
+        parts.retain(|part| part.name.as_deref().is_some_and(|name| name != CONTENT_TYPES_ENTRY && !name.ends_with('/')));
+        for part in &mut parts {
+            let content_type = part.name.as_deref().and_then(|name| content_types.content_type(name));
+            part.content_type = content_type.map(String::from).or(part.content_type.take());
+        }
+        Ok(parts)
+    }
+
+    /// Returns the relationships of `source_part`, `/` for those of the package; none if it
+    /// has no relationships part.
+    fn relationships(&self, source_part: &str) -> ZipContainerResult<Vec<Relationship>> {
+        let entry = relationships_entry(source_part);
+        if !self.get_file_names()?.contains(&entry) {
+            return Ok(Vec::new());
+        }
+        parse_relationships(&entry, &String::from_utf8(self.load_file_from_zip(&entry)?)?)
+    }
+
+    /// Returns the part names the relationships of `source_part` of `relationship_type`
+    /// point at, e.g. the main document for the package's `officeDocument` relationship.
+    fn related_parts(&self, source_part: &str, relationship_type: &str) -> ZipContainerResult<Vec<String>> {
+        let relationships = self.relationships(source_part)?;
+        Ok(relationships
+            .iter()
+            .filter(|relationship| relationship.relationship_type == relationship_type)
+            .filter_map(|relationship| relationship.target_part(source_part))
+            .collect())
+    }
+
+    /// Loads the part `part_name`.
+    fn load_part(&self, part_name: &str) -> ZipContainerResult<Vec<u8>> {
+        self.load_file_from_zip(part_entry(part_name))
+    }
+
+    /// Returns the ZIP data with `changes` applied and the required parts written with
+    /// them: `[Content_Types].xml` gets the content types of the new parts and of the
+    /// relationship parts, and each relationship list is written to its `.rels` part.
+    /// A container without ZIP data starts a new package.
+    fn save_opc(&self, changes: &OpcChanges) -> ZipContainerResult<Vec<u8>> {
+        let mut content_types = match self.zip_data() {
+            Ok(_) => self.content_types()?,
+            Err(ZipContainerError::MissingValue { .. }) => ContentTypes::default(),
+            Err(e) => return Err(e),
+        };
+        let mut files = Vec::new();
+        for part in &changes.parts {
+            if part_entry(&part.name) == CONTENT_TYPES_ENTRY || part.name.is_empty() {
+                return Err(ZipContainerError::InvalidData {
+                    module_path: function_path!(),
+                    message: format!("'{}' is not a valid part name", part.name),
+                });
+            }
+            content_types.set(&part.name, &part.content_type);
+            files.push((part_entry(&part.name).to_string(), part.data.clone()));
+        }
+        if !changes.relationships.is_empty() {
+            content_types.set_default("rels", RELATIONSHIPS_CONTENT_TYPE);
+        }
+        for (source_part, relationships) in &changes.relationships {
+            files.push((relationships_entry(source_part), relationships_to_xml(relationships).into_bytes()));
+        }
+        files.insert(0, (CONTENT_TYPES_ENTRY.to_string(), content_types.to_xml().into_bytes()));
+        self.write_entries(&files)
+    }
+}
+
+impl<T: ZipContainerTrait> OpcPackage for T {}
+
+fn parse_xml(entry: &str, content: &str) -> ZipContainerResult<Tree> {
+    Tree::parse(content.trim_start_matches('\u{feff}')).map_err(|diagnostic| ZipContainerError::InvalidData {
+        module_path: function_path!(),
+        message: format!("Invalid XML in '{}': {}", entry, diagnostic),
+    })
+}
+
+fn xml_declaration() -> String {
+    "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\r\n".to_string()
+}
+
@@ -0,0 +1,30 @@
+// src/pointer.rs
+//! Definition pointers of EPUB (OCF) and ODF packages: `META-INF/container.xml` names the
+//! package document in the `full-path` of its first `rootfile`, and `META-INF/manifest.xml`
+//! lists `content.xml`, the document of an ODF file. Requires the `xml` feature.
+use crate::xml_tree::Tree;
+
+/// Entries that point at the definition, in the order they are tried.
+pub const POINTER_ENTRIES: &[&str] = &["META-INF/container.xml", "META-INF/manifest.xml"];
+
+/// The document of an ODF package.
+const ODF_CONTENT: &str = "content.xml";
+
+/// Returns the definition path the pointer `content` names, if it is a container or
+/// manifest that names one.
+pub(crate) fn pointed_definition(content: &str) -> Option<String> {
+    let tree = Tree::parse(content.trim_start_matches('\u{feff}')).ok()?;
+    let full_path = |node| tree.attribute(node, "full-path").or_else(|| tree.attribute(node, "manifest:full-path"));
+    match tree.kind(0) {
+        "container" => (0..tree.nodes.len())
+            .find(|&node| tree.kind(node) == "rootfile")
+            .and_then(full_path)
+            .map(|path| path.trim_start_matches('/').to_string()),
+        "manifest" => (0..tree.nodes.len())
+            .filter(|&node| tree.kind(node) == "file-entry")
+            .filter_map(full_path)
+            .find(|path| path.trim_start_matches('/') == ODF_CONTENT)
+            .map(|_| ODF_CONTENT.to_string()),
+        _ => None,
+    }
+}
@@ -0,0 +1,262 @@
+// src/profile.rs
+//! Container profiles: the expected layout of a container format.
+//!
+//! A profile names the definition file, the entries that must or may be present (as
+//! globs), the folders entries may live in, the content types of entries and, for formats
+//! like EPUB and ODF, the content of the `mimetype` entry that has to come first.
+//!
+//! Globs match whole entry names: `*` matches within a folder, `**` across folders and
+//! `?` a single character. `ProfileDescriptor::gldf()` and `ProfileDescriptor::l3d()`
+//! describe the two formats this crate is mostly used with.
+#[cfg(feature = "json")]
+use crate::ReferenceRules;
+use crate::Diagnostic;
+use std::fmt::Debug;
+
+/// The expected layout of a container format.
+pub trait ContainerProfile: Debug + Send + Sync {
+    /// Returns the name of the format, e.g. `GLDF`.
+    fn name(&self) -> &str;
+
+    /// Returns the path of the definition file in the archive.
+    fn definition_path(&self) -> &str;
+
+    /// Returns globs of which each must match at least one entry.
+    fn required_entries(&self) -> &[String] {
+        &[]
+    }
+
+    /// Returns globs of further entries that may be present.
+    fn optional_entries(&self) -> &[String] {
+        &[]
+    }
+
+    /// Returns the top-level folders (globs) entries may be in. If empty, entries in folders
+    /// are only allowed when they match `required_entries` or `optional_entries`.
+    fn allowed_folders(&self) -> &[String] {
+        &[]
+    }
+
+    /// Returns pairs of an entry glob and the content type of matching entries.
+    fn content_types(&self) -> &[(String, String)] {
+        &[]
+    }
+
+    /// Returns the content type of `entry`, from the first matching glob of `content_types`.
+    fn content_type(&self, entry: &str) -> Option<&str> {
+        self.content_types()
+            .iter()
+            .find(|(pattern, _)| glob_matches(pattern, entry))
+            .map(|(_, content_type)| content_type.as_str())
+    }
+
+    /// Returns the content of the `mimetype` entry if the format requires one as the first,
+    /// uncompressed entry of the archive.
+    fn mimetype(&self) -> Option<&str> {
+        None
+    }
+
+    /// Returns the rules `check_references` uses for this format.
+    #[cfg(feature = "json")]
+    fn reference_rules(&self) -> Option<ReferenceRules> {
+        None
+    }
+}
+
+/// A `ContainerProfile` given as data.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProfileDescriptor {
+    pub name: String,
+    pub definition_path: String,
+    pub required_entries: Vec<String>,
+    pub optional_entries: Vec<String>,
+    pub allowed_folders: Vec<String>,
+    pub content_types: Vec<(String, String)>,
+    pub mimetype: Option<String>,
+    #[cfg(feature = "json")]
+    pub reference_rules: Option<ReferenceRules>,
+}
+
+/// The folders of a GLDF container, one per kind of file.
+const GLDF_FOLDERS: &[&str] = &["ldc", "image", "geo", "doc", "sensor", "spectrum", "symbol", "other"];
+
+fn strings(values: &[&str]) -> Vec<String> {
+    values.iter().map(|value| value.to_string()).collect()
+}
+
+fn pairs(values: &[(&str, &str)]) -> Vec<(String, String)> {
+    values.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect()
+}
+
+impl ProfileDescriptor {
+    /// GLDF: `product.xml`, an optional `meta-information.xml` and the files of the
+    /// definition in `ldc/`, `image/`, `geo/`, `doc/` and the other GLDF folders.
+    pub fn gldf() -> ProfileDescriptor {
+        ProfileDescriptor {
+            name: "GLDF".to_string(),
+            definition_path: "product.xml".to_string(),
+            optional_entries: strings(&["meta-information.xml"]),
+            allowed_folders: strings(GLDF_FOLDERS),
+            content_types: pairs(&[
+                ("ldc/*.ldt", "ldc/eulumdat"),
+                ("ldc/*.ies", "ldc/iesna"),
+                ("ldc/*.xml", "ldc/iesxml"),
+                ("image/*.jpg", "image/jpg"),
+                ("image/*.jpeg", "image/jpg"),
+                ("image/*.png", "image/png"),
+                ("image/*.svg", "image/svg"),
+                ("geo/*.l3d", "geo/l3d"),
+                ("geo/*.m3d", "geo/m3d"),
+                ("geo/*.r3d", "geo/r3d"),
+                ("doc/*.pdf", "document/pdf"),
+                ("sensor/*.xml", "sensor/sens-xml"),
+                ("sensor/*.ldt", "sensor/sens-ldt"),
+                ("spectrum/*.txt", "spectrum/text"),
+                ("symbol/*.svg", "symbol/svg"),
+                ("other/**", "other"),
+            ]),
+            #[cfg(feature = "json")]
+            reference_rules: Some(ReferenceRules {
+                expressions: strings(&["//GeneralDefinitions/Files/File/text()"]),
+                directories: strings(GLDF_FOLDERS),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    /// L3D: `structure.xml` and at least one OBJ geometry, each geometry with its
+    /// materials and textures in a top-level folder.
+    pub fn l3d() -> ProfileDescriptor {
+        ProfileDescriptor {
+            name: "L3D".to_string(),
+            definition_path: "structure.xml".to_string(),
+            required_entries: strings(&["*/*.obj"]),
+            allowed_folders: strings(&["*"]),
+            content_types: pairs(&[
+                ("*/*.obj", "model/obj"),
+                ("*/*.mtl", "model/mtl"),
+                ("*/*.png", "image/png"),
+                ("*/*.jpg", "image/jpeg"),
+                ("*/*.jpeg", "image/jpeg"),
+            ]),
+            #[cfg(feature = "json")]
+            reference_rules: Some(ReferenceRules {
+                expressions: strings(&["//GeometryFileDefinition/@filename"]),
+                directories: strings(&["*"]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+}
+
+impl ContainerProfile for ProfileDescriptor {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn definition_path(&self) -> &str {
+        &self.definition_path
+    }
+
+    fn required_entries(&self) -> &[String] {
+        &self.required_entries
+    }
+
+    fn optional_entries(&self) -> &[String] {
+        &self.optional_entries
+    }
+
+    fn allowed_folders(&self) -> &[String] {
+        &self.allowed_folders
+    }
+
+    fn content_types(&self) -> &[(String, String)] {
+        &self.content_types
+    }
+
+    fn mimetype(&self) -> Option<&str> {
+        self.mimetype.as_deref()
+    }
+
+    #[cfg(feature = "json")]
+    fn reference_rules(&self) -> Option<ReferenceRules> {
+        self.reference_rules.clone()
+    }
+}
+
+/// An archive entry as seen by `check_layout`.
+pub(crate) struct LayoutEntry {
+    pub(crate) name: String,
+    pub(crate) stored: bool,
+}
+
+/// Checks `entries`, in archive order, against `profile`. `mimetype` is the content of the
+/// `mimetype` entry, if there is one.
+pub(crate) fn check_layout(profile: &dyn ContainerProfile, entries: &[LayoutEntry], mimetype: Option<&str>) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let problem = |path: &str, message: String| Diagnostic { path: path.to_string(), message, line: None, column: None };
+    let files: Vec<&str> = entries.iter().map(|entry| entry.name.as_str()).filter(|name| !name.ends_with('/')).collect();
+
+    if let Some(expected) = profile.mimetype() {
+        match entries.iter().position(|entry| entry.name == "mimetype") {
+            None => diagnostics.push(problem("mimetype", "the mimetype entry is missing".to_string())),
+            Some(index) => {
+                if index != 0 {
+                    diagnostics.push(problem("mimetype", "the mimetype entry must be the first entry".to_string()));
+                }
+                if !entries[index].stored {
+                    diagnostics.push(problem("mimetype", "the mimetype entry must be stored uncompressed".to_string()));
+                }
+                if mimetype != Some(expected) {
+                    diagnostics.push(problem("mimetype", format!("the mimetype must be '{}'", expected)));
+                }
+            }
+        }
+    }
+    if !files.contains(&profile.definition_path()) {
+        diagnostics.push(problem(profile.definition_path(), "the definition file is missing".to_string()));
+    }
+    for pattern in profile.required_entries() {
+        if !files.iter().any(|file| glob_matches(pattern, file)) {
+            diagnostics.push(problem(pattern, "no entry matches this required entry".to_string()));
+        }
+    }
+    for entry in entries {
+        let name = entry.name.as_str();
+        let allowed = name == profile.definition_path()
+            || (name == "mimetype" && profile.mimetype().is_some())
+            || profile.required_entries().iter().chain(profile.optional_entries()).any(|pattern| glob_matches(pattern, name))
+            || name.split_once('/').is_some_and(|(folder, _)| {
+                profile.allowed_folders().iter().any(|pattern| glob_matches(pattern, folder))
+            });
+        if !allowed {
+            diagnostics.push(problem(name, format!("the entry is not allowed in a {} container", profile.name())));
+        }
+    }
+    diagnostics
+}
+
+/// Returns whether the glob `pattern` matches the whole of `path`.
+pub(crate) fn glob_matches(pattern: &str, path: &str) -> bool {
+    fn matches(pattern: &[char], path: &[char]) -> bool {
+        match pattern {
+            [] => path.is_empty(),
+            // `**/` matches nothing or whole folders.
+            ['*', '*', '/', rest @ ..] => {
+                matches(rest, path) || (0..path.len()).any(|i| path[i] == '/' && matches(rest, &path[i + 1..]))
+            }
+            ['*', '*', rest @ ..] => (0..=path.len()).any(|skip| matches(rest, &path[skip..])),
+            ['*', rest @ ..] => {
+                let folder_end = path.iter().position(|&c| c == '/').unwrap_or(path.len());
+                (0..=folder_end).any(|skip| matches(rest, &path[skip..]))
+            }
+            ['?', rest @ ..] => path.first().is_some_and(|&c| c != '/') && matches(rest, &path[1..]),
+            [c, rest @ ..] => path.first() == Some(c) && matches(rest, &path[1..]),
+        }
+    }
+    let pattern: Vec<char> = pattern.chars().collect();
+    let path: Vec<char> = path.chars().collect();
+    matches(&pattern, &path)
+}
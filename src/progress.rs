@@ -0,0 +1,149 @@
+// src/progress.rs
+//! Download progress reporting and cancellation for the HTTP loaders.
+use crate::{function_path, ZipContainerError, ZipContainerResult};
+use futures::future::{select, Either};
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+/// Progress of a download.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Progress {
+    /// Bytes of the body received so far.
+    pub received: u64,
+    /// Total size of the body if the server announced it (`Content-Length`).
+    pub total: Option<u64>,
+}
+
+/// Callback invoked with the download progress after every received chunk.
+#[derive(Clone)]
+pub struct ProgressHandler(Arc<dyn Fn(Progress) + Send + Sync>);
+
+impl ProgressHandler {
+    pub fn new(callback: impl Fn(Progress) + Send + Sync + 'static) -> Self {
+        ProgressHandler(Arc::new(callback))
+    }
+
+    pub fn report(&self, received: u64, total: Option<u64>) {
+        (self.0)(Progress { received, total })
+    }
+}
+
+impl fmt::Debug for ProgressHandler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProgressHandler")
+    }
+}
+
+impl PartialEq for ProgressHandler {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+#[derive(Default)]
+struct CancellationState {
+    cancelled: AtomicBool,
+    wakers: Mutex<Vec<Waker>>,
+}
+
+/// Token to abort running downloads. Clones share the same state, so the UI can keep
+/// one clone and hand the other to the loader through `HttpOptions::cancellation`.
+#[derive(Clone, Default)]
+pub struct CancellationToken(Arc<CancellationState>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels all downloads using this token.
+    pub fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::SeqCst);
+        let wakers = std::mem::take(&mut *self.0.wakers.lock().unwrap_or_else(|e| e.into_inner()));
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Returns a future that completes once the token is cancelled.
+    pub fn cancelled(&self) -> Cancelled {
+        Cancelled(self.clone())
+    }
+
+    /// Returns `ZipContainerError::Cancelled` if the token has been cancelled.
+    pub fn check(&self, url: &str) -> ZipContainerResult<()> {
+        if self.is_cancelled() {
+            return Err(cancelled_error(url));
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for CancellationToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("CancellationToken")
+            .field(&self.is_cancelled())
+            .finish()
+    }
+}
+
+impl PartialEq for CancellationToken {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// Future returned by `CancellationToken::cancelled`.
+pub struct Cancelled(CancellationToken);
+
+impl Future for Cancelled {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.0.is_cancelled() {
+            return Poll::Ready(());
+        }
+        let mut wakers = self.0 .0.wakers.lock().unwrap_or_else(|e| e.into_inner());
+        // Re-check under the lock, `cancel` may have drained the wakers meanwhile
+        if self.0.is_cancelled() {
+            return Poll::Ready(());
+        }
+        if !wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+            wakers.push(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+}
+
+/// Runs `future` unless `token` is cancelled first.
+pub(crate) async fn cancellable<F: Future>(
+    token: Option<&CancellationToken>,
+    future: F,
+    url: &str,
+) -> ZipContainerResult<F::Output> {
+    match token {
+        Some(token) => {
+            token.check(url)?;
+            match select(Box::pin(future), token.cancelled()).await {
+                Either::Left((output, _)) => Ok(output),
+                Either::Right(_) => Err(cancelled_error(url)),
+            }
+        }
+        None => Ok(future.await),
+    }
+}
+
+pub(crate) fn cancelled_error(url: &str) -> ZipContainerError {
+    ZipContainerError::Cancelled {
+        module_path: function_path!(),
+        message: format!("Download cancelled: {}", url),
+    }
+}
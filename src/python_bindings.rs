@@ -1,17 +1,104 @@
 // src/python_bindings.rs
 use pyo3::prelude::*;
-use pyo3::types::PyDict;
-use pyo3::exceptions::PyException;
+use pyo3::types::{PyDict, PyList, PyTuple, PyType};
+use pyo3::exceptions::PyKeyError;
+use pyo3::sync::GILOnceCell;
 use std::ops::Deref;
-use crate::{ZipContainer as ZipContainerRust, BufFile, ZipContainerError };
+use crate::{ZipContainer as ZipContainerRust, BufFile, Diagnostic, ZipContainerError };
 use crate::zip_container_trait::ZipContainerTrait;
+
+/// Python exceptions mirroring the variants of `ZipContainerError`.
+///
+/// All of them derive from `zip_container.ZipContainerError` and carry the attributes
+/// `code`, `entry`, `container` and `module_path` (`None` where not applicable).
+// `create_exception!` of pyo3 0.19 checks a cfg newer compilers do not know about
+#[allow(unexpected_cfgs)]
+pub mod exceptions {
+    use pyo3::create_exception;
+    use pyo3::exceptions::PyException;
+
+    create_exception!(zip_container, ZipContainerError, PyException, "Base class of all zip_container errors.");
+    create_exception!(zip_container, MissingValueError, ZipContainerError, "A required value is not set.");
+    create_exception!(zip_container, ContainerIOError, ZipContainerError, "Reading or writing a file failed.");
+    create_exception!(zip_container, InvalidDataError, ZipContainerError, "Data is malformed.");
+    create_exception!(zip_container, NetworkError, ZipContainerError, "An HTTP request failed.");
+    create_exception!(zip_container, UnsupportedOperationError, ZipContainerError, "The operation is not supported.");
+    create_exception!(zip_container, CancelledError, ZipContainerError, "A download was cancelled.");
+    create_exception!(zip_container, IntegrityError, ZipContainerError, "Data does not match the expected digest or size.");
+    create_exception!(zip_container, InvalidArchiveError, ZipContainerError, "Data is not a readable ZIP archive.");
+    create_exception!(zip_container, UnsupportedCompressionError, ZipContainerError, "An entry uses an unsupported compression method.");
+    create_exception!(zip_container, EncryptedEntryError, ZipContainerError, "An entry is encrypted.");
+    create_exception!(zip_container, LimitExceededError, ZipContainerError, "Data exceeds a configured limit.");
+    create_exception!(zip_container, DefinitionParseError, ZipContainerError, "The definition file cannot be parsed.");
+    create_exception!(zip_container, ValidationError, ZipContainerError, "The definition does not match its schema.");
+    create_exception!(zip_container, DecodeError, ZipContainerError, "Data is not valid UTF-8.");
+}
+
+/// `EntryNotFoundError` subclasses both `ZipContainerError` and `KeyError`, which
+/// `create_exception!` cannot express, so the type is created through `type()`.
+fn entry_not_found_error(py: Python<'_>) -> PyResult<&PyType> {
+    static TYPE: GILOnceCell<Py<PyType>> = GILOnceCell::new();
+    let entry_not_found = TYPE.get_or_try_init(py, || -> PyResult<Py<PyType>> {
+        let bases = PyTuple::new(
+            py,
+            [py.get_type::<exceptions::ZipContainerError>(), py.get_type::<PyKeyError>()],
+        );
+        let namespace = PyDict::new(py);
+        namespace.set_item("__module__", "zip_container")?;
+        namespace.set_item("__doc__", "An entry is not part of the archive.")?;
+        let created = py
+            .import("builtins")?
+            .getattr("type")?
+            .call1(("EntryNotFoundError", bases, namespace))?;
+        Ok(created.downcast::<PyType>()?.into())
+    })?;
+    Ok(entry_not_found.as_ref(py))
+}
+
+fn exception_type<'py>(py: Python<'py>, err: &ZipContainerError) -> PyResult<&'py PyType> {
+    Ok(match err {
+        ZipContainerError::MissingValue { .. } => py.get_type::<exceptions::MissingValueError>(),
+        ZipContainerError::IOError { .. } => py.get_type::<exceptions::ContainerIOError>(),
+        ZipContainerError::InvalidData { .. } => py.get_type::<exceptions::InvalidDataError>(),
+        #[cfg(feature = "http")]
+        ZipContainerError::ReqwestError { .. } => py.get_type::<exceptions::NetworkError>(),
+        ZipContainerError::UnsupportedOperation { .. } => py.get_type::<exceptions::UnsupportedOperationError>(),
+        ZipContainerError::Cancelled { .. } => py.get_type::<exceptions::CancelledError>(),
+        ZipContainerError::IntegrityMismatch { .. } => py.get_type::<exceptions::IntegrityError>(),
+        ZipContainerError::EntryNotFound { .. } => entry_not_found_error(py)?,
+        ZipContainerError::InvalidArchive { .. } => py.get_type::<exceptions::InvalidArchiveError>(),
+        ZipContainerError::UnsupportedCompression { .. } => py.get_type::<exceptions::UnsupportedCompressionError>(),
+        ZipContainerError::Encrypted { .. } => py.get_type::<exceptions::EncryptedEntryError>(),
+        ZipContainerError::LimitExceeded { .. } => py.get_type::<exceptions::LimitExceededError>(),
+        ZipContainerError::DefinitionParse { .. } => py.get_type::<exceptions::DefinitionParseError>(),
+        ZipContainerError::ValidationFailed { .. } => py.get_type::<exceptions::ValidationError>(),
+        ZipContainerError::Utf8Error(_) => py.get_type::<exceptions::DecodeError>(),
+    })
+}
+
+/// Converts diagnostics into a list of dicts with the keys `path`, `message`, `line` and `column`.
+fn diagnostics_to_py<'py>(py: Python<'py>, diagnostics: &[Diagnostic]) -> PyResult<&'py PyList> {
+    let list = PyList::empty(py);
+    for diagnostic in diagnostics {
+        let item = PyDict::new(py);
+        item.set_item("path", &diagnostic.path)?;
+        item.set_item("message", &diagnostic.message)?;
+        item.set_item("line", diagnostic.line)?;
+        item.set_item("column", diagnostic.column)?;
+        list.append(item)?;
+    }
+    Ok(list)
+}
+
 // Implementing necessary conversion from ZipContainerError to PyErr
 impl From<ZipContainerError> for PyErr {
     /// Converts a `ZipContainerError` into a `PyErr`.
     ///
-    /// This function facilitates the conversion of a `ZipContainerError` into a Python
-    /// exception (`PyErr`) that can be raised in Python code. It formats the error
-    /// message from the `ZipContainerError` to be used in the Python exception.
+    /// The exception raised is the subclass of `zip_container.ZipContainerError` matching
+    /// the variant, e.g. `EntryNotFoundError` (also a `KeyError`) or `InvalidArchiveError`.
+    /// The message is the `Display` text of the error, and the `code`, `entry`,
+    /// `container` and `module_path` attributes are set on the exception instance, as well as
+    /// `diagnostics`, the problems found by schema validation (see `diagnostics_to_py`).
     ///
     /// # Parameters
     ///
@@ -19,10 +106,32 @@ impl From<ZipContainerError> for PyErr {
     ///
     /// # Returns
     ///
-    /// A `PyErr` representing a Python exception with the formatted error message from
-    /// the `ZipContainerError`.
+    /// A `PyErr` representing the matching Python exception.
     fn from(err: ZipContainerError) -> PyErr {
-        PyException::new_err(format!("{:?}", err))
+        Python::with_gil(|py| {
+            let exception_type = match exception_type(py, &err) {
+                Ok(exception_type) => exception_type,
+                Err(type_error) => return type_error,
+            };
+            let py_err = PyErr::from_type(exception_type, err.to_string());
+            let value = py_err.value(py);
+            let attributes = [
+                ("code", Some(err.code())),
+                ("entry", err.entry()),
+                ("container", err.container()),
+                ("module_path", err.module_path()),
+            ];
+            for (name, attribute) in attributes {
+                if let Err(attribute_error) = value.setattr(name, attribute) {
+                    return attribute_error;
+                }
+            }
+            let diagnostics = diagnostics_to_py(py, err.diagnostics());
+            if let Err(attribute_error) = diagnostics.and_then(|diagnostics| value.setattr("diagnostics", diagnostics)) {
+                return attribute_error;
+            }
+            py_err
+        })
     }
 }
 
@@ -192,8 +301,24 @@ impl ZipContainer {
 }
 
 #[pymodule]
-fn zip_container(_py: Python, m: &PyModule) -> PyResult<()> {
+fn zip_container(py: Python, m: &PyModule) -> PyResult<()> {
     m.add_class::<ZipContainer>()?;
+    m.add("ZipContainerError", py.get_type::<exceptions::ZipContainerError>())?;
+    m.add("MissingValueError", py.get_type::<exceptions::MissingValueError>())?;
+    m.add("ContainerIOError", py.get_type::<exceptions::ContainerIOError>())?;
+    m.add("InvalidDataError", py.get_type::<exceptions::InvalidDataError>())?;
+    m.add("NetworkError", py.get_type::<exceptions::NetworkError>())?;
+    m.add("UnsupportedOperationError", py.get_type::<exceptions::UnsupportedOperationError>())?;
+    m.add("CancelledError", py.get_type::<exceptions::CancelledError>())?;
+    m.add("IntegrityError", py.get_type::<exceptions::IntegrityError>())?;
+    m.add("EntryNotFoundError", entry_not_found_error(py)?)?;
+    m.add("InvalidArchiveError", py.get_type::<exceptions::InvalidArchiveError>())?;
+    m.add("UnsupportedCompressionError", py.get_type::<exceptions::UnsupportedCompressionError>())?;
+    m.add("EncryptedEntryError", py.get_type::<exceptions::EncryptedEntryError>())?;
+    m.add("LimitExceededError", py.get_type::<exceptions::LimitExceededError>())?;
+    m.add("DefinitionParseError", py.get_type::<exceptions::DefinitionParseError>())?;
+    m.add("ValidationError", py.get_type::<exceptions::ValidationError>())?;
+    m.add("DecodeError", py.get_type::<exceptions::DecodeError>())?;
     //m.add_class::<ZipContainer>()?;
     Ok(())
 }
\ No newline at end of file
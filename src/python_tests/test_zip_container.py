@@ -1,11 +1,16 @@
 import unittest
+from pathlib import Path
+import zip_container
 from zip_container import ZipContainer
 
+# The wheel is built without the `http` feature, so the tests use the local test container
+TEST_GLDF = str(Path(__file__).resolve().parents[2] / "test_data" / "test.gldf")
+
 class TestZipContainer(unittest.TestCase):
 
     def setUp(self):
         # Initialize a ZipContainer instance for testing
-        self.zip_container = ZipContainer("https://raw.githubusercontent.com/holg/gldf-rs/refs/heads/master/tests/data/test.gldf", "product.xml")
+        self.zip_container = ZipContainer(TEST_GLDF, "product.xml")
 
     def test_zip_data(self):
         # Test the zip_data getter
@@ -23,5 +28,45 @@ class TestZipContainer(unittest.TestCase):
         self.assertIsInstance(files, list, "Files should be returned as a list")
         self.assertGreater(len(files), 0, "There should be at least one file in the ZIP")
 
+
+class TestZipContainerErrors(unittest.TestCase):
+
+    def test_entry_not_found_is_a_key_error(self):
+        container = ZipContainer(TEST_GLDF, "missing.xml")
+        with self.assertRaises(KeyError) as raised:
+            container.load_definition_file_str
+        error = raised.exception
+        self.assertIsInstance(error, zip_container.EntryNotFoundError)
+        self.assertIsInstance(error, zip_container.ZipContainerError)
+        self.assertEqual(error.code, "ENTRY_NOT_FOUND")
+        self.assertEqual(error.entry, "missing.xml")
+        self.assertEqual(error.container, TEST_GLDF)
+        self.assertIsNotNone(error.module_path)
+        self.assertEqual(error.diagnostics, [])
+
+    def test_invalid_archive(self):
+        container = ZipContainer(TEST_GLDF, "product.xml")
+        container.zip_data = b"not a zip archive"
+        with self.assertRaises(zip_container.InvalidArchiveError) as raised:
+            container.get_zip_files()
+        error = raised.exception
+        self.assertNotIsInstance(error, KeyError)
+        self.assertEqual(error.code, "INVALID_ARCHIVE")
+        self.assertIsNone(error.entry)
+
+    def test_missing_value(self):
+        container = ZipContainer(TEST_GLDF, None)
+        container.zip_data = None
+        with self.assertRaises(zip_container.ZipContainerError) as raised:
+            container.get_zip_files()
+        self.assertIsInstance(raised.exception, zip_container.MissingValueError)
+        self.assertEqual(raised.exception.code, "MISSING_VALUE")
+
+    def test_hierarchy(self):
+        for name in ("MissingValueError", "InvalidArchiveError", "NetworkError", "ValidationError"):
+            self.assertTrue(issubclass(getattr(zip_container, name), zip_container.ZipContainerError), name)
+        self.assertTrue(issubclass(zip_container.EntryNotFoundError, KeyError))
+        self.assertTrue(issubclass(zip_container.ZipContainerError, Exception))
+
 if __name__ == '__main__':
     unittest.main()
\ No newline at end of file
@@ -0,0 +1,88 @@
+// src/query.rs
+//! Queries on definitions. Requires the `json` feature, XPath also the `xml` feature.
+//!
+//! Expressions starting with `$` are JSONPath and run on the document tree of any
+//! definition (for XML the mapping described in `document`). Anything else is XPath and
+//! runs on XML definitions only.
+//!
+//! * XPath 1.0 location paths: `/`, `//`, relative paths, `.`, `..`, `*`, `@name`, `@*`,
+//!   `text()`, `node()`, unions with `|` and predicates with positions, `last()`,
+//!   `position()`, comparisons (`=`, `!=`, `<`, `<=`, `>`, `>=`), `and`, `or` and `not()`.
+//!   Names without a prefix match the local name. Other functions and axes are rejected.
+//! * JSONPath (RFC 9535): `$`, `.name`, `['name']`, `[index]` (negative from the end), `*`,
+//!   `..` descendants, `[a,b]` unions, `[start:end:step]` slices and filters like
+//!   `[?@.price < 10 && @.name]` (also written `[?(...)]`). Functions are rejected.
+//!
+//! Elements and values that are objects or arrays are returned as subtrees, everything
+//! else as a string.
+use crate::{function_path, jsonpath, Definition, ZipContainerError, ZipContainerResult};
+use serde_json::Value;
+
+/// A value matched by `Definition::query`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum QueryMatch {
+    /// A string, number, boolean, attribute, text or element with only text. `null` and
+    /// empty elements are empty strings.
+    Value(String),
+    /// An object, array or element with attributes or children, as a document tree.
+    Tree(Value),
+}
+
+impl QueryMatch {
+    pub(crate) fn from_value(value: &Value) -> QueryMatch {
+        match value {
+            Value::Null => QueryMatch::Value(String::new()),
+            Value::String(text) => QueryMatch::Value(text.clone()),
+            Value::Bool(_) | Value::Number(_) => QueryMatch::Value(value.to_string()),
+            Value::Array(_) | Value::Object(_) => QueryMatch::Tree(value.clone()),
+        }
+    }
+
+    /// Returns the string of a `Value` match.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            QueryMatch::Value(text) => Some(text),
+            QueryMatch::Tree(_) => None,
+        }
+    }
+
+    /// Returns the match as a document tree, a `Value` match as a JSON string.
+    pub fn into_value(self) -> Value {
+        match self {
+            QueryMatch::Value(text) => Value::String(text),
+            QueryMatch::Tree(tree) => tree,
+        }
+    }
+}
+
+impl Definition {
+    /// Runs an XPath or JSONPath expression and returns the matches in document order.
+    /// Fails with `InvalidData` for an invalid expression or definition and with
+    /// `UnsupportedOperation` for XPath on a definition that is not XML.
+    pub fn query(&self, expression: &str) -> ZipContainerResult<Vec<QueryMatch>> {
+        if expression.trim_start().starts_with('$') {
+            return jsonpath::query(&self.to_document()?, expression);
+        }
+        match self {
+            #[cfg(feature = "xml")]
+            Definition::XML(content) => crate::xpath::query(content, expression),
+            #[cfg(not(feature = "xml"))]
+            Definition::XML(_) => Err(crate::definition_format::feature_disabled("xml")),
+            _ => Err(ZipContainerError::UnsupportedOperation {
+                module_path: function_path!(),
+                message: format!(
+                    "XPath needs an XML definition, query {} definitions with a JSONPath starting with '$'",
+                    self.format_name()
+                ),
+            }),
+        }
+    }
+
+    /// Returns the string of the first match that is not a subtree, if any.
+    pub fn query_value(&self, expression: &str) -> ZipContainerResult<Option<String>> {
+        Ok(self.query(expression)?.into_iter().find_map(|found| match found {
+            QueryMatch::Value(text) => Some(text),
+            QueryMatch::Tree(_) => None,
+        }))
+    }
+}
@@ -0,0 +1,96 @@
+// src/references.rs
+//! Integrity of the file references of a definition: which referenced files are in the
+//! archive, which are missing, which are only reachable through the loader and which
+//! archive entries nothing refers to. Requires the `json` feature.
+use crate::loader_registry::scheme_of;
+use crate::QueryMatch;
+
+/// XPath expressions of the references in GLDF (`product.xml`) and L3D (`structure.xml`).
+pub const DEFAULT_REFERENCE_EXPRESSIONS: &[&str] =
+    &["//GeneralDefinitions/Files/File/text()", "//GeometryFileDefinition/@filename"];
+
+/// Directories searched for references given by file name only: the GLDF content folders
+/// and, for L3D, any top-level directory.
+pub const DEFAULT_REFERENCE_DIRECTORIES: &[&str] =
+    &["ldc", "image", "geo", "doc", "sensor", "spectrum", "symbol", "other", "*"];
+
+/// Where `check_references` finds references and how it resolves them to archive entries.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReferenceRules {
+    /// XPath or JSONPath expressions (see `query`) whose string matches are references.
+    pub expressions: Vec<String>,
+    /// Directories tried, in order, after the archive root for a reference that is not an
+    /// entry itself; `*` stands for any top-level directory.
+    pub directories: Vec<String>,
+    /// Entries never reported as orphans, besides the definition file.
+    pub ignored: Vec<String>,
+    /// Whether references outside the archive are checked with `FileLoader::exists` of the
+    /// loader (an HTTP `HEAD` request for URLs) to tell external ones from dangling ones.
+    /// Otherwise URLs count as external and paths as dangling.
+    pub probe_external: bool,
+}
+
+impl Default for ReferenceRules {
+    fn default() -> Self {
+        ReferenceRules {
+            expressions: DEFAULT_REFERENCE_EXPRESSIONS.iter().map(|e| e.to_string()).collect(),
+            directories: DEFAULT_REFERENCE_DIRECTORIES.iter().map(|d| d.to_string()).collect(),
+            ignored: Vec::new(),
+            probe_external: false,
+        }
+    }
+}
+
+/// The result of `check_references`, each list in definition or archive order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ReferenceReport {
+    /// References with the archive entry they resolve to.
+    pub resolved: Vec<(String, String)>,
+    /// References that are neither in the archive nor loadable.
+    pub dangling: Vec<String>,
+    /// References that are not in the archive and resolve only through the loader, the
+    /// URL fallback of `load_file`.
+    pub external: Vec<String>,
+    /// Archive entries no reference resolves to.
+    pub orphans: Vec<String>,
+}
+
+impl ReferenceReport {
+    /// Returns whether every reference is in the archive and every entry is referenced.
+    pub fn is_clean(&self) -> bool {
+        self.dangling.is_empty() && self.external.is_empty() && self.orphans.is_empty()
+    }
+}
+
+/// Returns the distinct non-empty string matches, in order.
+pub(crate) fn collect_references(matches: impl IntoIterator<Item = QueryMatch>) -> Vec<String> {
+    let mut references: Vec<String> = Vec::new();
+    for found in matches {
+        let Some(reference) = found.as_str().map(str::trim) else { continue };
+        if !reference.is_empty() && !references.iter().any(|r| r == reference) {
+            references.push(reference.to_string());
+        }
+    }
+    references
+}
+
+/// Returns the entry of `entries` that `reference` names, directly or in one of `directories`.
+pub(crate) fn resolve<'e>(reference: &str, entries: &'e [String], directories: &[String]) -> Option<&'e String> {
+    let path = reference.trim_start_matches("./").trim_start_matches('/');
+    if let Some(entry) = entries.iter().find(|entry| *entry == path) {
+        return Some(entry);
+    }
+    directories.iter().find_map(|directory| {
+        let directory = directory.trim_matches('/');
+        entries.iter().find(|entry| match entry.strip_suffix(path).and_then(|e| e.strip_suffix('/')) {
+            Some(parent) if directory == "*" => !parent.is_empty() && !parent.contains('/'),
+            Some(parent) => parent == directory,
+            None => false,
+        })
+    })
+}
+
+/// Returns whether `reference` is a URL rather than a path.
+pub(crate) fn is_url(reference: &str) -> bool {
+    scheme_of(reference).is_some()
+}
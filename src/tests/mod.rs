@@ -1,12 +1,93 @@
 // src/tests.rs
 #[cfg(test)]
 #[cfg(not(target_arch = "wasm32"))]
+#[allow(clippy::module_inception)]
 mod tests {
-    use crate::{BufFile, Definition, ZipContainer, ZipContainerTrait};
+    use crate::{BufFile, Definition, FileLoader, LoaderRegistry, ZipContainer, ZipContainerError, ZipContainerTrait};
+    use crate::data_url::{DataUrl, DEFAULT_MEDIA_TYPE};
+    use crate::loader_registry::scheme_of;
+    #[cfg(feature = "http")]
+    use crate::http_cache::HttpCache;
+    use crate::loaders::{DirectoryFileLoader, MemoryFileLoader};
+    #[cfg(feature = "http")]
+    use crate::loaders::HttpFileLoader;
+    use crate::AsyncFileLoader;
+    #[cfg(feature = "http")]
+    use crate::{CancellationToken, HttpAuth, HttpOptions, Progress, ProgressHandler, RetryPolicy};
+    use crate::ExpectedIntegrity;
+    use crate::integrity::sha256_hex;
+    #[cfg(feature = "http")]
+    use std::sync::{Arc, Mutex};
+    #[cfg(feature = "http")]
+    use std::time::Duration;
     // use super::*;
     // use std::path::Path;
 
+    /// Builds a ZIP with the given stored entries.
+    fn zip_with(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        use std::io::Write;
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        let options = zip::write::SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        for (name, content) in entries {
+            writer.start_file(*name, options).unwrap();
+            writer.write_all(content).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    /// Builds a ZIP with a single stored `a.txt` and patches the general purpose flags
+    /// and compression method of its local and central directory headers.
+    fn patched_zip(flags: u16, method: u16) -> Vec<u8> {
+        let mut data = zip_with(&[("a.txt", b"hello")]);
+        let find = |signature: &[u8]| data.windows(4).position(|window| window == signature).unwrap();
+        let (local, central) = (find(b"PK\x03\x04"), find(b"PK\x01\x02"));
+        data[local + 6..local + 8].copy_from_slice(&flags.to_le_bytes());
+        data[local + 8..local + 10].copy_from_slice(&method.to_le_bytes());
+        data[central + 8..central + 10].copy_from_slice(&flags.to_le_bytes());
+        data[central + 10..central + 12].copy_from_slice(&method.to_le_bytes());
+        data
+    }
+
+    /// Serves the given raw HTTP responses, one per connection, on a local port
+    /// and returns the base URL plus a handle yielding the received requests.
+    #[cfg(feature = "http")]
+    fn serve_http(responses: Vec<String>) -> (String, std::thread::JoinHandle<Vec<String>>) {
+        use std::io::{Read, Write};
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            let mut requests = Vec::new();
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = Vec::new();
+                let mut buffer = [0u8; 4096];
+                while !request.ends_with(b"\r\n\r\n") {
+                    let read = stream.read(&mut buffer).unwrap();
+                    if read == 0 {
+                        break;
+                    }
+                    request.extend_from_slice(&buffer[..read]);
+                }
+                requests.push(String::from_utf8_lossy(&request).to_lowercase());
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+            requests
+        });
+        (url, handle)
+    }
+
+    #[cfg(feature = "http")]
+    fn http_response(status: &str, body: &str) -> String {
+        format!(
+            "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        )
+    }
+
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn test_zip_data_not_set() {
         let zip_container = ZipContainer::default();
         let result = zip_container.zip_data();
@@ -19,6 +100,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn test_definition_path_not_set() {
         let zip_container = ZipContainer::default();
         let result = zip_container.definition_path();
@@ -39,7 +121,6 @@ mod tests {
         assert!(matches!(zip_container.definition_content, Some(Definition::XML(_))));
     }
 
-
     #[test]
     fn test_initialize_zip_container_with_default_values() {
         let zip_container = ZipContainer::default();
@@ -61,6 +142,7 @@ mod tests {
         assert_eq!(zip_container.definition_path, definition_path);
         assert!(matches!(zip_container.definition_content, Some(Definition::JSON(_))));
     }
+
     #[test]
     fn test_initialize_zip_container_with_unsupported_definition_path() {
         let zip_path = String::from("test.zip");
@@ -124,6 +206,1428 @@ mod tests {
         assert_eq!(toml_definition.as_ref(), "toml content");
     }
 
+    #[test]
+    fn test_scheme_of() {
+        assert_eq!(scheme_of("https://example.com/x.gldf").as_deref(), Some("https"));
+        assert_eq!(scheme_of("S3://bucket/x.gldf").as_deref(), Some("s3"));
+        assert_eq!(scheme_of("data:text/plain,hi").as_deref(), Some("data"));
+        assert_eq!(scheme_of("C:\\containers\\x.gldf"), None);
+        assert_eq!(scheme_of("ldc/diffuse.ldt"), None);
+    }
+
+    #[test]
+    fn test_loader_registry_mem_scheme() {
+        let memory = MemoryFileLoader::new();
+        memory.insert("mem://catalogue/test.gldf", include_bytes!("../../test_data/test.gldf").to_vec());
+        let mut loader = LoaderRegistry::default();
+        loader.register("mem", memory);
+
+        let zip_container = ZipContainer::with_loader(
+            "mem://catalogue/test.gldf".to_string(),
+            Some("product.xml".to_string()),
+            loader,
+        );
+        assert!(zip_container.get_file_names().unwrap().contains(&"ldc/diffuse.ldt".to_string()));
+        assert!(zip_container.load_definition_file_str().unwrap().contains("<Root"));
+        assert!(zip_container.load_file("mem://missing").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_loader_registry_custom_scheme_with_directory_standin() {
+        let root = std::env::temp_dir().join(format!("zip_container_s3_{}", std::process::id()));
+        std::fs::create_dir_all(root.join("bucket")).unwrap();
+        std::fs::write(root.join("bucket/logo.txt"), b"logo").unwrap();
+
+        let mut loader = LoaderRegistry::default();
+        assert!(!loader.handles("s3://bucket/logo.txt"));
+        loader.register("s3", DirectoryFileLoader::new(&root));
+        assert!(loader.handles("s3://bucket/logo.txt"));
+        assert_eq!(loader.load("s3://bucket/logo.txt").unwrap(), b"logo");
+        assert!(loader.load("s3://bucket/../../etc/passwd").is_err());
+
+        let zip_container = ZipContainer { loader, ..Default::default() };
+        let data = zip_container.load_file_async("s3://bucket/logo.txt").await.unwrap();
+        assert_eq!(data, b"logo");
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_loader_registry_unknown_scheme() {
+        let loader = LoaderRegistry::default();
+        let result = loader.load("ftp://example.com/x.gldf");
+        assert!(matches!(result, Err(ZipContainerError::UnsupportedOperation { .. })));
+    }
+
+    #[test]
+    fn test_data_url_parse() {
+        let png = DataUrl::parse("data:image/png;base64,iVBORw0KGgo=").unwrap();
+        assert_eq!(png.media_type, "image/png");
+        assert_eq!(png.data, vec![0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a]);
+
+        let text = DataUrl::parse("data:,A%20brief%20note").unwrap();
+        assert_eq!(text.media_type, DEFAULT_MEDIA_TYPE);
+        assert_eq!(text.data, b"A brief note");
+
+        let charset = DataUrl::parse("data:;charset=utf-8,%C3%BC").unwrap();
+        assert_eq!(charset.media_type, "text/plain;charset=utf-8");
+        assert_eq!(charset.data, "ü".as_bytes());
+        assert_eq!(DataUrl::parse("data:,%+1%-f%4").unwrap().data, b"%+1%-f%4");
+
+        assert!(DataUrl::parse("data:image/png;base64").is_err());
+        assert!(DataUrl::parse("data:image/png;base64,!!!").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_load_data_url_through_container() {
+        let zip_container = ZipContainer {
+            zip_data: Some(include_bytes!("../../test_data/test.gldf").to_vec()),
+            ..Default::default()
+        };
+        let url = "data:text/plain;base64,aGVsbG8=";
+        assert_eq!(zip_container.load_file(url).unwrap(), b"hello");
+        assert_eq!(zip_container.load_file_async(url).await.unwrap(), b"hello");
+
+        let buf_file = zip_container.load_buf_file_async(url).await.unwrap();
+        assert_eq!(buf_file.content_type.as_deref(), Some("text/plain"));
+        assert_eq!(buf_file.content.as_deref(), Some(&b"hello"[..]));
+        assert_eq!(buf_file.size, Some(5));
+
+        let from_zip = zip_container.load_buf_file("ldc/diffuse.ldt").unwrap();
+        assert_eq!(from_zip.name.as_deref(), Some("ldc/diffuse.ldt"));
+        assert_eq!(from_zip.size, Some(623));
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn test_http_options_headers_auth_and_retry() {
+        let (url, server) = serve_http(vec![
+            http_response("503 Service Unavailable", ""),
+            http_response("200 OK", "hello"),
+        ]);
+        let mut options = HttpOptions {
+            headers: vec![("X-Api-Key".to_string(), "secret".to_string())],
+            auth: Some(HttpAuth::Bearer("token".to_string())),
+            user_agent: Some("catalogue-sync/1.0".to_string()),
+            timeout: Some(Duration::from_secs(5)),
+            ..Default::default()
+        };
+        options.retry.max_retries = 2;
+        options.retry.initial_backoff = Duration::from_millis(10);
+        let mut loader = LoaderRegistry::default();
+        loader.register_http(options);
+
+        assert_eq!(loader.load(&format!("{}/x.gldf", url)).unwrap(), b"hello");
+        let requests = server.join().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[1].contains("authorization: bearer token"));
+        assert!(requests[1].contains("x-api-key: secret"));
+        assert!(requests[1].contains("user-agent: catalogue-sync/1.0"));
+    }
+
+    #[cfg(feature = "http")]
+    #[tokio::test]
+    async fn test_http_options_max_body_size_and_status() {
+        let (url, server) = serve_http(vec![
+            http_response("200 OK", "0123456789abcdef"),
+            http_response("404 Not Found", "missing"),
+        ]);
+        let options = HttpOptions { max_body_size: Some(8), ..Default::default() };
+        let loader = HttpFileLoader::new(options);
+
+        let too_large = loader.load_async(&format!("{}/big", url)).await;
+        assert!(matches!(too_large, Err(ZipContainerError::LimitExceeded { .. })));
+        let not_found = loader.load_async(&format!("{}/missing", url)).await;
+        assert!(matches!(not_found, Err(ZipContainerError::ReqwestError { .. })));
+        server.join().unwrap();
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn test_retry_policy_and_basic_auth() {
+        let retry = RetryPolicy {
+            max_retries: 5,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(350),
+        };
+        assert_eq!(retry.backoff(0), Duration::from_millis(100));
+        assert_eq!(retry.backoff(1), Duration::from_millis(200));
+        assert_eq!(retry.backoff(2), Duration::from_millis(350));
+        assert_eq!(retry.backoff(40), Duration::from_millis(350));
+
+        let auth = HttpAuth::Basic { username: "user".to_string(), password: Some("pass".to_string()) };
+        assert_eq!(auth.header_value(), "Basic dXNlcjpwYXNz");
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn test_http_cache_revalidation_and_offline_mode() {
+        let cache_dir = std::env::temp_dir().join(format!("zip_container_cache_{}", std::process::id()));
+        let (url, server) = serve_http(vec![
+            "HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nContent-Length: 7\r\nConnection: close\r\n\r\nv1-body".to_string(),
+            "HTTP/1.1 304 Not Modified\r\nETag: \"v1\"\r\nConnection: close\r\n\r\n".to_string(),
+        ]);
+        let url = format!("{}/catalogue/x.gldf", url);
+        let online = HttpFileLoader::new(HttpOptions {
+            cache: Some(HttpCache::new(&cache_dir)),
+            ..Default::default()
+        });
+        assert_eq!(online.load(&url).unwrap(), b"v1-body");
+        assert_eq!(online.load(&url).unwrap(), b"v1-body");
+        let requests = server.join().unwrap();
+        assert!(!requests[0].contains("if-none-match"));
+        assert!(requests[1].contains("if-none-match: \"v1\""));
+
+        let offline = HttpFileLoader::new(HttpOptions {
+            cache: Some(HttpCache::offline(&cache_dir)),
+            ..Default::default()
+        });
+        assert_eq!(offline.load(&url).unwrap(), b"v1-body");
+        assert!(offline.load("http://127.0.0.1:9/not-cached.gldf").is_err());
+        std::fs::remove_dir_all(cache_dir).unwrap();
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn test_http_download_progress() {
+        let (url, server) = serve_http(vec![http_response("200 OK", "0123456789")]);
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let sink = reports.clone();
+        let loader = HttpFileLoader::new(HttpOptions {
+            progress: Some(ProgressHandler::new(move |progress| sink.lock().unwrap().push(progress))),
+            ..Default::default()
+        });
+        assert_eq!(loader.load(&url).unwrap(), b"0123456789");
+        server.join().unwrap();
+        let reports = reports.lock().unwrap();
+        assert_eq!(reports.last(), Some(&Progress { received: 10, total: Some(10) }));
+    }
+
+    #[cfg(feature = "http")]
+    #[tokio::test]
+    async fn test_http_download_cancellation() {
+        // A server that accepts the connection but never answers
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/slow.gldf", listener.local_addr().unwrap());
+        let token = CancellationToken::new();
+        let loader = HttpFileLoader::new(HttpOptions {
+            cancellation: Some(token.clone()),
+            ..Default::default()
+        });
+        let canceller = token.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            canceller.cancel();
+        });
+        let result = loader.load_async(&url).await;
+        assert!(matches!(result, Err(ZipContainerError::Cancelled { .. })));
+        // Already cancelled tokens stop the blocking loader before any request
+        assert!(matches!(loader.load(&url), Err(ZipContainerError::Cancelled { .. })));
+        drop(listener);
+    }
+
+    #[test]
+    fn test_expected_integrity_formats() {
+        let hex = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+        assert_eq!(sha256_hex(b"hello"), hex);
+        assert_eq!(ExpectedIntegrity::sha256(&hex.to_uppercase()).unwrap().sha256, hex);
+        let sri = ExpectedIntegrity::sha256("sha256-LPJNul+wow4m6DsqxbninhsWHlwfp0JecwQzYpOLmCQ=").unwrap();
+        assert_eq!(sri.sha256, hex);
+        assert!(ExpectedIntegrity::sha256("abc").is_err());
+        assert!(ExpectedIntegrity::sha256("sha256-not base64").is_err());
+
+        let expected = sri.with_size(5);
+        assert!(expected.verify(b"hello", "mem://x").is_ok());
+        assert!(matches!(
+            expected.verify(b"hello!", "mem://x"),
+            Err(ZipContainerError::IntegrityMismatch { expected, .. }) if expected == "5 bytes"
+        ));
+        assert!(matches!(
+            expected.verify(b"HELLO", "mem://x"),
+            Err(ZipContainerError::IntegrityMismatch { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_new_verified_container() {
+        let data = include_bytes!("../../test_data/test.gldf").to_vec();
+        let expected = ExpectedIntegrity::sha256(&sha256_hex(&data)).unwrap().with_size(data.len() as u64);
+        let memory = MemoryFileLoader::new();
+        memory.insert("mem://test.gldf", data);
+        let mut loader = LoaderRegistry::default();
+        loader.register("mem", memory.clone());
+
+        assert!(loader.load_verified_async("mem://test.gldf", &expected).await.is_ok());
+        let zip_container = ZipContainer::new_verified(
+            "mem://test.gldf".to_string(),
+            Some("product.xml".to_string()),
+            loader.clone(),
+            &expected,
+        )
+        .unwrap();
+        assert!(zip_container.verify_integrity(&expected).is_ok());
+        assert!(zip_container.load_definition_file_str().unwrap().contains("<Root"));
+
+        // A swapped package is rejected instead of being opened
+        memory.insert("mem://test.gldf", b"PK tampered".to_vec());
+        let result = ZipContainer::new_verified("mem://test.gldf".to_string(), None, loader, &expected);
+        assert!(matches!(result, Err(ZipContainerError::IntegrityMismatch { .. })));
+    }
+
+    #[test]
+    fn test_http_schemes_follow_http_feature() {
+        let loader = LoaderRegistry::default();
+        assert_eq!(loader.handles("https://example.com/x.gldf"), cfg!(feature = "http"));
+        assert_eq!(loader.schemes().contains(&"http".to_string()), cfg!(feature = "http"));
+        assert!(loader.handles("data:,x") && loader.handles("/tmp/x.gldf"));
+    }
+
+    #[test]
+    fn test_error_taxonomy_for_archives() {
+        let zip_container = ZipContainer::new("test_data/test.gldf".to_string(), None);
+        let missing = zip_container.load_file_from_zip("missing.xml").unwrap_err();
+        assert!(matches!(missing, ZipContainerError::EntryNotFound { .. }));
+        assert_eq!(missing.code(), "ENTRY_NOT_FOUND");
+        assert_eq!(missing.entry(), Some("missing.xml"));
+        assert_eq!(missing.container(), Some("test_data/test.gldf"));
+
+        let not_a_zip = ZipContainer { zip_data: Some(b"not a zip".to_vec()), ..Default::default() };
+        let invalid = not_a_zip.get_file_names().unwrap_err();
+        assert_eq!(invalid.code(), "INVALID_ARCHIVE");
+        assert_eq!(invalid.container(), Some(crate::IN_MEMORY_SOURCE));
+
+        let encrypted = ZipContainer { zip_data: Some(patched_zip(1, 0)), ..Default::default() };
+        let error = encrypted.load_file_from_zip("a.txt").unwrap_err();
+        assert!(matches!(error, ZipContainerError::Encrypted { .. }), "{:?}", error);
+        assert_eq!(error.entry(), Some("a.txt"));
+
+        let bzip2 = ZipContainer { zip_data: Some(patched_zip(0, 12)), ..Default::default() };
+        let error = bzip2.load_file_from_zip("a.txt").unwrap_err();
+        assert_eq!(error.code(), "UNSUPPORTED_COMPRESSION", "{:?}", error);
+        assert_eq!(bzip2.get_file_names().unwrap(), ["a.txt"]);
+    }
+
+    #[tokio::test]
+    async fn test_load_file_missing_entry() {
+        let zip_container = ZipContainer::new("test_data/test.gldf".to_string(), Some("product.xml".to_string()));
+        let error = zip_container.load_file("ldc/missing.ldt").unwrap_err();
+        assert!(matches!(error, ZipContainerError::EntryNotFound { .. }), "{:?}", error);
+        assert_eq!((error.entry(), error.container()), (Some("ldc/missing.ldt"), Some("test_data/test.gldf")));
+        let error = zip_container.load_file_async("ldc/missing.ldt").await.unwrap_err();
+        assert_eq!(error.code(), "ENTRY_NOT_FOUND");
+        assert_eq!(zip_container.load_buf_file("missing").unwrap_err().code(), "ENTRY_NOT_FOUND");
+    }
+
+    #[test]
+    fn test_definition_parse_error() {
+        let memory = MemoryFileLoader::new();
+        memory.insert("mem://broken.xml", vec![0xff, 0xfe, 0x00]);
+        let mut loader = LoaderRegistry::default();
+        loader.register("mem", memory);
+        let zip_container = ZipContainer {
+            zip_data: Some(include_bytes!("../../test_data/test.gldf").to_vec()),
+            definition_path: Some("mem://broken.xml".to_string()),
+            loader,
+            ..Default::default()
+        };
+        let error = zip_container.load_definition_file_str().unwrap_err();
+        assert_eq!(error.code(), "DEFINITION_PARSE");
+        assert_eq!(error.entry(), Some("mem://broken.xml"));
+        assert!(error.module_path().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_logger_receives_container_events() {
+        let events = std::sync::Arc::new(std::sync::Mutex::new(Vec::<String>::new()));
+        let sink = events.clone();
+        let zip_container = ZipContainer::with_logger(
+            "test_data/test.gldf".to_string(),
+            None,
+            LoaderRegistry::default(),
+            move |message: &str| sink.lock().unwrap().push(message.to_string()),
+        );
+        zip_container.get_file_names().unwrap();
+        zip_container.load_file("ldc/diffuse.ldt").unwrap();
+        zip_container.load_file_async("data:,x").await.unwrap();
+
+        let events = events.lock().unwrap();
+        assert!(events[0].starts_with("[INFO] test_data/test.gldf: opened source ("), "{:?}", events);
+        assert!(events.iter().any(|event| event.ends_with("listed 4 entries")));
+        assert!(events.iter().any(|event| event.contains("read 623 bytes from 'ldc/diffuse.ldt' in ")));
+        assert!(events.iter().any(|event| event.contains("'data:,x' is not in the archive, loading it through the 'data' loader")));
+        assert!(events.iter().any(|event| event.contains("loaded 1 bytes from 'data:,x' in ")));
+    }
+
+    #[tokio::test]
+    async fn test_async_logger_receives_async_loads() {
+        struct Collector(std::sync::Arc<std::sync::Mutex<Vec<String>>>);
+        impl crate::AsyncLogger for Collector {
+            fn log(&self, message: &str) -> impl std::future::Future<Output = ()> + Send {
+                let events = self.0.clone();
+                let message = message.to_string();
+                async move { events.lock().unwrap().push(message) }
+            }
+        }
+        let events = std::sync::Arc::new(std::sync::Mutex::new(Vec::<String>::new()));
+        let mut zip_container = ZipContainer::new("test_data/test.gldf".to_string(), None);
+        zip_container.async_logger = Some(crate::SharedAsyncLogger::new(Collector(events.clone())));
+        zip_container.load_file_async("ldc/diffuse.ldt").await.unwrap();
+        zip_container.load_buf_file_async("data:,x").await.unwrap();
+        zip_container.load_file("image/bulb.jpg").unwrap();
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 3, "{:?}", events);
+        assert!(events[0].starts_with("[DEBUG] test_data/test.gldf: read 623 bytes from 'ldc/diffuse.ldt' in "));
+        assert!(events[1].contains("'data:,x' is not in the archive"));
+        assert!(events[2].contains("loaded 1 bytes from 'data:,x' in "));
+    }
+
+    #[test]
+    fn test_sniff_definition_formats() {
+        use crate::detection::{definition_from_extension, sniff_definition};
+        assert!(matches!(definition_from_extension("x/product.GLDF.json"), Some(Definition::JSON(_))));
+        assert!(matches!(definition_from_extension("config.yml"), Some(Definition::YAML(_))));
+        assert!(definition_from_extension("manifest").is_none());
+        assert!(definition_from_extension(".xml").is_none());
+
+        assert!(matches!(sniff_definition(b"\xef\xbb\xbf<?xml version=\"1.0\"?><Root/>"), Some(Definition::XML(_))));
+        assert!(matches!(sniff_definition(b"  \n{\"a\": 1}"), Some(Definition::JSON(_))));
+        assert!(matches!(sniff_definition(b"[1, 2]"), Some(Definition::JSON(_))));
+        assert!(matches!(sniff_definition(br#"["a"]"#), Some(Definition::JSON(_))));
+        assert!(matches!(sniff_definition(br#"[["a", "b"]]"#), Some(Definition::JSON(_))));
+        assert!(matches!(sniff_definition(b"[servers.alpha]\nip = \"10.0.0.1\""), Some(Definition::TOML(_))));
+        assert!(matches!(sniff_definition(b"---\nname: x"), Some(Definition::YAML(_))));
+        assert!(matches!(sniff_definition(b"name: x\nitems:\n  - a"), Some(Definition::YAML(_))));
+        assert!(matches!(sniff_definition(b"# comment\n[package]\nname = \"x\""), Some(Definition::TOML(_))));
+        assert!(matches!(sniff_definition(b"name = \"x\""), Some(Definition::TOML(_))));
+        assert!(sniff_definition(b"just some text").is_none());
+    }
+
+    #[test]
+    fn test_definition_detection_method() {
+        let memory = MemoryFileLoader::new();
+        memory.insert("mem://c.zip", zip_with(&[("manifest", b"{\"name\": \"x\"}"), ("data.txt", b"plain")]));
+        let mut loader = LoaderRegistry::default();
+        loader.register("mem", memory.clone());
+
+        let sniffed = ZipContainer::with_loader("mem://c.zip".to_string(), Some("manifest".to_string()), loader.clone());
+        assert!(matches!(sniffed.definition_content, Some(Definition::JSON(_))));
+        assert_eq!(sniffed.definition_detection, Some(crate::DetectionMethod::Content));
+
+        let by_name = ZipContainer::new("test_data/test.gldf".to_string(), Some("product.xml".to_string()));
+        assert_eq!(by_name.definition_detection, Some(crate::DetectionMethod::Extension));
+
+        let unknown = ZipContainer::with_loader("mem://c.zip".to_string(), Some("data.txt".to_string()), loader);
+        assert!(unknown.definition_content.is_none() && unknown.definition_detection.is_none());
+
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        #[derive(Clone)]
+        struct CountingLoader(MemoryFileLoader, Arc<AtomicUsize>);
+        impl FileLoader for CountingLoader {
+            fn load(&self, path_or_url: &str) -> crate::ZipContainerResult<Vec<u8>> {
+                self.1.fetch_add(1, Ordering::SeqCst);
+                self.0.load(path_or_url)
+            }
+        }
+        impl AsyncFileLoader for CountingLoader {
+            fn load_async(&self, path_or_url: &str) -> crate::zip_container_trait::ZipContainerFuture<Vec<u8>> {
+                self.1.fetch_add(1, Ordering::SeqCst);
+                self.0.load_async(path_or_url)
+            }
+        }
+        memory.insert("mem://absent", b"{\"name\": \"outside\"}".to_vec());
+        let loads = Arc::new(AtomicUsize::new(0));
+        let mut loader = LoaderRegistry::default();
+        loader.register("mem", CountingLoader(memory, loads.clone()));
+        let absent = ZipContainer::with_loader("mem://c.zip".to_string(), Some("absent".to_string()), loader);
+        assert!(absent.definition_content.is_none() && absent.definition_detection.is_none());
+        assert_eq!(loads.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_discover_definition_rules() {
+        use crate::detection::{discover_definition, DEFAULT_DEFINITION_CANDIDATES};
+        let entries = |names: &[&str]| names.iter().map(|name| name.to_string()).collect::<Vec<_>>();
+        let gldf = entries(&["ldc/a.ldt", "Product.xml", "other.xml"]);
+        assert_eq!(discover_definition(&gldf, DEFAULT_DEFINITION_CANDIDATES).as_deref(), Some("Product.xml"));
+        let single = entries(&["images/a.jpg", "catalogue.json", "nested/x.xml"]);
+        assert_eq!(discover_definition(&single, DEFAULT_DEFINITION_CANDIDATES).as_deref(), Some("catalogue.json"));
+        let ambiguous = entries(&["a.xml", "b.json"]);
+        assert_eq!(discover_definition(&ambiguous, DEFAULT_DEFINITION_CANDIDATES), None);
+        assert_eq!(discover_definition(&ambiguous, &["b.json"]).as_deref(), Some("b.json"));
+    }
+
+    #[test]
+    fn test_container_discovers_definition() {
+        let zip_container = ZipContainer::new("test_data/test.gldf".to_string(), None);
+        assert_eq!(zip_container.definition_path.as_deref(), Some("product.xml"));
+        assert!(zip_container.load_definition_file_str().unwrap().contains("<Root"));
+
+        let mut custom = ZipContainer {
+            zip_data: Some(zip_with(&[("a.xml", b"<a/>"), ("b.json", b"{}")])),
+            definition_candidates: Some(vec!["b.json".to_string()]),
+            ..Default::default()
+        };
+        assert_eq!(custom.resolve_definition(), Some(crate::DetectionMethod::Extension));
+        assert_eq!(custom.load_definition_file_str().unwrap(), "{}");
+    }
+
+    #[cfg(all(feature = "json", feature = "yaml", feature = "toml", feature = "xml"))]
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    #[serde(rename = "Root")]
+    struct GldfRoot {
+        #[serde(rename = "Header")]
+        header: GldfHeader,
+    }
+
+    #[cfg(all(feature = "json", feature = "yaml", feature = "toml", feature = "xml"))]
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct GldfHeader {
+        #[serde(rename = "Manufacturer")]
+        manufacturer: String,
+        #[serde(rename = "FormatVersion")]
+        format_version: String,
+    }
+
+    #[cfg(all(feature = "json", feature = "yaml", feature = "toml", feature = "xml"))]
+    #[test]
+    fn test_definition_as_typed_xml() {
+        let zip_container = ZipContainer::new("test_data/test.gldf".to_string(), Some("product.xml".to_string()));
+        let root: GldfRoot = zip_container.definition_as().unwrap();
+        assert_eq!(root.header.manufacturer, "demo");
+        assert_eq!(root.header.format_version, "1.0.0-rc.1");
+
+        let error = zip_container.definition_as::<Vec<u32>>().unwrap_err();
+        assert_eq!(error.code(), "DEFINITION_PARSE");
+        assert_eq!(error.entry(), Some("product.xml"));
+    }
+
+    #[cfg(all(feature = "json", feature = "yaml", feature = "toml", feature = "xml"))]
+    #[test]
+    fn test_set_definition_from_roundtrip() {
+        let value = GldfRoot {
+            header: GldfHeader { manufacturer: "acme".to_string(), format_version: "1.0".to_string() },
+        };
+        for (name, kind) in [
+            ("definition.json", crate::DefinitionKind::Json),
+            ("definition.yaml", crate::DefinitionKind::Yaml),
+            ("definition.toml", crate::DefinitionKind::Toml),
+            ("definition.xml", crate::DefinitionKind::Xml),
+        ] {
+            let mut zip_container = ZipContainer {
+                zip_data: Some(zip_with(&[("other.txt", b"x")])),
+                definition_path: Some(name.to_string()),
+                ..Default::default()
+            };
+            zip_container.set_definition_from(&value).unwrap();
+            assert_eq!(zip_container.definition().unwrap().kind(), kind);
+            assert_eq!(zip_container.definition_as::<GldfRoot>().unwrap(), value, "{}", name);
+        }
+        assert!(ZipContainer::default().set_definition_from(&value).is_err());
+    }
+
+    #[test]
+    fn test_set_definition_defaults_to_unsupported() {
+        /// An implementor with only the required methods, as downstream crates write them.
+        #[derive(Clone)]
+        struct ReadOnly(Vec<u8>);
+        impl ZipContainerTrait for ReadOnly {
+            fn zip_data(&self) -> crate::ZipContainerResult<&[u8]> {
+                Ok(&self.0)
+            }
+            fn definition_path(&self) -> crate::ZipContainerResult<&str> {
+                Ok("product.xml")
+            }
+            fn set_files(&mut self, _files: Vec<BufFile>) {}
+        }
+        let mut read_only = ReadOnly(include_bytes!("../../test_data/test.gldf").to_vec());
+        assert!(read_only.load_definition_file_str().unwrap().contains("<Root"));
+        let error = read_only.set_definition(Definition::XML("<Root/>".to_string())).unwrap_err();
+        assert_eq!(error.code(), "UNSUPPORTED_OPERATION");
+    }
+
+    #[cfg(all(feature = "json", feature = "yaml", feature = "toml", feature = "xml"))]
+    #[test]
+    fn test_convert_xml_definition() {
+        use crate::DefinitionKind;
+        let zip_container = ZipContainer::new("test_data/test.gldf".to_string(), Some("product.xml".to_string()));
+        let json = zip_container.definition().unwrap().convert_to(DefinitionKind::Json).unwrap();
+        let document: serde_json::Value = json.parse_as().unwrap();
+        assert!(document["Root"]["@xmlns:xsi"].is_string());
+        assert!(document["Root"]["@xsi:noNamespaceSchemaLocation"].is_string());
+        assert_eq!(document["Root"]["Header"]["Manufacturer"], "demo");
+
+        let xml = json.convert_to(DefinitionKind::Xml).unwrap();
+        assert!(xml.as_ref().starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<Root xmlns:xsi="));
+        let root: GldfRoot = xml.parse_as().unwrap();
+        assert_eq!(root.header.manufacturer, "demo");
+        assert_eq!(xml.convert_to(DefinitionKind::Json).unwrap().to_document().unwrap(), document);
+    }
+
+    #[cfg(all(feature = "json", feature = "yaml", feature = "toml", feature = "xml"))]
+    #[test]
+    fn test_convert_definition_mapping() {
+        use crate::DefinitionKind;
+        let xml = Definition::XML(
+            r#"<a id="1"><b>x &amp; y</b><b>z</b><c/><d k="v">text</d></a>"#.to_string(),
+        );
+        let document = xml.to_document().unwrap();
+        assert_eq!(
+            document,
+            serde_json::json!({"a": {"@id": "1", "b": ["x & y", "z"], "c": null, "d": {"@k": "v", "#text": "text"}}})
+        );
+        let keys: Vec<&String> = document["a"].as_object().unwrap().keys().collect();
+        assert_eq!(keys, ["@id", "b", "c", "d"]);
+
+        let yaml = xml.convert_to(DefinitionKind::Yaml).unwrap();
+        assert_eq!(yaml.convert_to(DefinitionKind::Xml).unwrap().to_document().unwrap(), document);
+        let error = yaml.convert_to(DefinitionKind::Toml).unwrap_err();
+        assert_eq!(error.code(), "INVALID_DATA");
+
+        let json = Definition::JSON(r#"{"name": "demo", "list": [1, 2], "nested": {"on": true}}"#.to_string());
+        let toml = json.convert_to(DefinitionKind::Yaml).unwrap().convert_to(DefinitionKind::Toml).unwrap();
+        assert_eq!(toml.to_document().unwrap(), json.to_document().unwrap());
+        assert!(Definition::JSON("[1]".to_string()).convert_to(DefinitionKind::Xml).is_err());
+    }
+
+    #[cfg(all(feature = "json", feature = "yaml", feature = "xml"))]
+    #[test]
+    fn test_convert_definition_keeps_xml_order() {
+        use crate::DefinitionKind;
+        let roundtrip = |xml: &str| {
+            let yaml = Definition::XML(xml.to_string()).convert_to(DefinitionKind::Yaml).unwrap();
+            yaml.convert_to(DefinitionKind::Xml).unwrap().as_ref().to_string()
+        };
+        let document = Definition::XML("<r><a>1</a><b>2</b><a>3</a></r>".to_string()).to_document().unwrap();
+        assert_eq!(document, serde_json::json!({"r": {"#children": [{"a": "1"}, {"b": "2"}, {"a": "3"}]}}));
+        assert_eq!(
+            roundtrip("<r><a>1</a><b>2</b><a>3</a></r>"),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<r>\n    <a>1</a>\n    <b>2</b>\n    <a>3</a>\n</r>\n"
+        );
+
+        let commented = "<!-- head --><r a=\"1\"><!-- note --><?render fast?><x/></r>";
+        let document = Definition::XML(commented.to_string()).to_document().unwrap();
+        assert_eq!(
+            document,
+            serde_json::json!({"#children": [
+                {"#comment": " head "},
+                {"r": {"@a": "1", "#children": [{"#comment": " note "}, {"?render": "fast"}, {"x": null}]}}
+            ]})
+        );
+        assert_eq!(
+            roundtrip(commented),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!-- head -->\n<r a=\"1\">\n    <!-- note -->\n    <?render fast?>\n    <x/>\n</r>\n"
+        );
+
+        let mixed = "<p>Hello <b>big</b> world <i/> !</p>";
+        let document = Definition::XML(mixed.to_string()).to_document().unwrap();
+        assert_eq!(
+            document,
+            serde_json::json!({"p": {"#children": [{"#text": "Hello "}, {"b": "big"}, {"#text": " world "}, {"i": null}, {"#text": " !"}]}})
+        );
+        assert_eq!(roundtrip(mixed), format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n{}\n", mixed));
+        assert_eq!(Definition::XML("<t>  padded  </t>".to_string()).to_document().unwrap(), serde_json::json!({"t": "  padded  "}));
+    }
+
+    #[cfg(all(feature = "json", feature = "xml"))]
+    #[test]
+    fn test_convert_definition_rejects_invalid_xml_names() {
+        use crate::DefinitionKind;
+        let convert = |json: &str| Definition::JSON(json.to_string()).convert_to(DefinitionKind::Xml);
+        for json in [
+            r##"{"root": {"my key": 1}}"##,
+            r##"{"root": {"1x": 2}}"##,
+            r##"{"root": {"@a b": "v"}}"##,
+            r##"{"<root>": null}"##,
+            r##"{"root": {"#children": [{"#comment": "a -- b"}]}}"##,
+            r##"{"root": {"#children": [{"?xml": "version"}]}}"##,
+            r##"{"#children": [{"a": null}, {"b": null}]}"##,
+        ] {
+            assert_eq!(convert(json).unwrap_err().code(), "INVALID_DATA", "{}", json);
+        }
+        let xml = convert(r##"{"ns:root": {"@xml:lang": "en", "é-1.x_y": "ok"}}"##).unwrap();
+        assert!(xml.as_ref().contains(r#"<ns:root xml:lang="en">"#));
+    }
+
+    #[cfg(feature = "validation")]
+    const HEADER_XSD: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">
+    <xs:element name="Root">
+        <xs:complexType>
+            <xs:sequence>
+                <xs:element name="Header" type="HeaderType"/>
+                <xs:any minOccurs="0" maxOccurs="unbounded"/>
+            </xs:sequence>
+        </xs:complexType>
+    </xs:element>
+    <xs:complexType name="HeaderType">
+        <xs:sequence>
+            <xs:element name="Author" type="xs:string"/>
+            <xs:element name="Manufacturer" type="NonEmpty"/>
+            <xs:element name="CreationTimeCode" type="xs:dateTime"/>
+            <xs:element name="CreatedWithApplication" type="xs:string"/>
+            <xs:element name="FormatVersion" type="xs:string"/>
+            <xs:element name="DefaultLanguage" type="xs:language" minOccurs="0"/>
+            <xs:element name="LicenseKeys" minOccurs="0">
+                <xs:complexType>
+                    <xs:sequence>
+                        <xs:element name="LicenseKey" maxOccurs="unbounded">
+                            <xs:complexType>
+                                <xs:simpleContent>
+                                    <xs:extension base="xs:string">
+                                        <xs:attribute name="application" use="required">
+                                            <xs:simpleType>
+                                                <xs:restriction base="xs:string">
+                                                    <xs:enumeration value="RELUX"/>
+                                                    <xs:enumeration value="DIALux"/>
+                                                </xs:restriction>
+                                            </xs:simpleType>
+                                        </xs:attribute>
+                                    </xs:extension>
+                                </xs:simpleContent>
+                            </xs:complexType>
+                        </xs:element>
+                    </xs:sequence>
+                </xs:complexType>
+            </xs:element>
+            <xs:any minOccurs="0" maxOccurs="unbounded" processContents="skip"/>
+        </xs:sequence>
+    </xs:complexType>
+    <xs:simpleType name="NonEmpty">
+        <xs:restriction base="xs:string">
+            <xs:minLength value="1"/>
+        </xs:restriction>
+    </xs:simpleType>
+</xs:schema>
+"#;
+
+    #[cfg(feature = "validation")]
+    #[test]
+    fn test_validate_xml_definition_against_xsd() {
+        use crate::SchemaSource;
+        let product = ZipContainer::new("test_data/test.gldf".to_string(), Some("product.xml".to_string()))
+            .load_definition_file_str()
+            .unwrap();
+        let invalid = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Root>
+    <Header>
+        <Author>x</Author>
+        <Manufacturer></Manufacturer>
+        <CreationTimeCode>yesterday</CreationTimeCode>
+        <FormatVersion>1.0</FormatVersion>
+        <LicenseKeys>
+            <LicenseKey application="Other">key</LicenseKey>
+            <LicenseKey>key</LicenseKey>
+        </LicenseKeys>
+    </Header>
+</Root>
+"#;
+        let zip_data = zip_with(&[
+            ("product.xml", product.as_bytes()),
+            ("invalid.xml", invalid.as_bytes()),
+            ("schema/gldf.xsd", HEADER_XSD.as_bytes()),
+        ]);
+        let valid = ZipContainer {
+            zip_data: Some(zip_data.clone()),
+            definition_path: Some("product.xml".to_string()),
+            ..Default::default()
+        };
+        let schema = SchemaSource::Entry("schema/gldf.xsd".to_string());
+        assert_eq!(valid.validate_definition(&schema).unwrap(), Vec::new());
+        valid.ensure_valid_definition(&schema).unwrap();
+
+        let upload = ZipContainer {
+            zip_data: Some(zip_data),
+            definition_path: Some("invalid.xml".to_string()),
+            ..Default::default()
+        };
+        let diagnostics = upload.validate_definition(&schema).unwrap();
+        let summary: Vec<(String, Option<usize>)> = diagnostics.iter().map(|d| (d.path.clone(), d.line)).collect();
+        assert_eq!(
+            summary,
+            [
+                ("/Root/Header/FormatVersion".to_string(), Some(7)),
+                ("/Root/Header/Manufacturer".to_string(), Some(5)),
+                ("/Root/Header/CreationTimeCode".to_string(), Some(6)),
+            ]
+        );
+        assert_eq!(diagnostics[0].message, "unexpected element 'FormatVersion' in 'Header', expected 'CreatedWithApplication'");
+        assert_eq!(diagnostics[0].column, Some(9));
+        assert!(diagnostics[2].message.contains("not a valid dateTime"), "{}", diagnostics[2].message);
+
+        let error = upload.ensure_valid_definition(&schema).unwrap_err();
+        assert_eq!(error.code(), "VALIDATION_FAILED");
+        assert_eq!(error.entry(), Some("invalid.xml"));
+        assert_eq!(error.diagnostics(), diagnostics.as_slice());
+
+        let inline = SchemaSource::Inline(HEADER_XSD.replace(r#"<xs:element name="CreatedWithApplication" type="xs:string"/>"#, ""));
+        let messages: Vec<String> = upload.validate_definition(&inline).unwrap().into_iter().map(|d| d.message).collect();
+        assert!(messages.contains(&"attribute 'application': 'Other' is not one of 'RELUX', 'DIALux'".to_string()), "{:?}", messages);
+        assert!(messages.contains(&"element 'LicenseKey' is missing required attribute 'application'".to_string()), "{:?}", messages);
+
+        let malformed = Definition::XML("<Root>\n  <Header>\n</Root>".to_string());
+        let diagnostics = crate::Schema::parse(HEADER_XSD).unwrap().validate(&malformed).unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line, Some(3));
+    }
+
+    #[cfg(all(feature = "validation", feature = "yaml"))]
+    #[test]
+    fn test_validate_definition_against_json_schema() {
+        use crate::SchemaSource;
+        let schema = r##"{
+            "type": "object",
+            "required": ["name", "products"],
+            "properties": {
+                "name": {"type": "string", "minLength": 1},
+                "products": {"type": "array", "items": {"$ref": "#/$defs/product"}}
+            },
+            "$defs": {
+                "product": {
+                    "type": "object",
+                    "required": ["id"],
+                    "properties": {"id": {"type": "string", "pattern": "^P[0-9]+$"}, "watts": {"type": "number", "minimum": 0}},
+                    "additionalProperties": false
+                }
+            }
+        }"##;
+        let memory = MemoryFileLoader::new();
+        memory.insert("mem://schemas/product.schema.json", schema.as_bytes().to_vec());
+        let mut loader = LoaderRegistry::default();
+        loader.register("mem", memory);
+        let json = "{\n  \"name\": \"demo\",\n  \"products\": [\n    {\"id\": \"X1\", \"watts\": -3}\n  ]\n}\n";
+        let yaml = "name: demo\nproducts:\n  - id: P1\n    colour: red\n";
+        let zip_container = ZipContainer {
+            zip_data: Some(zip_with(&[("definition.json", json.as_bytes()), ("definition.yaml", yaml.as_bytes())])),
+            definition_path: Some("definition.json".to_string()),
+            loader,
+            ..Default::default()
+        };
+        let source = SchemaSource::Location("mem://schemas/product.schema.json".to_string());
+        let diagnostics = zip_container.validate_definition(&source).unwrap();
+        let summary: Vec<(&str, Option<usize>, Option<usize>)> =
+            diagnostics.iter().map(|d| (d.path.as_str(), d.line, d.column)).collect();
+        assert_eq!(summary, [("/products/0/id", Some(4), Some(7)), ("/products/0/watts", Some(4), Some(19))]);
+        assert_eq!(diagnostics[0].message, "'X1' does not match the pattern '^P[0-9]+$'");
+
+        let yaml_container = ZipContainer { definition_path: Some("definition.yaml".to_string()), ..zip_container.clone() };
+        let diagnostics = yaml_container.validate_definition(&source).unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].path, "/products/0/colour");
+        assert_eq!((diagnostics[0].line, diagnostics[0].column), (Some(4), Some(5)));
+        assert_eq!(diagnostics[0].message, "property 'colour' is not allowed");
+
+        let relative = ZipContainer { zip_path: Some("mem://schemas/c.zip".to_string()), ..zip_container.clone() };
+        let diagnostics = relative.validate_definition(&SchemaSource::Location("product.schema.json".to_string())).unwrap();
+        assert_eq!(diagnostics.len(), 2);
+
+        let broken = crate::Schema::parse(schema).unwrap().validate(&Definition::JSON("{\n  \"name\": }".to_string())).unwrap();
+        assert_eq!((broken[0].line, broken[0].column), (Some(2), Some(11)));
+        let missing = crate::Schema::parse(schema).unwrap().validate(&Definition::JSON("{}".to_string())).unwrap();
+        assert_eq!(missing.len(), 2);
+        assert!(crate::Schema::parse(schema).unwrap().validate(&Definition::XML("<a/>".to_string())).is_err());
+    }
+
+    #[cfg(feature = "validation")]
+    #[test]
+    fn test_validation_reports_cycles_and_unsupported_schema_constructs() {
+        use crate::Schema;
+        let xsd = |body: &str| {
+            format!(r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">{}</xs:schema>"#, body)
+        };
+        let messages = |schema: &Schema, definition: Definition| -> Vec<String> {
+            schema.validate(&definition).unwrap().into_iter().map(|d| d.message).collect()
+        };
+        let extension = xsd(r#"<xs:element name="a" type="A"/><xs:complexType name="A"><xs:complexContent><xs:extension base="A"/></xs:complexContent></xs:complexType>"#);
+        let error = Schema::xsd(&extension).unwrap_err();
+        assert_eq!(error.code(), "INVALID_DATA");
+        assert!(error.to_string().contains("circular definition 'A' -> 'A'"), "{}", error);
+        let groups = xsd(r#"<xs:group name="G"><xs:sequence><xs:group ref="H"/></xs:sequence></xs:group><xs:group name="H"><xs:choice><xs:group ref="G"/></xs:choice></xs:group>"#);
+        assert!(Schema::xsd(&groups).unwrap_err().to_string().contains("circular definition"));
+        let recursive = xsd(r#"<xs:element name="a" type="A"/><xs:complexType name="A"><xs:sequence><xs:element name="a" type="A" minOccurs="0"/></xs:sequence></xs:complexType>"#);
+        let recursive = Schema::xsd(&recursive).unwrap();
+        assert!(messages(&recursive, Definition::XML("<a><a><a/></a></a>".to_string())).is_empty());
+
+        let unsupported = xsd(r#"<xs:import namespace="urn:other" schemaLocation="other.xsd"/>
+            <xs:element name="a"><xs:complexType><xs:sequence>
+                <xs:element name="code" maxOccurs="unbounded"><xs:simpleType><xs:restriction base="xs:string"><xs:pattern value="\i\c*"/></xs:restriction></xs:simpleType></xs:element>
+                <xs:element name="size" type="xs:int" nillable="true" minOccurs="0"/>
+                <xs:element name="kind" type="xs:int" minOccurs="0"/>
+                <xs:element name="other" type="Missing" minOccurs="0"/>
+            </xs:sequence></xs:complexType></xs:element>"#);
+        let unsupported = Schema::xsd(&unsupported).unwrap();
+        let instance = r#"<a xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance"><code>x</code><size xsi:nil="true">3</size><kind xsi:nil="true"/><other>1</other></a>"#;
+        assert_eq!(
+            messages(&unsupported, Definition::XML(instance.to_string())),
+            [
+                "the schema's xs:import of 'other.xsd' (line 1) is not supported, its components are unknown",
+                r"the pattern '\i\c*' is not supported: XML name and block escapes",
+                "element 'size' is nil but has content",
+                "element 'kind' is not nillable",
+                "type 'Missing' is neither declared in the schema nor a built-in type",
+            ]
+        );
+
+        let json_schema = |schema: &str| Schema::json_schema(schema).unwrap();
+        let instance = || Definition::JSON(r#"{"a": {"a": {}}}"#.to_string());
+        assert_eq!(messages(&json_schema(r##"{"$ref": "#"}"##), instance()), ["$ref '#' refers back to itself for the same value"]);
+        let nested = r##"{"type": "object", "additionalProperties": {"$ref": "#"}, "maxProperties": 1}"##;
+        assert!(messages(&json_schema(nested), instance()).is_empty());
+        let cycle = r##"{"$defs": {"a": {"anyOf": [{"$ref": "#/$defs/b"}]}, "b": {"$ref": "#/$defs/a"}}, "$ref": "#/$defs/a"}"##;
+        assert!(messages(&json_schema(cycle), instance()).contains(&"value does not match any schema of anyOf".to_string()));
+        assert_eq!(
+            messages(&json_schema(r#"{"propertyNames": {"maxLength": 3}}"#), instance()),
+            ["the schema keyword 'propertyNames' is not supported"]
+        );
+    }
+
+    #[test]
+    fn test_decode_non_utf8_definitions() {
+        let product = ZipContainer::new("test_data/test.gldf".to_string(), Some("product.xml".to_string()))
+            .load_definition_file_str()
+            .unwrap();
+        let utf16: Vec<u8> = [0xfeff].into_iter().chain(product.encode_utf16()).flat_map(u16::to_le_bytes).collect();
+        let latin1_xml = b"<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?>\n<Root><City>M\xfcnchenstein</City></Root>\n";
+        let latin1_json = b"{\"city\": \"M\xfcnchenstein\"}";
+        let zip_data = zip_with(&[
+            ("utf16/product.xml", &utf16),
+            ("latin1.xml", latin1_xml),
+            ("latin1.json", latin1_json),
+            ("manifest", &utf16),
+        ]);
+        let open = |definition_path: &str| ZipContainer {
+            zip_data: Some(zip_data.clone()),
+            definition_path: Some(definition_path.to_string()),
+            ..Default::default()
+        };
+
+        let utf16_container = open("utf16/product.xml");
+        assert_eq!(utf16_container.load_definition_file_str().unwrap(), product);
+        let encoding = utf16_container.definition_encoding().unwrap();
+        assert_eq!((encoding.name(), encoding.has_bom()), ("UTF-16LE", true));
+        assert_eq!(utf16_container.encode_definition().unwrap(), utf16);
+
+        let mut latin1 = open("latin1.xml");
+        assert!(latin1.load_definition_file_str().unwrap().contains("<City>Münchenstein</City>"));
+        assert_eq!(latin1.definition_encoding().unwrap().name(), "windows-1252");
+        latin1.set_definition(Definition::XML(latin1.load_definition_file_str().unwrap().replace("Münchenstein", "Zürich"))).unwrap();
+        assert_eq!(latin1.definition_encoding.map(|encoding| encoding.name()), Some("windows-1252"));
+        assert!(latin1.encode_definition().unwrap().windows(6).any(|window| window == b"Z\xfcrich"));
+        latin1.set_definition(Definition::XML("<City>Łódź</City>".to_string())).unwrap();
+        assert_eq!(latin1.encode_definition().unwrap_err().code(), "INVALID_DATA");
+
+        assert_eq!(open("latin1.json").load_definition_file_str().unwrap(), "{\"city\": \"Münchenstein\"}");
+        let mut sniffed = open("manifest");
+        assert_eq!(sniffed.detect_definition(), Some(crate::DetectionMethod::Content));
+        assert!(matches!(sniffed.definition_content, Some(Definition::XML(_))));
+    }
+
+    #[cfg(all(feature = "json", feature = "xml"))]
+    #[test]
+    fn test_query_xml_definition_with_xpath() {
+        use crate::QueryMatch;
+        let zip_container = ZipContainer::new("test_data/test.gldf".to_string(), Some("product.xml".to_string()));
+        assert_eq!(zip_container.query_value("/Root/Header/Manufacturer").unwrap().as_deref(), Some("demo"));
+        let applications = zip_container.query("//LicenseKey/@application").unwrap();
+        assert_eq!(applications, [QueryMatch::Value("RELUX".to_string()), QueryMatch::Value("DIALux".to_string())]);
+        let dialux = zip_container.query("//LicenseKey[@application='DIALux']/text() | //EMail[last()]/@mailto").unwrap();
+        let dialux: Vec<Option<&str>> = dialux.iter().map(QueryMatch::as_str).collect();
+        assert_eq!(dialux, [Some("_empty_"), Some("info@relux.com8")]);
+        assert_eq!(zip_container.query_value("//Address[City='Münchenstein4']/../../ReluxMemberId").unwrap().as_deref(), Some("dmo"));
+
+        let contact = zip_container.query("/Root/Header/Contact").unwrap();
+        let QueryMatch::Tree(contact) = &contact[0] else { panic!("expected a subtree") };
+        assert_eq!(contact["Address"]["EMailAddresses"]["EMail"]["@mailto"], "info@relux.com8");
+        assert!(zip_container.query("//Missing").unwrap().is_empty());
+        assert_eq!(zip_container.query("//LicenseKey[").unwrap_err().code(), "INVALID_DATA");
+        for unsupported in ["count(//LicenseKey)", "//LicenseKey[contains(@application, 'D')]", "/Root/child::Header"] {
+            let error = zip_container.query(unsupported).unwrap_err();
+            assert!(error.to_string().contains("is not supported"), "{}", error);
+        }
+        let error = Definition::JSON("{}".to_string()).query("/a").unwrap_err();
+        assert_eq!(error.code(), "UNSUPPORTED_OPERATION");
+    }
+
+    #[cfg(all(feature = "json", feature = "yaml", feature = "xml"))]
+    #[test]
+    fn test_query_definition_with_jsonpath() {
+        use crate::QueryMatch;
+        let json = Definition::JSON(
+            r#"{"name": "demo", "products": [{"id": "P1", "watts": 12.5}, {"id": "P2", "watts": 40, "dim": true}, {"id": "P3"}]}"#
+                .to_string(),
+        );
+        let ids = |expression: &str| -> Vec<String> {
+            json.query(expression).unwrap().into_iter().filter_map(|found| found.as_str().map(String::from)).collect()
+        };
+        assert_eq!(ids("$.products[*].id"), ["P1", "P2", "P3"]);
+        assert_eq!(ids("$.products[?@.watts > 20].id"), ["P2"]);
+        assert_eq!(ids("$.products[?(@.watts && !@.dim)].id"), ["P1"]);
+        assert_eq!(ids("$.products[-1]['id']"), ["P3"]);
+        assert_eq!(ids("$.products[::2].id"), ["P1", "P3"]);
+        assert_eq!(ids("$.products[1::9223372036854775807].id"), ["P2"]);
+        assert_eq!(ids("$.products[1::-9223372036854775807].id"), ["P2"]);
+        assert_eq!(ids("$.products[::-9223372036854775808].id"), ["P3"]);
+        assert_eq!(ids("$..watts"), ["12.5", "40"]);
+        assert_eq!(json.query("$.products[1]").unwrap()[0], QueryMatch::Tree(serde_json::json!({"id": "P2", "watts": 40, "dim": true})));
+        assert_eq!(json.query("$.products[").unwrap_err().code(), "INVALID_DATA");
+        let error = json.query("$.products[?length(@.id) > 1]").unwrap_err();
+        assert!(error.to_string().contains("the function 'length()' is not supported"), "{}", error);
+
+        let yaml = Definition::YAML("name: demo\nproducts:\n  - id: P1\n".to_string());
+        assert_eq!(yaml.query_value("$.products[0].id").unwrap().as_deref(), Some("P1"));
+        let xml = Definition::XML(r#"<a><b id="1">x</b><b id="2">y</b></a>"#.to_string());
+        assert_eq!(xml.query_value("$.a.b[?@['@id'] == '2']['#text']").unwrap().as_deref(), Some("y"));
+    }
+
+    #[cfg(all(feature = "json", feature = "xml"))]
+    #[test]
+    fn test_query_rejects_deeply_nested_xml() {
+        let nested = |depth: usize| Definition::XML(format!("{}{}", "<a>".repeat(depth), "</a>".repeat(depth)));
+        assert_eq!(nested(128).query("//a").unwrap().len(), 128);
+        let deep = nested(100_000);
+        for expression in ["//a", "$..a"] {
+            let error = deep.query(expression).unwrap_err();
+            assert_eq!(error.code(), "INVALID_DATA");
+            assert!(error.to_string().contains("nested deeper than 128 levels"), "{}", error);
+        }
+        assert_eq!(deep.convert_to(crate::DefinitionKind::Json).unwrap_err().code(), "INVALID_DATA");
+    }
+
+    #[cfg(all(feature = "json", feature = "xml"))]
+    #[test]
+    fn test_check_references_of_gldf() {
+        let zip_container = ZipContainer::new("test_data/test.gldf".to_string(), Some("product.xml".to_string()));
+        let report = zip_container.check_references().unwrap();
+        assert!(report.is_clean(), "{:?}", report);
+        assert_eq!(report.resolved[0], ("diffuse.ldt".to_string(), "ldc/diffuse.ldt".to_string()));
+        assert_eq!(report.resolved.len(), 3);
+
+        let product = r#"<Root><GeneralDefinitions><Files>
+            <File id="ldt" type="localFileName">diffuse.ldt</File>
+            <File id="pic" type="localFileName">missing.jpg</File>
+            <File id="web" type="url">mem://cdn/sheet.pdf</File>
+            <File id="gone" type="url">mem://cdn/gone.pdf</File>
+        </Files></GeneralDefinitions></Root>"#;
+        let memory = MemoryFileLoader::new();
+        memory.insert("mem://cdn/sheet.pdf", b"%PDF".to_vec());
+        let mut loader = LoaderRegistry::default();
+        loader.register("mem", memory);
+        let entries: &[(&str, &[u8])] =
+            &[("product.xml", product.as_bytes()), ("ldc/diffuse.ldt", b"ldt"), ("image/unused.jpg", b"jpg")];
+        let zip_container = ZipContainer {
+            zip_data: Some(zip_with(entries)),
+            definition_path: Some("product.xml".to_string()),
+            loader,
+            ..Default::default()
+        };
+        let report = zip_container.check_references().unwrap();
+        assert_eq!(report.dangling, ["missing.jpg"]);
+        assert_eq!(report.external, ["mem://cdn/sheet.pdf", "mem://cdn/gone.pdf"]);
+        assert_eq!(report.orphans, ["image/unused.jpg"]);
+
+        let rules = crate::ReferenceRules {
+            probe_external: true,
+            ignored: vec!["image/unused.jpg".to_string()],
+            ..Default::default()
+        };
+        let report = zip_container.check_references_with(&rules).unwrap();
+        assert_eq!(report.dangling, ["missing.jpg", "mem://cdn/gone.pdf"]);
+        assert_eq!(report.external, ["mem://cdn/sheet.pdf"]);
+        assert!(report.orphans.is_empty());
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_check_references_with_jsonpath() {
+        let manifest = r#"{"assets": [{"file": "./models/cube.obj"}, {"file": "cube.mtl"}, {"file": "textures/wood.png"}]}"#;
+        let entries: &[(&str, &[u8])] = &[
+            ("manifest.json", manifest.as_bytes()),
+            ("models/", b""),
+            ("models/cube.obj", b"o"),
+            ("models/cube.mtl", b"m"),
+            ("notes.txt", b"n"),
+        ];
+        let mut zip_container = ZipContainer {
+            zip_data: Some(zip_with(entries)),
+            definition_path: Some("manifest.json".to_string()),
+            ..Default::default()
+        };
+        zip_container.reference_rules = Some(crate::ReferenceRules {
+            expressions: vec!["$.assets[*].file".to_string(), "//File/text()".to_string()],
+            directories: vec!["models".to_string()],
+            ..Default::default()
+        });
+        let report = zip_container.check_references().unwrap();
+        let resolved: Vec<&str> = report.resolved.iter().map(|(_, entry)| entry.as_str()).collect();
+        assert_eq!(resolved, ["models/cube.obj", "models/cube.mtl"]);
+        assert_eq!(report.dangling, ["textures/wood.png"]);
+        assert_eq!(report.orphans, ["notes.txt"]);
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn test_http_exists_uses_head_requests() {
+        let (url, server) = serve_http(vec![
+            http_response("200 OK", ""),
+            http_response("404 Not Found", ""),
+            http_response("405 Method Not Allowed", ""),
+            http_response("200 OK", "body"),
+        ]);
+        let loader = LoaderRegistry::default();
+        assert!(loader.exists(&format!("{}/found.pdf", url)));
+        assert!(!loader.exists(&format!("{}/gone.pdf", url)));
+        assert!(loader.exists(&format!("{}/no-head.pdf", url)));
+        let requests = server.join().unwrap();
+        let lines: Vec<&str> = requests.iter().filter_map(|request| request.lines().next()).collect();
+        assert_eq!(
+            lines,
+            ["head /found.pdf http/1.1", "head /gone.pdf http/1.1", "head /no-head.pdf http/1.1", "get /no-head.pdf http/1.1"]
+        );
+    }
+
+    #[test]
+    fn test_open_with_gldf_profile() {
+        use crate::ProfileDescriptor;
+        let zip_container =
+            ZipContainer::open_with_profile("test_data/test.gldf".to_string(), ProfileDescriptor::gldf(), LoaderRegistry::default(), None);
+        assert_eq!(zip_container.definition_path().unwrap(), "product.xml");
+        assert_eq!(zip_container.validate_layout().unwrap(), []);
+        let files = zip_container.get_zip_files().unwrap();
+        let ldt = files.iter().find(|file| file.name.as_deref() == Some("ldc/diffuse.ldt")).unwrap();
+        assert_eq!(ldt.content_type.as_deref(), Some("ldc/eulumdat"));
+        #[cfg(all(feature = "json", feature = "xml"))]
+        assert!(zip_container.check_references().unwrap().is_clean());
+
+        let events = std::sync::Arc::new(std::sync::Mutex::new(Vec::<String>::new()));
+        let sink = events.clone();
+        let memory = MemoryFileLoader::new();
+        memory.insert("mem://uploads/stray.gldf", zip_with(&[("product.xml", b"<Root/>"), ("stray.bin", b"x")]));
+        let mut loader = LoaderRegistry::default();
+        loader.register("mem", memory);
+        let logger = crate::SharedLogger::new(move |message: &str| sink.lock().unwrap().push(message.to_string()));
+        let uploaded = ZipContainer::open_with_profile("mem://uploads/stray.gldf".to_string(), ProfileDescriptor::gldf(), loader, Some(logger));
+        assert_eq!(uploaded.load_definition_file_str().unwrap(), "<Root/>");
+        let warnings: Vec<String> = events.lock().unwrap().iter().filter(|event| event.starts_with("[WARN]")).cloned().collect();
+        assert!(!warnings.is_empty() && warnings.iter().all(|warning| warning.contains("mem://uploads/stray.gldf")), "{:?}", warnings);
+
+        let entries: &[(&str, &[u8])] = &[("product.xml", b"<Root/>"), ("ldc/a.ldt", b"ldt"), ("notes/todo.txt", b"x"), ("x.bin", b"x")];
+        let layout = ZipContainer { zip_data: Some(zip_with(entries)), ..Default::default() }
+            .validate_layout_with(&ProfileDescriptor::l3d())
+            .unwrap();
+        let problems: Vec<(&str, &str)> = layout.iter().map(|d| (d.path.as_str(), d.message.as_str())).collect();
+        assert_eq!(
+            problems,
+            [
+                ("structure.xml", "the definition file is missing"),
+                ("*/*.obj", "no entry matches this required entry"),
+                ("product.xml", "the entry is not allowed in a L3D container"),
+                ("x.bin", "the entry is not allowed in a L3D container"),
+            ]
+        );
+        assert_eq!(ZipContainer::default().validate_layout().unwrap_err().code(), "MISSING_VALUE");
+    }
+
+    #[test]
+    fn test_profile_mimetype_rule_and_globs() {
+        use crate::profile::glob_matches;
+        use crate::ProfileDescriptor;
+        let epub = ProfileDescriptor {
+            name: "EPUB".to_string(),
+            definition_path: "META-INF/container.xml".to_string(),
+            optional_entries: vec!["**".to_string()],
+            mimetype: Some("application/epub+zip".to_string()),
+            ..Default::default()
+        };
+        let valid: &[(&str, &[u8])] = &[("mimetype", b"application/epub+zip"), ("META-INF/container.xml", b"<container/>")];
+        let zip_container = ZipContainer { zip_data: Some(zip_with(valid)), ..Default::default() };
+        assert_eq!(zip_container.validate_layout_with(&epub).unwrap(), []);
+        let misplaced: &[(&str, &[u8])] = &[("META-INF/container.xml", b"<container/>"), ("mimetype", b"text/plain")];
+        let zip_container = ZipContainer { zip_data: Some(zip_with(misplaced)), ..Default::default() };
+        let messages: Vec<String> = zip_container.validate_layout_with(&epub).unwrap().into_iter().map(|d| d.message).collect();
+        assert_eq!(messages, ["the mimetype entry must be the first entry", "the mimetype must be 'application/epub+zip'"]);
+
+        assert!(glob_matches("ldc/*.ldt", "ldc/a.ldt"));
+        assert!(!glob_matches("ldc/*.ldt", "ldc/sub/a.ldt"));
+        assert!(glob_matches("**/*.png", "a.png") && glob_matches("**/*.png", "x/y/a.png"));
+        assert!(!glob_matches("**/b", "xb"));
+        assert!(glob_matches("ima?e/**", "image/a/b.jpg"));
+    }
+
+    #[cfg(all(feature = "json", feature = "xml"))]
+    #[test]
+    fn test_gldf_file_table() {
+        let zip_container = ZipContainer::new("test_data/test.gldf".to_string(), Some("product.xml".to_string()));
+        let files = zip_container.get_zip_files_with_ids().unwrap();
+        let ldt = files.iter().find(|file| file.name.as_deref() == Some("ldc/diffuse.ldt")).unwrap();
+        assert_eq!((ldt.file_id.as_deref(), ldt.content_type.as_deref()), (Some("ldtfile"), Some("ldc/eulumdat")));
+        let picture = files.iter().find(|file| file.name.as_deref() == Some("image/bulb.jpg")).unwrap();
+        assert_eq!(picture.file_id.as_deref(), Some("pic01"));
+        assert_eq!(
+            zip_container.load_file_by_id("ldtfile").unwrap(),
+            zip_container.load_file_from_zip("ldc/diffuse.ldt").unwrap()
+        );
+        assert_eq!(zip_container.load_file_by_id("nope").unwrap_err().code(), "ENTRY_NOT_FOUND");
+
+        let product = r#"<Root><GeneralDefinitions><Files>
+            <File id="diffuse" contentType="ldc/eulumdat" type="localFileName">ldc/diffuse.ldt</File>
+            <File id="sheet" contentType="document/pdf" type="url">mem://cdn/sheet.pdf</File>
+        </Files></GeneralDefinitions></Root>"#;
+        let memory = MemoryFileLoader::new();
+        memory.insert("mem://cdn/sheet.pdf", b"%PDF".to_vec());
+        let mut loader = LoaderRegistry::default();
+        loader.register("mem", memory);
+        let entries: &[(&str, &[u8])] = &[("product.xml", product.as_bytes()), ("ldc/diffuse.ldt", b"ldt")];
+        let zip_container = ZipContainer {
+            zip_data: Some(zip_with(entries)),
+            definition_path: Some("product.xml".to_string()),
+            loader,
+            ..Default::default()
+        };
+        let table = zip_container.file_table().unwrap();
+        assert_eq!(table[0].location(), "ldc/diffuse.ldt");
+        assert_eq!((table[1].entry.as_deref(), table[1].file_type.as_deref()), (None, Some("url")));
+        assert_eq!(zip_container.load_file_by_id("diffuse").unwrap(), b"ldt");
+        assert_eq!(zip_container.load_file_by_id("sheet").unwrap(), b"%PDF");
+    }
+
+    #[cfg(all(feature = "json", feature = "xml"))]
+    #[test]
+    fn test_localize_external_references() {
+        let product = r#"<Root><GeneralDefinitions><Files>
+            <File id="ldt" contentType="ldc/eulumdat" type="localFileName">diffuse.ldt</File>
+            <File id="sheet" contentType="document/pdf" type="url">mem://cdn/docs/sheet.pdf?v=1&amp;lang=en</File>
+            <File id="other" contentType="document/pdf" type="url">mem://cdn/other/sheet.pdf</File>
+        </Files></GeneralDefinitions>
+        <!-- mirrored from mem://cdn/other/sheet.pdf -->
+        <Note source="mem://cdn/other/sheet.pdf">mem://cdn/other/sheet.pdf</Note></Root>"#;
+        let memory = MemoryFileLoader::new();
+        memory.insert("mem://cdn/docs/sheet.pdf?v=1&lang=en", b"%PDF-1".to_vec());
+        memory.insert("mem://cdn/other/sheet.pdf", b"%PDF-2".to_vec());
+        let mut loader = LoaderRegistry::default();
+        loader.register("mem", memory);
+        let entries: &[(&str, &[u8])] = &[("product.xml", product.as_bytes()), ("ldc/diffuse.ldt", b"ldt")];
+        let mut zip_container = ZipContainer {
+            zip_data: Some(zip_with(entries)),
+            definition_path: Some("product.xml".to_string()),
+            loader,
+            ..Default::default()
+        };
+        let localized = zip_container.localize(Some("doc")).unwrap();
+        assert_eq!(
+            localized,
+            [
+                ("mem://cdn/docs/sheet.pdf?v=1&lang=en".to_string(), "doc/sheet.pdf".to_string()),
+                ("mem://cdn/other/sheet.pdf".to_string(), "doc/sheet-2.pdf".to_string()),
+            ]
+        );
+        assert_eq!(zip_container.load_file_from_zip("doc/sheet-2.pdf").unwrap(), b"%PDF-2");
+        assert_eq!(zip_container.load_file_from_zip("ldc/diffuse.ldt").unwrap(), b"ldt");
+        let written = String::from_utf8(zip_container.load_file_from_zip("product.xml").unwrap()).unwrap();
+        assert!(written.contains(r#"<File id="sheet" contentType="document/pdf" type="localFileName">doc/sheet.pdf</File>"#));
+        assert!(written.contains(r#"<File id="other" contentType="document/pdf" type="localFileName">doc/sheet-2.pdf</File>"#));
+        assert!(written.contains("<!-- mirrored from mem://cdn/other/sheet.pdf -->"), "{}", written);
+        assert!(written.contains(r#"<Note source="mem://cdn/other/sheet.pdf">mem://cdn/other/sheet.pdf</Note>"#), "{}", written);
+        let report = zip_container.check_references().unwrap();
+        assert!(report.is_clean(), "{:?}", report);
+        assert!(zip_container.localize(None).unwrap().is_empty());
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_localize_json_definition() {
+        let manifest = r#"{"logo": "mem://cdn/logo.png", "name": "mem://cdn/logo.png.txt"}"#;
+        let memory = MemoryFileLoader::new();
+        memory.insert("mem://cdn/logo.png", b"png".to_vec());
+        let mut loader = LoaderRegistry::default();
+        loader.register("mem", memory);
+        let zip_container = ZipContainer {
+            zip_data: Some(zip_with(&[("manifest.json", manifest.as_bytes())])),
+            definition_path: Some("manifest.json".to_string()),
+            reference_rules: Some(crate::ReferenceRules { expressions: vec!["$.logo".to_string()], ..Default::default() }),
+            loader,
+            ..Default::default()
+        };
+        let localized = zip_container.localize_references("assets").unwrap();
+        assert_eq!(localized.definition.as_ref(), r#"{"logo": "assets/logo.png", "name": "mem://cdn/logo.png.txt"}"#);
+        let container = ZipContainer { zip_data: Some(localized.zip_data), ..zip_container.clone() };
+        assert_eq!(container.load_file_from_zip("assets/logo.png").unwrap(), b"png");
+        assert_eq!(container.load_definition_file_str().unwrap(), localized.definition.as_ref());
+    }
+
+    #[cfg(all(feature = "json", feature = "yaml"))]
+    #[test]
+    fn test_localize_rewrites_only_matched_values() {
+        let manifest = "# logo: mem://cdn/logo.png\nmirror: mem://cdn/logo.png\nassets:\n  - mem://cdn/logo.png\n  - name: logo\n    file: mem://cdn/logo.png\n";
+        let memory = MemoryFileLoader::new();
+        memory.insert("mem://cdn/logo.png", b"png".to_vec());
+        let mut loader = LoaderRegistry::default();
+        loader.register("mem", memory);
+        let zip_container = ZipContainer {
+            zip_data: Some(zip_with(&[("manifest.yaml", manifest.as_bytes())])),
+            definition_path: Some("manifest.yaml".to_string()),
+            reference_rules: Some(crate::ReferenceRules {
+                expressions: vec!["$.assets[0]".to_string(), "$.assets[*].file".to_string()],
+                ..Default::default()
+            }),
+            loader,
+            ..Default::default()
+        };
+        let localized = zip_container.localize_references("assets").unwrap();
+        assert_eq!(
+            localized.definition.as_ref(),
+            "# logo: mem://cdn/logo.png\nmirror: mem://cdn/logo.png\nassets:\n  - assets/logo.png\n  - name: logo\n    file: assets/logo.png\n"
+        );
+    }
+
+    #[test]
+    fn test_resolve_references_against_origin() {
+        use crate::loader_registry::resolve_against;
+        assert_eq!(resolve_against("https://host/catalog/x.gldf", "../shared/logo.png"), "https://host/shared/logo.png");
+        assert_eq!(resolve_against("https://host/catalog/x.gldf", "/logo.png?v=2"), "https://host/logo.png?v=2");
+        assert_eq!(resolve_against("https://host/catalog/x.gldf", "https://cdn/a.png"), "https://cdn/a.png");
+        assert_eq!(resolve_against("test_data/test.gldf", "../shared/logo.png"), "../shared/logo.png");
+        assert_eq!(resolve_against("C:\\data\\x.gldf", "logo.png"), "logo.png");
+
+        let memory = MemoryFileLoader::new();
+        memory.insert("mem://host/catalog/x.gldf", zip_with(&[("product.xml", b"<Root/>")]));
+        memory.insert("mem://host/shared/logo.png", b"png".to_vec());
+        let mut loader = LoaderRegistry::default();
+        loader.register("mem", memory);
+        let zip_container = ZipContainer::with_loader("mem://host/catalog/x.gldf".to_string(), None, loader);
+        assert_eq!(zip_container.origin(), Some("mem://host/catalog/x.gldf"));
+        assert_eq!(zip_container.load_file("../shared/logo.png").unwrap(), b"png");
+        assert_eq!(zip_container.load_buf_file("../shared/logo.png").unwrap().content.unwrap(), b"png");
+        let data = futures::executor::block_on(zip_container.load_file_async("../shared/logo.png")).unwrap();
+        assert_eq!(data, b"png");
+        assert_eq!(zip_container.load_file("product.xml").unwrap(), b"<Root/>");
+    }
+
+    #[cfg(feature = "xml")]
+    #[test]
+    fn test_opc_package() {
+        use crate::opc::{resolve_part_uri, OpcChanges, OpcPackage, OpcPart, Relationship};
+        const OFFICE_DOCUMENT: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument";
+        const IMAGE: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships/image";
+        let relationship = |id: &str, relationship_type: &str, target: &str, external: bool| Relationship {
+            id: id.to_string(),
+            relationship_type: relationship_type.to_string(),
+            target: target.to_string(),
+            external,
+        };
+        let part = |name: &str, content_type: &str, data: &[u8]| OpcPart {
+            name: name.to_string(),
+            content_type: content_type.to_string(),
+            data: data.to_vec(),
+        };
+        let changes = OpcChanges {
+            parts: vec![part("/word/document.xml", "application/vnd.openxmlformats-officedocument.wordprocessingml.document.main+xml", b"<w:document/>")],
+            relationships: vec![
+                ("/".to_string(), vec![relationship("rId1", OFFICE_DOCUMENT, "word/document.xml", false)]),
+                (
+                    "/word/document.xml".to_string(),
+                    vec![
+                        relationship("rId1", IMAGE, "media/image%201.png", false),
+                        relationship("rId2", "http://example.com/link", "https://example.com/a?b=1&c=2", true),
+                    ],
+                ),
+            ],
+        };
+        let package = ZipContainer { zip_data: Some(ZipContainer::default().save_opc(&changes).unwrap()), ..Default::default() };
+        let names = package.get_file_names().unwrap();
+        assert_eq!(names, ["[Content_Types].xml", "word/document.xml", "_rels/.rels", "word/_rels/document.xml.rels"]);
+
+        let main = package.related_parts("/", OFFICE_DOCUMENT).unwrap();
+        assert_eq!(main, ["/word/document.xml"]);
+        let relationships = package.relationships(&main[0]).unwrap();
+        assert_eq!(relationships[1].target, "https://example.com/a?b=1&c=2");
+        assert_eq!(relationships[1].target_part(&main[0]), None);
+        assert_eq!(package.related_parts(&main[0], IMAGE).unwrap(), ["/word/media/image 1.png"]);
+        assert_eq!(resolve_part_uri("/word/document.xml", "../customXml/item1.xml"), "/customXml/item1.xml");
+        assert_eq!(resolve_part_uri("/", "/docProps/core.xml"), "/docProps/core.xml");
+        assert_eq!(resolve_part_uri("/", "media/a%20b%+1.png"), "/media/a b%+1.png");
+
+        let image = part("/word/media/image 1.png", "image/png", b"png");
+        let package = ZipContainer {
+            zip_data: Some(package.save_opc(&OpcChanges { parts: vec![image], ..Default::default() }).unwrap()),
+            ..Default::default()
+        };
+        assert_eq!(package.load_part("/word/media/image 1.png").unwrap(), b"png");
+        let content_types: Vec<(String, Option<String>)> =
+            package.get_parts().unwrap().into_iter().map(|part| (part.name.unwrap(), part.content_type)).collect();
+        let content_type = |name: &str| content_types.iter().find(|(part, _)| part == name).unwrap().1.clone();
+        assert_eq!(content_type("_rels/.rels").as_deref(), Some(crate::opc::RELATIONSHIPS_CONTENT_TYPE));
+        assert_eq!(content_type("word/media/image 1.png").as_deref(), Some("image/png"));
+        assert!(content_type("word/document.xml").unwrap().ends_with("document.main+xml"));
+        assert_eq!(content_types.len(), 4);
+    }
+
+    #[cfg(feature = "xml")]
+    #[test]
+    fn test_definition_pointers_and_mimetype_first() {
+        use crate::ProfileDescriptor;
+        let container_xml = br#"<?xml version="1.0"?>
+            <container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+              <rootfiles><rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/></rootfiles>
+            </container>"#;
+        let files = [
+            ("META-INF/container.xml".to_string(), container_xml.to_vec()),
+            ("OEBPS/content.opf".to_string(), b"<package/>".to_vec()),
+            ("mimetype".to_string(), b"application/epub+zip".to_vec()),
+        ];
+        let mut epub = ZipContainer { zip_data: Some(ZipContainer::default().write_entries(&files).unwrap()), ..Default::default() };
+        assert_eq!(epub.get_file_names().unwrap(), ["mimetype", "META-INF/container.xml", "OEBPS/content.opf"]);
+        let profile = ProfileDescriptor {
+            name: "EPUB".to_string(),
+            definition_path: "OEBPS/content.opf".to_string(),
+            optional_entries: vec!["**".to_string()],
+            mimetype: Some("application/epub+zip".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(epub.validate_layout_with(&profile).unwrap(), []);
+        assert_eq!(epub.resolve_definition(), Some(crate::DetectionMethod::Content));
+        assert_eq!(epub.definition_path.as_deref(), Some("OEBPS/content.opf"));
+
+        let manifest = br#"<manifest:manifest xmlns:manifest="urn:oasis:names:tc:opendocument:xmlns:manifest:1.0">
+              <manifest:file-entry manifest:full-path="/" manifest:media-type="application/vnd.oasis.opendocument.text"/>
+              <manifest:file-entry manifest:full-path="content.xml" manifest:media-type="text/xml"/>
+            </manifest:manifest>"#;
+        let entries: &[(&str, &[u8])] = &[
+            ("content.xml", b"<office:document-content/>"),
+            ("META-INF/manifest.xml", manifest),
+            ("mimetype", b"application/vnd.oasis.opendocument.text"),
+        ];
+        let odt = ZipContainer { zip_data: Some(zip_with(entries)), ..Default::default() };
+        assert_eq!(odt.definition_from_pointer().unwrap().as_deref(), Some("content.xml"));
+        let rewritten = odt.write_entries(&[("styles.xml".to_string(), b"<styles/>".to_vec())]).unwrap();
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(rewritten)).unwrap();
+        let first = archive.by_index(0).unwrap();
+        assert_eq!((first.name(), first.compression()), ("mimetype", zip::CompressionMethod::Stored));
+        drop(first);
+        assert_eq!(archive.len(), 4);
+    }
+
+    #[test]
+    fn test_write_entries_keeps_the_last_duplicate_and_the_comment() {
+        use std::io::Write;
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        writer.start_file("a.txt", zip::write::SimpleFileOptions::default()).unwrap();
+        writer.write_all(b"old").unwrap();
+        writer.set_comment("catalog 2024");
+        let commented = ZipContainer { zip_data: Some(writer.finish().unwrap().into_inner()), ..Default::default() };
+        let files = [
+            ("a.txt".to_string(), b"first".to_vec()),
+            ("b.txt".to_string(), b"first".to_vec()),
+            ("a.txt".to_string(), b"last".to_vec()),
+            ("b.txt".to_string(), b"last".to_vec()),
+        ];
+        let rewritten = ZipContainer { zip_data: Some(commented.write_entries(&files).unwrap()), ..Default::default() };
+        assert_eq!(rewritten.get_file_names().unwrap(), ["a.txt", "b.txt"]);
+        assert_eq!(rewritten.load_file_from_zip("a.txt").unwrap(), b"last");
+        assert_eq!(rewritten.load_file_from_zip("b.txt").unwrap(), b"last");
+        let archive = zip::ZipArchive::new(std::io::Cursor::new(rewritten.zip_data.unwrap())).unwrap();
+        assert_eq!(archive.comment(), b"catalog 2024");
+    }
+
     #[cfg(target_arch = "wasm32")]
     #[tokio::test]
     async fn test_async_initialize_zip_container_on_wasm32() {
@@ -141,21 +1645,28 @@ mod tests {
 
 #[cfg(test)]
 #[cfg(target_arch = "wasm32")]
+#[allow(clippy::module_inception)]
 mod tests {
     use crate::wasm_bindings::WasmZipContainer;
     // use crate::ZipContainerTrait;
-    use js_sys::{Promise, Uint8Array, Reflect, Function};
-    use wasm_bindgen::{JsCast, JsValue};
+    use js_sys::Uint8Array;
+    #[cfg(feature = "http")]
+    use js_sys::{Reflect, Function};
+    use wasm_bindgen::JsCast;
+    #[cfg(feature = "http")]
+    use wasm_bindgen::JsValue;
 
     use wasm_bindgen_futures::JsFuture;
     use wasm_bindgen_test::*;
     wasm_bindgen_test_configure!(run_in_browser );
+    #[cfg(feature = "http")]
     #[wasm_bindgen_test]
     async fn test_get_file_names() {
         // Call from_url, which returns a Promise
         let container_promise = WasmZipContainer::from_url(
             "https://raw.githubusercontent.com/holg/gldf-rs/refs/heads/master/tests/data/test.gldf".to_string(),
             Some("product.xml".to_string()),
+            JsValue::UNDEFINED,
         );
 
         // Await the Promise to get the JsValue representing the WasmZipContainer
@@ -186,7 +1697,7 @@ mod tests {
 
         // Read a specific file
         let promise = container.read_file("product.xml");
-        let js_value = JsFuture::from(Promise::from(promise))
+        let js_value = JsFuture::from(promise)
             .await
             .unwrap();
 
@@ -201,4 +1712,14 @@ mod tests {
         // Perform assertions on the content
         assert!(content_str.contains("<Product"));
     }
-}
\ No newline at end of file
+
+    #[wasm_bindgen_test]
+    fn test_errors_are_js_error_objects() {
+        let container = WasmZipContainer::new(Uint8Array::from(&b"not a zip"[..]), None).unwrap();
+        let error = container.get_file_names().unwrap_err();
+        let error = error.dyn_into::<js_sys::Error>().unwrap();
+        assert_eq!(String::from(error.name()), "InvalidArchiveError");
+        let code = js_sys::Reflect::get(&error, &"code".into()).unwrap();
+        assert_eq!(code.as_string().as_deref(), Some("INVALID_ARCHIVE"));
+    }
+}
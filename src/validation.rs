@@ -0,0 +1,144 @@
+// src/validation.rs
+//! Validation of definitions against a schema: an XSD for `Definition::XML`, a JSON Schema
+//! for `Definition::JSON`, `Definition::YAML` and `Definition::TOML`.
+//!
+//! Both validators implement the commonly used subset of their specification:
+//!
+//! * XSD: global and local elements, named and anonymous complex and simple types,
+//!   `sequence`, `choice`, `all`, `group`, `any`, `minOccurs`/`maxOccurs`, attributes
+//!   (`use="required"`, `attributeGroup`, `anyAttribute`), `simpleContent` and
+//!   `complexContent` extensions, restrictions with `enumeration`, `pattern`, length and
+//!   range facets, `list`, `union` and the usual built-in types. Namespaces are matched by
+//!   local name; identity constraints and substitution groups are not checked. Circular
+//!   type or group definitions are rejected when parsing. `import`, `include`, `redefine`
+//!   and `override`, patterns using XSD-only regex syntax and undeclared types are
+//!   reported as diagnostics.
+//! * JSON Schema: `type`, `enum`, `const`, numeric, string, array and object keywords,
+//!   `allOf`/`anyOf`/`oneOf`/`not`, `if`/`then`/`else` and local `$ref`s. `format` is ignored.
+//!   Remote `$ref`s, `$ref` cycles that do not descend into the value and keywords like
+//!   `unevaluatedProperties`, `dependentSchemas` or `propertyNames` are reported as
+//!   diagnostics.
+//!
+//! Both are written for this crate rather than taken from a dependency: there is no pure
+//! Rust XSD validator, and both need to report the paths and source positions above for
+//! YAML and TOML as well, from the same parsed trees `query` and `convert_to` use.
+//!
+//! XML diagnostics point at the offending element. For JSON, YAML and TOML they point at
+//! the innermost key of the path, found by searching the source.
+use crate::document::{locate_key, Segment};
+use crate::xml_tree::LineCounter;
+use crate::{function_path, json_schema, xsd, Definition, Diagnostic, ZipContainerError, ZipContainerResult};
+use serde_json::Value;
+
+/// Where a schema is loaded from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SchemaSource {
+    /// An entry of the container, e.g. `schema/gldf.xsd`.
+    Entry(String),
+    /// A path or URL, loaded through the loader of the container.
+    Location(String),
+    /// The schema itself.
+    Inline(String),
+}
+
+/// A parsed XSD or JSON Schema.
+#[derive(Debug)]
+pub enum Schema {
+    Xsd(Box<xsd::XsdSchema>),
+    JsonSchema(Value),
+}
+
+impl Schema {
+    /// Parses `content` as an XSD if it starts with `<`, otherwise as a JSON Schema.
+    pub fn parse(content: &str) -> ZipContainerResult<Schema> {
+        match content.trim_start_matches('\u{feff}').trim_start().starts_with('<') {
+            true => Schema::xsd(content),
+            false => Schema::json_schema(content),
+        }
+    }
+
+    pub fn xsd(content: &str) -> ZipContainerResult<Schema> {
+        Ok(Schema::Xsd(Box::new(xsd::XsdSchema::parse(content)?)))
+    }
+
+    pub fn json_schema(content: &str) -> ZipContainerResult<Schema> {
+        let schema = serde_json::from_str(content).map_err(|e| ZipContainerError::InvalidData {
+            module_path: function_path!(),
+            message: format!("Invalid JSON Schema: {}", e),
+        })?;
+        Ok(Schema::JsonSchema(schema))
+    }
+
+    /// Validates `definition` and returns the problems found, none if it is valid. A
+    /// definition that cannot be parsed yields a single diagnostic at the syntax error.
+    pub fn validate(&self, definition: &Definition) -> ZipContainerResult<Vec<Diagnostic>> {
+        match (self, definition) {
+            (Schema::Xsd(schema), Definition::XML(content)) => Ok(schema.validate(content)),
+            (Schema::JsonSchema(schema), Definition::JSON(_) | Definition::YAML(_) | Definition::TOML(_)) => {
+                let content = definition.as_ref();
+                let instance = match parse_instance(definition)? {
+                    Ok(instance) => instance,
+                    Err(diagnostic) => return Ok(vec![diagnostic]),
+                };
+                let diagnostics = json_schema::validate(schema, &instance)
+                    .into_iter()
+                    .map(|(path, message)| {
+                        let (line, column) = match locate(content, &path) {
+                            Some((line, column)) => (Some(line), Some(column)),
+                            None => (None, None),
+                        };
+                        Diagnostic { path: json_pointer(&path), message, line, column }
+                    })
+                    .collect();
+                Ok(diagnostics)
+            }
+            (Schema::Xsd(_), _) => Err(ZipContainerError::UnsupportedOperation {
+                module_path: function_path!(),
+                message: format!("An XSD cannot validate a {} definition", definition.format_name()),
+            }),
+            (Schema::JsonSchema(_), _) => Err(ZipContainerError::UnsupportedOperation {
+                module_path: function_path!(),
+                message: "A JSON Schema cannot validate an XML definition".to_string(),
+            }),
+        }
+    }
+}
+
+/// Parses a JSON, YAML or TOML definition, turning syntax errors into a diagnostic.
+fn parse_instance(definition: &Definition) -> ZipContainerResult<Result<Value, Diagnostic>> {
+    let syntax_error = |message: String, position: Option<(usize, usize)>| Diagnostic {
+        path: String::new(),
+        message,
+        line: position.map(|(line, _)| line),
+        column: position.map(|(_, column)| column),
+    };
+    Ok(match definition {
+        Definition::JSON(content) => serde_json::from_str(content)
+            .map_err(|e| syntax_error(e.to_string(), Some((e.line(), e.column())))),
+        #[cfg(feature = "yaml")]
+        Definition::YAML(content) => serde_yaml::from_str(content).map_err(|e| {
+            let position = e.location().map(|location| (location.line(), location.column()));
+            syntax_error(e.to_string(), position)
+        }),
+        #[cfg(feature = "toml")]
+        Definition::TOML(content) => toml::from_str(content).map_err(|e| {
+            let position = e.span().map(|span| LineCounter::new(content).position(span.start));
+            syntax_error(e.message().to_string(), position)
+        }),
+        other => return other.to_document().map(Ok),
+    })
+}
+
+fn json_pointer(path: &[Segment]) -> String {
+    path.iter()
+        .map(|segment| match segment {
+            Segment::Key(key) => format!("/{}", key.replace('~', "~0").replace('/', "~1")),
+            Segment::Index(index) => format!("/{}", index),
+        })
+        .collect()
+}
+
+/// Finds the innermost key of `path` in a JSON, YAML or TOML source, see `locate_key`.
+fn locate(content: &str, path: &[Segment]) -> Option<(usize, usize)> {
+    locate_key(content, path).map(|offset| LineCounter::new(content).position(offset))
+}
@@ -1,13 +1,17 @@
 // src/wasm_bindings.rs
 
+#[cfg(feature = "http")]
+use crate::wasm_fetch::{fetch_bytes, http_options_from_js, integrity_from_js, FetchHooks};
+#[cfg(feature = "http")]
+pub use crate::wasm_fetch::{JsBasicAuth, JsHttpOptions};
 use crate::zip_container_trait::ZipContainerTrait;
-use crate::ZipContainer;
-use js_sys::{Function, Promise, Reflect, Uint8Array};
+use crate::{ZipContainer, ZipContainerError};
+use js_sys::{Promise, Reflect, Uint8Array};
 use serde_wasm_bindgen;
 use std::rc::Rc;
 use wasm_bindgen::prelude::*;
-use wasm_bindgen::JsCast;
-use wasm_bindgen_futures::{future_to_promise, JsFuture};
+#[cfg(feature = "http")]
+use wasm_bindgen_futures::future_to_promise;
 
 #[wasm_bindgen]
 pub struct WasmZipContainer {
@@ -27,90 +31,51 @@ impl WasmZipContainer {
         zip_data.copy_to(&mut data);
 
         // Create ZipContainer instance
-        let zip_container = ZipContainer {
+        let mut zip_container = ZipContainer {
             zip_data: Some(data),
             definition_path: definition_path.clone(),
-            definition_content: None,
-            files: None,
-            zip_path: None,
+            ..Default::default()
         };
+        zip_container.resolve_definition();
 
         Ok(WasmZipContainer {
             inner: Rc::new(zip_container),
         })
     }
 
-    /// Asynchronous constructor that fetches ZIP data from a URL
+    /// Asynchronous constructor that fetches ZIP data from a URL.
+    ///
+    /// `options` is an optional object such as
+    /// `{ headers: { "X-Api-Key": "..." }, bearerToken: "...", timeoutMs: 30000, retries: 3 }`,
+    /// see `JsHttpOptions` for all keys. In addition it may contain
+    /// `onProgress: (received, total) => ...` (`total` is `undefined` without a
+    /// `Content-Length`) and `signal`, an `AbortSignal` to cancel the download.
+    /// `integrity` (hex or `sha256-<base64>`) and `expectedSize` pin the downloaded
+    /// container; the promise is rejected if it does not match.
+    /// Only available with the `http` feature.
+    #[cfg(feature = "http")]
     #[wasm_bindgen]
-    pub fn from_url(url: String, definition_path: Option<String>) -> Promise {
+    pub fn from_url(url: String, definition_path: Option<String>, options: JsValue) -> Promise {
         let definition_path_clone = definition_path.clone();
 
         let fut = async move {
-            // Get the global object (works in both browser and Node.js)
-            let global = js_sys::global();
-
-            // Get the 'fetch' function from the global object
-            let fetch_fn = Reflect::get(&global, &JsValue::from_str("fetch"))
-                .map_err(|_| JsValue::from_str("Failed to get 'fetch' function"))?;
-
-            // Ensure that 'fetch' is a function
-            let fetch_fn = fetch_fn
-                .dyn_into::<Function>()
-                .map_err(|_| JsValue::from_str("'fetch' is not a function"))?;
-
-            // Call 'fetch' with the URL
-            let fetch_promise_value = fetch_fn
-                .call1(&global, &JsValue::from_str(&url))
-                .map_err(|_| JsValue::from_str("Failed to call 'fetch' function"))?;
-
-            // Convert fetch_promise_value to js_sys::Promise
-            let fetch_promise = fetch_promise_value
-                .dyn_into::<js_sys::Promise>()
-                .map_err(|_| JsValue::from_str("Failed to cast fetch result to Promise"))?;
-
-            let resp_value = JsFuture::from(fetch_promise)
-                .await
-                .map_err(|e| JsValue::from_str(&format!("Fetch error: {:?}", e)))?;
-
-            let resp = resp_value
-                .dyn_into::<web_sys::Response>()
-                .map_err(|_| JsValue::from_str("Failed to cast to Response"))?;
-
-            if !resp.ok() {
-                return Err(JsValue::from_str(&format!(
-                    "Network response was not ok: {}",
-                    resp.status()
-                )));
+            let hooks = FetchHooks::from_js(&options);
+            let expected = integrity_from_js(&options)?;
+            let options = http_options_from_js(options)?;
+            let data = fetch_bytes(&url, &options, &hooks).await?;
+            if let Some(expected) = expected {
+                expected.verify(&data, &url)?;
             }
 
-            // Get the array buffer promise
-            let array_buffer_promise_value = resp
-                .array_buffer()
-                .map_err(|_| JsValue::from_str("Failed to get array buffer"))?;
-
-            // Convert to js_sys::Promise
-            let array_buffer_promise = array_buffer_promise_value
-                .dyn_into::<js_sys::Promise>()
-                .map_err(|_| JsValue::from_str("Failed to cast array buffer result to Promise"))?;
-
-            let array_buffer = JsFuture::from(array_buffer_promise)
-                .await
-                .map_err(|e| JsValue::from_str(&format!("ArrayBuffer error: {:?}", e)))?;
-
-            let uint8_array = Uint8Array::new(&array_buffer);
-
-            // Convert Uint8Array to Vec<u8>
-            let mut data = vec![0; uint8_array.length() as usize];
-            uint8_array.copy_to(&mut data);
-
             // Create ZipContainer instance
-            let zip_container = ZipContainer {
+            // The URL is the origin relative references outside the archive are resolved against.
+            let mut zip_container = ZipContainer {
                 zip_data: Some(data),
                 definition_path: definition_path_clone,
-                definition_content: None,
-                files: None,
-                zip_path: None,
+                zip_path: Some(url),
+                ..Default::default()
             };
+            zip_container.resolve_definition();
 
             let wasm_zip_container = WasmZipContainer {
                 inner: Rc::new(zip_container),
@@ -128,7 +93,7 @@ impl WasmZipContainer {
         self.inner
             .get_file_names()
             .map(|names| serde_wasm_bindgen::to_value(&names).unwrap())
-            .map_err(|e| JsValue::from_str(&format!("Error: {:?}", e)))
+            .map_err(JsValue::from)
     }
 
     /// Read a specific file's content from the ZIP archive
@@ -143,7 +108,7 @@ impl WasmZipContainer {
                 .await
                 .map(|content| Uint8Array::from(&content[..]))
                 .map(JsValue::from)
-                .map_err(|e| JsValue::from_str(&format!("Error: {:?}", e)))
+                .map_err(JsValue::from)
         };
 
         wasm_bindgen_futures::future_to_promise(fut)
@@ -151,3 +116,66 @@ impl WasmZipContainer {
 
     // Add additional methods as needed
 }
+
+/// Creates a JavaScript `Error` with the given `name`, a stable `code` and `message`.
+pub(crate) fn js_error(name: &str, code: &str, message: &str) -> js_sys::Error {
+    let error = js_sys::Error::new(message);
+    error.set_name(name);
+    set_property(&error, "code", &JsValue::from_str(code));
+    error
+}
+
+/// Sets `key` on `target`, ignoring failures (only possible for frozen objects).
+pub(crate) fn set_property(target: &JsValue, key: &str, value: &JsValue) {
+    let _ = Reflect::set(target, &JsValue::from_str(key), value);
+}
+
+fn error_name(err: &ZipContainerError) -> &'static str {
+    match err {
+        ZipContainerError::MissingValue { .. } => "MissingValueError",
+        ZipContainerError::IOError { .. } => "ContainerIOError",
+        ZipContainerError::InvalidData { .. } => "InvalidDataError",
+        #[cfg(feature = "http")]
+        ZipContainerError::ReqwestError { .. } => "NetworkError",
+        ZipContainerError::UnsupportedOperation { .. } => "UnsupportedOperationError",
+        ZipContainerError::Cancelled { .. } => "CancelledError",
+        ZipContainerError::IntegrityMismatch { .. } => "IntegrityError",
+        ZipContainerError::EntryNotFound { .. } => "EntryNotFoundError",
+        ZipContainerError::InvalidArchive { .. } => "InvalidArchiveError",
+        ZipContainerError::UnsupportedCompression { .. } => "UnsupportedCompressionError",
+        ZipContainerError::Encrypted { .. } => "EncryptedEntryError",
+        ZipContainerError::LimitExceeded { .. } => "LimitExceededError",
+        ZipContainerError::DefinitionParse { .. } => "DefinitionParseError",
+        ZipContainerError::ValidationFailed { .. } => "ValidationError",
+        ZipContainerError::Utf8Error(_) => "DecodeError",
+    }
+}
+
+impl From<ZipContainerError> for JsValue {
+    /// Converts a `ZipContainerError` into a JavaScript `Error` whose `name` matches the
+    /// Python exception class (e.g. `EntryNotFoundError`), with the properties `code`,
+    /// `entry`, `container` and `modulePath` (`undefined` where not applicable), the
+    /// `diagnostics` of a failed schema validation and the underlying error as `cause`.
+    fn from(err: ZipContainerError) -> JsValue {
+        let error = js_error(error_name(&err), err.code(), &err.to_string());
+        let optional = |value: Option<&str>| value.map_or(JsValue::UNDEFINED, JsValue::from_str);
+        set_property(&error, "entry", &optional(err.entry()));
+        set_property(&error, "container", &optional(err.container()));
+        set_property(&error, "modulePath", &optional(err.module_path()));
+        let diagnostics = js_sys::Array::new();
+        for diagnostic in err.diagnostics() {
+            let item = js_sys::Object::new();
+            let position = |value: Option<usize>| value.map_or(JsValue::UNDEFINED, |value| JsValue::from(value as u32));
+            set_property(&item, "path", &JsValue::from_str(&diagnostic.path));
+            set_property(&item, "message", &JsValue::from_str(&diagnostic.message));
+            set_property(&item, "line", &position(diagnostic.line));
+            set_property(&item, "column", &position(diagnostic.column));
+            diagnostics.push(&item);
+        }
+        set_property(&error, "diagnostics", &diagnostics);
+        if let Some(source) = std::error::Error::source(&err) {
+            error.set_cause(&js_sys::Error::new(&source.to_string()));
+        }
+        error.into()
+    }
+}
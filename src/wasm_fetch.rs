@@ -0,0 +1,401 @@
+// src/wasm_fetch.rs
+//! Downloads for `WasmZipContainer.from_url` using the global `fetch`.
+//! Only available with the `http` feature.
+use crate::http_options::{sleep, HttpAuth, HttpOptions, RetryPolicy};
+use crate::integrity::ExpectedIntegrity;
+use crate::logging::{log_request, Stopwatch};
+use crate::progress::cancelled_error;
+use crate::wasm_bindings::{js_error, set_property};
+use crate::{function_path, ZipContainerError};
+use js_sys::{Function, Object, Promise, Reflect, Uint8Array};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::Duration;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+
+/// HTTP options accepted by `WasmZipContainer.from_url`, using JavaScript naming.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct JsHttpOptions {
+    pub headers: HashMap<String, String>,
+    pub bearer_token: Option<String>,
+    pub basic_auth: Option<JsBasicAuth>,
+    pub timeout_ms: Option<u64>,
+    pub retries: Option<u32>,
+    pub retry_backoff_ms: Option<u64>,
+    pub max_body_size: Option<u64>,
+    pub user_agent: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct JsBasicAuth {
+    pub username: String,
+    pub password: Option<String>,
+}
+
+impl From<JsHttpOptions> for HttpOptions {
+    fn from(js: JsHttpOptions) -> Self {
+        let mut headers: Vec<(String, String)> = js.headers.into_iter().collect();
+        headers.sort();
+        let auth = match (js.bearer_token, js.basic_auth) {
+            (Some(token), _) => Some(HttpAuth::Bearer(token)),
+            (None, Some(basic)) => Some(HttpAuth::Basic {
+                username: basic.username,
+                password: basic.password,
+            }),
+            (None, None) => None,
+        };
+        let mut retry = RetryPolicy::default();
+        if let Some(retries) = js.retries {
+            retry.max_retries = retries;
+        }
+        if let Some(backoff) = js.retry_backoff_ms {
+            retry.initial_backoff = Duration::from_millis(backoff);
+        }
+        HttpOptions {
+            headers,
+            auth,
+            timeout: js.timeout_ms.map(Duration::from_millis),
+            retry,
+            user_agent: js.user_agent,
+            max_body_size: js.max_body_size,
+            ..Default::default()
+        }
+    }
+}
+
+pub(crate) fn http_options_from_js(options: JsValue) -> Result<HttpOptions, JsValue> {
+    if options.is_undefined() || options.is_null() {
+        return Ok(HttpOptions::default());
+    }
+    let js_options: JsHttpOptions =
+        serde_wasm_bindgen::from_value(options).map_err(|e| ZipContainerError::InvalidData {
+            module_path: function_path!(),
+            message: format!("Invalid HTTP options: {}", e),
+        })?;
+    Ok(js_options.into())
+}
+
+/// Reads the `integrity` and `expectedSize` keys of the `from_url` options.
+pub(crate) fn integrity_from_js(options: &JsValue) -> Result<Option<ExpectedIntegrity>, JsValue> {
+    if !options.is_object() {
+        return Ok(None);
+    }
+    let digest = Reflect::get(options, &JsValue::from_str("integrity"))
+        .ok()
+        .and_then(|digest| digest.as_string());
+    let Some(digest) = digest else {
+        return Ok(None);
+    };
+    let mut expected = ExpectedIntegrity::sha256(&digest)?;
+    if let Some(size) = Reflect::get(options, &JsValue::from_str("expectedSize"))
+        .ok()
+        .and_then(|size| size.as_f64())
+    {
+        expected = expected.with_size(size as u64);
+    }
+    Ok(Some(expected))
+}
+
+/// Callbacks of a `from_url` download that cannot be expressed in `HttpOptions`.
+#[derive(Default)]
+pub(crate) struct FetchHooks {
+    /// `onProgress(received, total)`
+    on_progress: Option<Function>,
+    /// `AbortSignal` supplied by the caller
+    signal: Option<web_sys::AbortSignal>,
+}
+
+impl FetchHooks {
+    pub(crate) fn from_js(options: &JsValue) -> FetchHooks {
+        if !options.is_object() {
+            return FetchHooks::default();
+        }
+        FetchHooks {
+            on_progress: Reflect::get(options, &JsValue::from_str("onProgress"))
+                .ok()
+                .and_then(|f| f.dyn_into::<Function>().ok()),
+            signal: Reflect::get(options, &JsValue::from_str("signal"))
+                .ok()
+                .and_then(|signal| signal.dyn_into::<web_sys::AbortSignal>().ok()),
+        }
+    }
+
+    fn is_aborted(&self) -> bool {
+        self.signal.as_ref().is_some_and(|signal| signal.aborted())
+    }
+
+    fn report_progress(&self, received: usize, total: Option<u64>) {
+        if let Some(on_progress) = &self.on_progress {
+            let total = total.map_or(JsValue::UNDEFINED, |total| JsValue::from_f64(total as f64));
+            let _ = on_progress.call2(&JsValue::NULL, &JsValue::from_f64(received as f64), &total);
+        }
+    }
+}
+
+/// Fetches `url` with the global `fetch` (browser and Node.js), retrying as configured.
+pub(crate) async fn fetch_bytes(url: &str, options: &HttpOptions, hooks: &FetchHooks) -> Result<Vec<u8>, JsValue> {
+    let stopwatch = Stopwatch::start();
+    let result = fetch_with_retries(url, options, hooks).await;
+    log_request(url, &result.as_ref().map_err(|e| format!("{:?}", e)), stopwatch);
+    result
+}
+
+async fn fetch_with_retries(url: &str, options: &HttpOptions, hooks: &FetchHooks) -> Result<Vec<u8>, JsValue> {
+    let mut attempt = 0;
+    loop {
+        if hooks.is_aborted() {
+            return Err(cancelled_error(url).into());
+        }
+        match fetch_once(url, options, hooks).await {
+            Ok(data) => return Ok(data),
+            Err(FetchFailure::Retryable(_)) if attempt < options.retry.max_retries && !hooks.is_aborted() => {
+                sleep(options.retry.backoff(attempt)).await;
+                attempt += 1;
+            }
+            Err(_) if hooks.is_aborted() => return Err(cancelled_error(url).into()),
+            Err(FetchFailure::Retryable(e)) | Err(FetchFailure::Fatal(e)) => return Err(e),
+        }
+    }
+}
+
+enum FetchFailure {
+    Retryable(JsValue),
+    Fatal(JsValue),
+}
+
+/// Error for requests that did not produce a response (`code` `NETWORK_ERROR`), with the
+/// rejection of `fetch` or of the body read as `cause`.
+fn network_error(url: &str, message: &str, cause: Option<JsValue>) -> JsValue {
+    let error = js_error("NetworkError", "NETWORK_ERROR", &format!("{}: {}", message, url));
+    set_property(&error, "url", &JsValue::from_str(url));
+    if let Some(cause) = cause {
+        error.set_cause(&cause);
+    }
+    error.into()
+}
+
+/// Error for responses with a non-success status (`code` `HTTP_STATUS`), carrying
+/// `status` and `statusText`.
+fn http_status_error(url: &str, resp: &web_sys::Response) -> JsValue {
+    let message = format!("HTTP status {} {}: {}", resp.status(), resp.status_text(), url);
+    let error = js_error("HttpStatusError", "HTTP_STATUS", &message);
+    set_property(&error, "url", &JsValue::from_str(url));
+    set_property(&error, "status", &JsValue::from_f64(resp.status() as f64));
+    set_property(&error, "statusText", &JsValue::from_str(&resp.status_text()));
+    error.into()
+}
+
+fn body_too_large(url: &str, options: &HttpOptions) -> FetchFailure {
+    FetchFailure::Fatal(
+        ZipContainerError::LimitExceeded {
+            module_path: function_path!(),
+            container: url.to_string(),
+            entry: None,
+            message: format!(
+                "response body exceeds max_body_size of {} bytes",
+                options.max_body_size.unwrap_or_default()
+            ),
+        }
+        .into(),
+    )
+}
+
+async fn fetch_once(url: &str, options: &HttpOptions, hooks: &FetchHooks) -> Result<Vec<u8>, FetchFailure> {
+    let fatal = |message: &str| FetchFailure::Fatal(network_error(url, message, None));
+
+    // Get the global object (works in both browser and Node.js)
+    let global = js_sys::global();
+
+    // Get the 'fetch' function from the global object
+    let fetch_fn = Reflect::get(&global, &JsValue::from_str("fetch"))
+        .map_err(|_| fatal("Failed to get 'fetch' function"))?;
+
+    // Ensure that 'fetch' is a function
+    let fetch_fn = fetch_fn.dyn_into::<Function>().map_err(|_| {
+        FetchFailure::Fatal(
+            ZipContainerError::UnsupportedOperation {
+                module_path: function_path!(),
+                message: "'fetch' is not available in this environment".to_string(),
+            }
+            .into(),
+        )
+    })?;
+
+    // Request init with headers and an abort signal for the timeout and the caller's signal
+    let init = Object::new();
+    let headers = Object::new();
+    for (name, value) in options.request_headers() {
+        Reflect::set(&headers, &JsValue::from_str(&name), &JsValue::from_str(&value))
+            .map_err(|_| fatal("Failed to set request header"))?;
+    }
+    Reflect::set(&init, &JsValue::from_str("headers"), &headers)
+        .map_err(|_| fatal("Failed to set request headers"))?;
+    if options.timeout.is_some() || hooks.signal.is_some() {
+        let controller = web_sys::AbortController::new()
+            .map_err(|_| fatal("Failed to create AbortController"))?;
+        Reflect::set(&init, &JsValue::from_str("signal"), &controller.signal())
+            .map_err(|_| fatal("Failed to set abort signal"))?;
+        if let Some(timeout) = options.timeout {
+            schedule_abort(&global, &controller, timeout);
+        }
+        if let Some(signal) = &hooks.signal {
+            forward_abort(signal, &controller);
+        }
+    }
+
+    // Call 'fetch' with the URL
+    let fetch_promise_value = fetch_fn
+        .call2(&global, &JsValue::from_str(url), &init)
+        .map_err(|_| fatal("Failed to call 'fetch' function"))?;
+
+    // Convert fetch_promise_value to js_sys::Promise
+    let fetch_promise = fetch_promise_value
+        .dyn_into::<js_sys::Promise>()
+        .map_err(|_| fatal("Failed to cast fetch result to Promise"))?;
+
+    let resp_value = JsFuture::from(fetch_promise)
+        .await
+        .map_err(|e| FetchFailure::Retryable(network_error(url, "Request failed", Some(e))))?;
+
+    let resp = resp_value
+        .dyn_into::<web_sys::Response>()
+        .map_err(|_| fatal("Failed to cast to Response"))?;
+
+    if !resp.ok() {
+        let message = http_status_error(url, &resp);
+        return Err(if RetryPolicy::is_retryable_status(resp.status()) {
+            FetchFailure::Retryable(message)
+        } else {
+            FetchFailure::Fatal(message)
+        });
+    }
+
+    let content_length = resp
+        .headers()
+        .get("content-length")
+        .ok()
+        .flatten()
+        .and_then(|length| length.parse::<u64>().ok());
+    if content_length.is_some_and(|length| options.exceeds_max_body_size(length)) {
+        return Err(body_too_large(url, options));
+    }
+
+    let body = Reflect::get(&resp, &JsValue::from_str("body")).unwrap_or(JsValue::UNDEFINED);
+    let data = if hooks.on_progress.is_some() && body.is_object() {
+        read_body_stream(&body, url, options, hooks, content_length).await?
+    } else {
+        read_array_buffer(&resp, url).await?
+    };
+    if options.exceeds_max_body_size(data.len() as u64) {
+        return Err(body_too_large(url, options));
+    }
+    Ok(data)
+}
+
+async fn read_array_buffer(resp: &web_sys::Response, url: &str) -> Result<Vec<u8>, FetchFailure> {
+    let fatal = |message: &str| FetchFailure::Fatal(network_error(url, message, None));
+
+    // Get the array buffer promise
+    let array_buffer_promise_value = resp
+        .array_buffer()
+        .map_err(|_| fatal("Failed to get array buffer"))?;
+
+    // Convert to js_sys::Promise
+    let array_buffer_promise = array_buffer_promise_value
+        .dyn_into::<js_sys::Promise>()
+        .map_err(|_| fatal("Failed to cast array buffer result to Promise"))?;
+
+    let array_buffer = JsFuture::from(array_buffer_promise)
+        .await
+        .map_err(|e| FetchFailure::Retryable(network_error(url, "Reading the response body failed", Some(e))))?;
+
+    let uint8_array = Uint8Array::new(&array_buffer);
+
+    // Convert Uint8Array to Vec<u8>
+    let mut data = vec![0; uint8_array.length() as usize];
+    uint8_array.copy_to(&mut data);
+    Ok(data)
+}
+
+/// Reads the body `ReadableStream` chunk by chunk, reporting progress after each chunk.
+async fn read_body_stream(
+    body: &JsValue,
+    url: &str,
+    options: &HttpOptions,
+    hooks: &FetchHooks,
+    content_length: Option<u64>,
+) -> Result<Vec<u8>, FetchFailure> {
+    let fatal = |message: &str| FetchFailure::Fatal(network_error(url, message, None));
+    let method = |target: &JsValue, name: &str| {
+        Reflect::get(target, &JsValue::from_str(name))
+            .ok()
+            .and_then(|f| f.dyn_into::<Function>().ok())
+            .ok_or_else(|| fatal(&format!("Body stream has no '{}' method", name)))
+    };
+
+    let reader = method(body, "getReader")?
+        .call0(body)
+        .map_err(|_| fatal("Failed to get body reader"))?;
+    let read = method(&reader, "read")?;
+    let mut data = Vec::new();
+    loop {
+        let promise = read
+            .call0(&reader)
+            .ok()
+            .and_then(|promise| promise.dyn_into::<Promise>().ok())
+            .ok_or_else(|| fatal("Failed to read body chunk"))?;
+        let result = JsFuture::from(promise)
+            .await
+            .map_err(|e| FetchFailure::Retryable(network_error(url, "Reading the response body failed", Some(e))))?;
+        let done = Reflect::get(&result, &JsValue::from_str("done"))
+            .ok()
+            .and_then(|done| done.as_bool())
+            .unwrap_or(true);
+        if done {
+            break;
+        }
+        let value = Reflect::get(&result, &JsValue::from_str("value")).unwrap_or(JsValue::UNDEFINED);
+        let chunk = Uint8Array::new(&value);
+        let offset = data.len();
+        data.resize(offset + chunk.length() as usize, 0);
+        chunk.copy_to(&mut data[offset..]);
+        if options.exceeds_max_body_size(data.len() as u64) {
+            if let Ok(cancel) = method(&reader, "cancel") {
+                let _ = cancel.call0(&reader);
+            }
+            return Err(body_too_large(url, options));
+        }
+        hooks.report_progress(data.len(), content_length);
+    }
+    Ok(data)
+}
+
+/// Aborts the request through `controller` when the caller's `signal` fires.
+fn forward_abort(signal: &web_sys::AbortSignal, controller: &web_sys::AbortController) {
+    if signal.aborted() {
+        controller.abort();
+        return;
+    }
+    let controller = controller.clone();
+    let abort = Closure::once_into_js(move || controller.abort());
+    if let Ok(add_event_listener) = Reflect::get(signal, &JsValue::from_str("addEventListener"))
+        .and_then(|f| f.dyn_into::<Function>())
+    {
+        let _ = add_event_listener.call2(signal, &JsValue::from_str("abort"), &abort);
+    }
+}
+
+/// Aborts the request through `controller` once `timeout` has elapsed.
+fn schedule_abort(global: &Object, controller: &web_sys::AbortController, timeout: Duration) {
+    let controller = controller.clone();
+    let abort = Closure::once_into_js(move || controller.abort());
+    if let Ok(set_timeout) = Reflect::get(global, &JsValue::from_str("setTimeout"))
+        .and_then(|f| f.dyn_into::<Function>())
+    {
+        let _ = set_timeout.call2(global, &abort, &JsValue::from_f64(timeout.as_millis() as f64));
+    }
+}
@@ -0,0 +1,104 @@
+// src/writer.rs
+//! Rewriting of ZIP data with replaced and added entries.
+use crate::{function_path, io_err, zip_err, ZipContainerResult};
+use std::io::{Read, Write};
+use zip::write::SimpleFileOptions;
+use zip::CompressionMethod;
+
+/// The entry EPUB and ODF require first and uncompressed.
+const MIMETYPE: &str = "mimetype";
+
+/// Returns `zip_data` with `files` written into it: a file whose name is already an entry
+/// replaces it in place, the others are appended in order. A name given more than once is
+/// written once, with its last content. Untouched entries are copied without recompressing
+/// them and the archive comment is kept; empty `zip_data` starts a new archive. A
+/// `mimetype` entry is always written first and stored. `container` names the source in
+/// errors.
+pub(crate) fn rewrite_archive(zip_data: &[u8], container: &str, files: &[(String, Vec<u8>)]) -> ZipContainerResult<Vec<u8>> {
+    let mut unique: Vec<(&str, &[u8])> = Vec::with_capacity(files.len());
+    for (name, content) in files {
+        match unique.iter_mut().find(|(existing, _)| existing == name) {
+            Some(file) => file.1 = content,
+            None => unique.push((name, content)),
+        }
+    }
+    let files = unique;
+    let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::with_capacity(zip_data.len())));
+    let mut written = vec![false; files.len()];
+    let mimetype = match files.iter().position(|(name, _)| *name == MIMETYPE) {
+        Some(index) => {
+            written[index] = true;
+            Some(files[index].1.to_vec())
+        }
+        None if !zip_data.is_empty() => read_mimetype(zip_data, container)?,
+        None => None,
+    };
+    if let Some(mimetype) = mimetype {
+        write_file(&mut writer, container, MIMETYPE, &mimetype)?;
+    }
+    if !zip_data.is_empty() {
+        copy_entries(zip_data, container, &files, &mut writer, &mut written)?;
+    }
+    for ((name, content), _) in files.iter().zip(written).filter(|(_, written)| !written) {
+        write_file(&mut writer, container, name, content)?;
+    }
+    Ok(zip_err!(writer.finish(), container)?.into_inner())
+}
+
+/// Copies the entries and the comment of `zip_data`, writing those named in `files` instead
+/// and marking them `written`.
+fn copy_entries<W: Write + std::io::Seek>(
+    zip_data: &[u8],
+    container: &str,
+    files: &[(&str, &[u8])],
+    writer: &mut zip::ZipWriter<W>,
+    written: &mut [bool],
+) -> ZipContainerResult<()> {
+    let mut archive = zip_err!(zip::ZipArchive::new(std::io::Cursor::new(zip_data)), container)?;
+    writer.set_raw_comment(archive.comment().into());
+    for i in 0..archive.len() {
+        let entry = zip_err!(archive.by_index_raw(i), container)?;
+        if entry.name() == MIMETYPE {
+            continue;
+        }
+        match files.iter().position(|(name, _)| *name == entry.name()) {
+            Some(index) => {
+                let (name, content) = files[index];
+                drop(entry);
+                write_file(writer, container, name, content)?;
+                written[index] = true;
+            }
+            None => {
+                let name = entry.name().to_string();
+                zip_err!(writer.raw_copy_file(entry), container, &name)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns the content of the `mimetype` entry of `zip_data`, if it has one.
+fn read_mimetype(zip_data: &[u8], container: &str) -> ZipContainerResult<Option<Vec<u8>>> {
+    let mut archive = zip_err!(zip::ZipArchive::new(std::io::Cursor::new(zip_data)), container)?;
+    let Ok(mut entry) = archive.by_name(MIMETYPE) else { return Ok(None) };
+    let mut content = Vec::new();
+    io_err!(entry.read_to_end(&mut content))?;
+    Ok(Some(content))
+}
+
+/// Writes a deflated entry; a `mimetype` entry is stored, as EPUB and ODF require.
+fn write_file<W: Write + std::io::Seek>(
+    writer: &mut zip::ZipWriter<W>,
+    container: &str,
+    name: &str,
+    content: &[u8],
+) -> ZipContainerResult<()> {
+    let method = match name {
+        MIMETYPE => CompressionMethod::Stored,
+        _ => CompressionMethod::Deflated,
+    };
+    let options = SimpleFileOptions::default().compression_method(method);
+    zip_err!(writer.start_file(name, options), container, name)?;
+    io_err!(writer.write_all(content))?;
+    Ok(())
+}
@@ -0,0 +1,233 @@
+// src/xml_tree.rs
+//! A minimal XML element tree with source positions, shared by the XSD validation, the
+//! document mapping and XPath queries.
+use crate::Diagnostic;
+use quick_xml::events::{BytesStart, Event};
+use std::ops::Range;
+
+/// A piece of the content of an element or of the document, in document order.
+#[derive(Debug)]
+#[cfg_attr(not(feature = "json"), allow(dead_code))]
+pub(crate) enum Content {
+    Element(usize),
+    /// Text and CDATA, unescaped; adjacent pieces are merged.
+    Text(String),
+    Comment(String),
+    /// A processing instruction, `target` and `data`.
+    Instruction(String, String),
+    Doctype(String),
+}
+
+/// An element of a parsed XML document with its position in the source.
+#[derive(Debug)]
+pub(crate) struct Node {
+    pub(crate) name: String,
+    pub(crate) attributes: Vec<(String, String)>,
+    pub(crate) children: Vec<usize>,
+    #[cfg_attr(not(feature = "json"), allow(dead_code))]
+    pub(crate) parent: Option<usize>,
+    /// The text directly inside the element, concatenated.
+    pub(crate) text: String,
+    /// The children, text, comments and processing instructions of the element, in order.
+    #[cfg_attr(not(feature = "json"), allow(dead_code))]
+    pub(crate) content: Vec<Content>,
+    /// Byte range of the start tag, or of the whole element if it is empty (`<a/>`).
+    #[cfg_attr(not(feature = "json"), allow(dead_code))]
+    pub(crate) tag: Range<usize>,
+    /// Byte range between the start and the end tag.
+    #[cfg_attr(not(feature = "json"), allow(dead_code))]
+    pub(crate) inner: Range<usize>,
+    #[cfg_attr(not(feature = "validation"), allow(dead_code))]
+    pub(crate) line: usize,
+    #[cfg_attr(not(feature = "validation"), allow(dead_code))]
+    pub(crate) column: usize,
+}
+
+/// The deepest element nesting a document may have, as in `serde_json`. The tree is walked
+/// recursively by the queries and the document mapping, so deeper documents are rejected
+/// instead of overflowing the stack.
+const MAX_DEPTH: usize = 128;
+
+/// A parsed XML document; `nodes[0]` is the root element.
+#[derive(Debug)]
+pub(crate) struct Tree {
+    pub(crate) nodes: Vec<Node>,
+    /// The root element with the comments, processing instructions and document type
+    /// declaration around it, in order. The XML declaration is not kept.
+    #[cfg_attr(not(feature = "json"), allow(dead_code))]
+    pub(crate) prolog: Vec<Content>,
+}
+
+impl Tree {
+    pub(crate) fn parse(content: &str) -> Result<Tree, Diagnostic> {
+        let mut reader = quick_xml::Reader::from_str(content);
+        let mut lines = LineCounter::new(content);
+        let mut nodes: Vec<Node> = Vec::new();
+        let mut stack: Vec<usize> = Vec::new();
+        let mut prolog: Vec<Content> = Vec::new();
+        loop {
+            let offset = reader.buffer_position() as usize;
+            let event = match reader.read_event() {
+                Ok(event) => event,
+                Err(e) => return Err(syntax_error(e.to_string(), lines.position(reader.error_position() as usize))),
+            };
+            match event {
+                Event::Start(start) => {
+                    let tag = offset..reader.buffer_position() as usize;
+                    let index = push_node(&mut nodes, &stack, &start, tag, lines.position(offset))?;
+                    if stack.is_empty() {
+                        prolog.push(Content::Element(index));
+                    }
+                    stack.push(index);
+                }
+                Event::Empty(start) => {
+                    let tag = offset..reader.buffer_position() as usize;
+                    let index = push_node(&mut nodes, &stack, &start, tag, lines.position(offset))?;
+                    if stack.is_empty() {
+                        prolog.push(Content::Element(index));
+                    }
+                }
+                Event::End(_) => {
+                    if let Some(current) = stack.pop() {
+                        nodes[current].inner.end = offset;
+                    }
+                }
+                Event::Text(text) => {
+                    if let Some(&current) = stack.last() {
+                        let text = text.unescape().map_err(|e| syntax_error(e.to_string(), lines.position(offset)))?;
+                        push_text(&mut nodes[current], &text);
+                    }
+                }
+                Event::CData(data) => {
+                    if let Some(&current) = stack.last() {
+                        push_text(&mut nodes[current], &String::from_utf8_lossy(&data));
+                    }
+                }
+                Event::Comment(comment) => {
+                    let comment = Content::Comment(String::from_utf8_lossy(&comment).into_owned());
+                    match stack.last() {
+                        Some(&current) => nodes[current].content.push(comment),
+                        None => prolog.push(comment),
+                    }
+                }
+                Event::PI(instruction) => {
+                    let target = String::from_utf8_lossy(instruction.target()).into_owned();
+                    let data = String::from_utf8_lossy(instruction.content()).trim_start().to_string();
+                    let instruction = Content::Instruction(target, data);
+                    match stack.last() {
+                        Some(&current) => nodes[current].content.push(instruction),
+                        None => prolog.push(instruction),
+                    }
+                }
+                Event::DocType(doctype) => {
+                    prolog.push(Content::Doctype(String::from_utf8_lossy(&doctype).trim().to_string()));
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+        match nodes.is_empty() {
+            true => Err(syntax_error("no root element".to_string(), lines.position(content.len()))),
+            false => Ok(Tree { nodes, prolog }),
+        }
+    }
+
+    pub(crate) fn attribute(&self, node: usize, name: &str) -> Option<&str> {
+        let node = &self.nodes[node];
+        node.attributes.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+    }
+
+    pub(crate) fn kind(&self, node: usize) -> &str {
+        local(&self.nodes[node].name)
+    }
+}
+
+fn syntax_error(message: String, (line, column): (usize, usize)) -> Diagnostic {
+    Diagnostic { path: String::new(), message, line: Some(line), column: Some(column) }
+}
+
+/// Adds the element `start`, its start `tag` at `position`, as a child of the innermost
+/// open element.
+fn push_node(
+    nodes: &mut Vec<Node>,
+    stack: &[usize],
+    start: &BytesStart,
+    tag: Range<usize>,
+    position: (usize, usize),
+) -> Result<usize, Diagnostic> {
+    if stack.is_empty() && !nodes.is_empty() {
+        return Err(syntax_error("more than one root element".to_string(), position));
+    }
+    if stack.len() >= MAX_DEPTH {
+        return Err(syntax_error(format!("elements are nested deeper than {} levels", MAX_DEPTH), position));
+    }
+    let attributes = start
+        .attributes()
+        .map(|attribute| {
+            let attribute = attribute.map_err(|e| e.to_string())?;
+            let value = attribute.unescape_value().map_err(|e| e.to_string())?;
+            Ok((String::from_utf8_lossy(attribute.key.as_ref()).into_owned(), value.into_owned()))
+        })
+        .collect::<Result<Vec<_>, String>>()
+        .map_err(|message| syntax_error(message, position))?;
+    nodes.push(Node {
+        name: String::from_utf8_lossy(start.name().as_ref()).into_owned(),
+        attributes,
+        children: Vec::new(),
+        parent: stack.last().copied(),
+        text: String::new(),
+        content: Vec::new(),
+        inner: tag.end..tag.end,
+        tag,
+        line: position.0,
+        column: position.1,
+    });
+    let index = nodes.len() - 1;
+    if let Some(&parent) = stack.last() {
+        nodes[parent].children.push(index);
+        nodes[parent].content.push(Content::Element(index));
+    }
+    Ok(index)
+}
+
+fn push_text(node: &mut Node, text: &str) {
+    node.text.push_str(text);
+    match node.content.last_mut() {
+        Some(Content::Text(last)) => last.push_str(text),
+        _ => node.content.push(Content::Text(text.to_string())),
+    }
+}
+
+/// Strips the namespace prefix of a qualified name.
+pub(crate) fn local(name: &str) -> &str {
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+/// Converts byte offsets into 1-based line and column numbers; offsets must not decrease
+/// between calls.
+pub(crate) struct LineCounter<'a> {
+    content: &'a str,
+    offset: usize,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> LineCounter<'a> {
+    pub(crate) fn new(content: &'a str) -> LineCounter<'a> {
+        LineCounter { content, offset: 0, line: 1, column: 1 }
+    }
+
+    pub(crate) fn position(&mut self, offset: usize) -> (usize, usize) {
+        let offset = offset.min(self.content.len());
+        for c in self.content[self.offset.min(offset)..offset].chars() {
+            if c == '\n' {
+                self.line += 1;
+                self.column = 1;
+            } else {
+                self.column += 1;
+            }
+        }
+        self.offset = self.offset.max(offset);
+        (self.line, self.column)
+    }
+}
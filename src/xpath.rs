@@ -0,0 +1,503 @@
+// src/xpath.rs
+//! The XPath subset used by `query`, see there for the supported syntax.
+use crate::document::element_value;
+use crate::query::QueryMatch;
+use crate::xml_tree::{local, Tree};
+use crate::{function_path, ZipContainerError, ZipContainerResult};
+
+/// Runs `expression` against an XML document.
+pub(crate) fn query(content: &str, expression: &str) -> ZipContainerResult<Vec<QueryMatch>> {
+    let paths = Parser::new(expression).parse()?;
+    let tree = Tree::parse(content).map_err(|diagnostic| ZipContainerError::InvalidData {
+        module_path: function_path!(),
+        message: format!("Invalid XML: {}", diagnostic),
+    })?;
+    let evaluator = Evaluator { tree: &tree };
+    Ok(evaluator.select(&paths).into_iter().map(|item| evaluator.to_match(item)).collect())
+}
+
+/// A text or attribute value of an XML document.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ValueNode {
+    /// The text of an element without child elements.
+    Text(usize),
+    /// An attribute, by element and index.
+    Attribute(usize, usize),
+}
+
+/// Runs `expression` against a parsed XML document and returns the matched values; matched
+/// elements count as their text if they have no child elements.
+pub(crate) fn query_values(tree: &Tree, expression: &str) -> ZipContainerResult<Vec<ValueNode>> {
+    let paths = Parser::new(expression).parse()?;
+    let evaluator = Evaluator { tree };
+    let values = evaluator.select(&paths).into_iter().filter_map(|item| match item {
+        Item::Element(node) | Item::Text(node) if tree.nodes[node].children.is_empty() => Some(ValueNode::Text(node)),
+        Item::Attribute(node, index) => Some(ValueNode::Attribute(node, index)),
+        _ => None,
+    });
+    Ok(values.collect())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Axis {
+    Child,
+    Attribute,
+    Parent,
+    SelfNode,
+    DescendantOrSelf,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Test {
+    Name(String),
+    Any,
+    Text,
+    Node,
+}
+
+#[derive(Debug)]
+struct Step {
+    axis: Axis,
+    test: Test,
+    predicates: Vec<Expr>,
+}
+
+#[derive(Debug)]
+struct LocationPath {
+    absolute: bool,
+    steps: Vec<Step>,
+}
+
+#[derive(Debug)]
+enum Expr {
+    Or(Vec<Expr>),
+    And(Vec<Expr>),
+    Not(Box<Expr>),
+    Compare(Box<Expr>, &'static str, Box<Expr>),
+    Path(LocationPath),
+    Literal(String),
+    Number(f64),
+    Position,
+    Last,
+}
+
+struct Parser<'a> {
+    expression: &'a str,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(expression: &'a str) -> Parser<'a> {
+        Parser { expression, position: 0 }
+    }
+
+    fn parse(mut self) -> ZipContainerResult<Vec<LocationPath>> {
+        let mut paths = vec![self.path()?];
+        while self.eat("|") {
+            paths.push(self.path()?);
+        }
+        self.skip_whitespace();
+        if self.position < self.expression.len() {
+            return Err(self.error("unexpected input"));
+        }
+        Ok(paths)
+    }
+
+    fn error(&self, message: &str) -> ZipContainerError {
+        ZipContainerError::InvalidData {
+            module_path: function_path!(),
+            message: format!("Invalid XPath '{}': {} at position {}", self.expression, message, self.position),
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.expression[self.position..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.position = self.expression.len() - self.rest().trim_start().len();
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        if self.rest().starts_with(token) {
+            self.position += token.len();
+            return true;
+        }
+        false
+    }
+
+    fn descendant_or_self() -> Step {
+        Step { axis: Axis::DescendantOrSelf, test: Test::Node, predicates: Vec::new() }
+    }
+
+    fn path(&mut self) -> ZipContainerResult<LocationPath> {
+        let mut steps = Vec::new();
+        let absolute = if self.eat("//") {
+            steps.push(Parser::descendant_or_self());
+            true
+        } else if self.eat("/") {
+            self.skip_whitespace();
+            if self.rest().is_empty() || self.rest().starts_with(['|', ']', ')']) {
+                return Ok(LocationPath { absolute: true, steps });
+            }
+            true
+        } else {
+            false
+        };
+        loop {
+            steps.push(self.step()?);
+            if self.eat("//") {
+                steps.push(Parser::descendant_or_self());
+            } else if !self.eat("/") {
+                return Ok(LocationPath { absolute, steps });
+            }
+        }
+    }
+
+    fn step(&mut self) -> ZipContainerResult<Step> {
+        if self.eat("..") {
+            return Ok(Step { axis: Axis::Parent, test: Test::Node, predicates: Vec::new() });
+        }
+        if self.eat(".") {
+            return Ok(Step { axis: Axis::SelfNode, test: Test::Node, predicates: Vec::new() });
+        }
+        let axis = if self.eat("@") { Axis::Attribute } else { Axis::Child };
+        let test = if self.eat("*") {
+            Test::Any
+        } else if self.eat("text()") {
+            Test::Text
+        } else if self.eat("node()") {
+            Test::Node
+        } else {
+            Test::Name(self.name()?)
+        };
+        let mut predicates = Vec::new();
+        while self.eat("[") {
+            predicates.push(self.or()?);
+            if !self.eat("]") {
+                return Err(self.error("expected ']'"));
+            }
+        }
+        Ok(Step { axis, test, predicates })
+    }
+
+    fn name(&mut self) -> ZipContainerResult<String> {
+        self.skip_whitespace();
+        let rest = self.rest();
+        let starts_name = rest.starts_with(|c: char| c.is_alphabetic() || c == '_');
+        let length = rest
+            .find(|c: char| !(c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | ':')))
+            .unwrap_or(rest.len());
+        if !starts_name || length == 0 {
+            return Err(self.error("expected a name"));
+        }
+        let name = &rest[..length];
+        if let Some((axis, _)) = name.split_once("::") {
+            return Err(self.error(&format!("the axis '{}::' is not supported", axis)));
+        }
+        if rest[length..].trim_start().starts_with('(') {
+            return Err(self.error(&format!("the function '{}()' is not supported", name)));
+        }
+        self.position += length;
+        Ok(name.to_string())
+    }
+
+    fn or(&mut self) -> ZipContainerResult<Expr> {
+        let mut operands = vec![self.and()?];
+        while self.eat_keyword("or") {
+            operands.push(self.and()?);
+        }
+        Ok(if operands.len() == 1 { operands.remove(0) } else { Expr::Or(operands) })
+    }
+
+    fn and(&mut self) -> ZipContainerResult<Expr> {
+        let mut operands = vec![self.comparison()?];
+        while self.eat_keyword("and") {
+            operands.push(self.comparison()?);
+        }
+        Ok(if operands.len() == 1 { operands.remove(0) } else { Expr::And(operands) })
+    }
+
+    /// Eats `keyword` if it is followed by whitespace, so names like `order` stay names.
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        self.skip_whitespace();
+        let rest = self.rest();
+        if rest.starts_with(keyword) && rest[keyword.len()..].starts_with(char::is_whitespace) {
+            self.position += keyword.len();
+            return true;
+        }
+        false
+    }
+
+    fn comparison(&mut self) -> ZipContainerResult<Expr> {
+        let left = self.operand()?;
+        for operator in ["!=", "<=", ">=", "=", "<", ">"] {
+            if self.eat(operator) {
+                let right = self.operand()?;
+                return Ok(Expr::Compare(Box::new(left), operator, Box::new(right)));
+            }
+        }
+        Ok(left)
+    }
+
+    fn operand(&mut self) -> ZipContainerResult<Expr> {
+        self.skip_whitespace();
+        let rest = self.rest();
+        if let Some(quote) = rest.chars().next().filter(|c| *c == '\'' || *c == '"') {
+            let end = rest[1..].find(quote).ok_or_else(|| self.error("unterminated string"))?;
+            self.position += end + 2;
+            return Ok(Expr::Literal(rest[1..end + 1].to_string()));
+        }
+        if rest.starts_with(|c: char| c.is_ascii_digit() || c == '-') {
+            let length = rest[1..].find(|c: char| !(c.is_ascii_digit() || c == '.')).map_or(rest.len(), |i| i + 1);
+            let number = rest[..length].parse().map_err(|_| self.error("invalid number"))?;
+            self.position += length;
+            return Ok(Expr::Number(number));
+        }
+        if self.eat("last()") {
+            return Ok(Expr::Last);
+        }
+        if self.eat("position()") {
+            return Ok(Expr::Position);
+        }
+        if self.eat("not(") {
+            let inner = self.or()?;
+            if !self.eat(")") {
+                return Err(self.error("expected ')'"));
+            }
+            return Ok(Expr::Not(Box::new(inner)));
+        }
+        if self.eat("(") {
+            let inner = self.or()?;
+            if !self.eat(")") {
+                return Err(self.error("expected ')'"));
+            }
+            return Ok(inner);
+        }
+        Ok(Expr::Path(self.path()?))
+    }
+}
+
+/// A node of the document: the document itself, an element, an attribute of an element
+/// or the text directly inside an element.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Item {
+    Root,
+    Element(usize),
+    Attribute(usize, usize),
+    Text(usize),
+}
+
+impl Item {
+    /// Orders items in document order: an element, its attributes, its text, its children.
+    fn order_key(&self) -> (usize, usize, usize) {
+        match *self {
+            Item::Root => (0, 0, 0),
+            Item::Element(node) => (node + 1, 0, 0),
+            Item::Attribute(node, index) => (node + 1, 1, index),
+            Item::Text(node) => (node + 1, 2, 0),
+        }
+    }
+}
+
+fn sort_unique(items: &mut Vec<Item>) {
+    items.sort_by_key(Item::order_key);
+    items.dedup();
+}
+
+enum XValue {
+    Nodes(Vec<Item>),
+    Text(String),
+    Number(f64),
+    Boolean(bool),
+}
+
+struct Evaluator<'a> {
+    tree: &'a Tree,
+}
+
+impl Evaluator<'_> {
+    fn select(&self, paths: &[LocationPath]) -> Vec<Item> {
+        let mut items = Vec::new();
+        for path in paths {
+            items.extend(self.select_path(path, Item::Root));
+        }
+        sort_unique(&mut items);
+        items
+    }
+
+    fn select_path(&self, path: &LocationPath, context: Item) -> Vec<Item> {
+        let mut items = vec![if path.absolute { Item::Root } else { context }];
+        for step in &path.steps {
+            let mut selected = Vec::new();
+            for &item in &items {
+                let candidates: Vec<Item> = self.axis(step.axis, item).into_iter().filter(|&c| self.test(&step.test, c)).collect();
+                selected.extend(self.filter(candidates, &step.predicates));
+            }
+            sort_unique(&mut selected);
+            items = selected;
+        }
+        items
+    }
+
+    fn axis(&self, axis: Axis, item: Item) -> Vec<Item> {
+        match (axis, item) {
+            (Axis::Child, Item::Root) => vec![Item::Element(0)],
+            (Axis::Child, Item::Element(node)) => {
+                let element = &self.tree.nodes[node];
+                let text = (!element.text.trim().is_empty()).then_some(Item::Text(node));
+                text.into_iter().chain(element.children.iter().map(|&child| Item::Element(child))).collect()
+            }
+            (Axis::Attribute, Item::Element(node)) => (0..self.tree.nodes[node].attributes.len())
+                .filter(|&index| !self.tree.nodes[node].attributes[index].0.starts_with("xmlns"))
+                .map(|index| Item::Attribute(node, index))
+                .collect(),
+            (Axis::Parent, Item::Element(node)) => vec![self.tree.nodes[node].parent.map_or(Item::Root, Item::Element)],
+            (Axis::Parent, Item::Attribute(node, _) | Item::Text(node)) => vec![Item::Element(node)],
+            (Axis::SelfNode, item) => vec![item],
+            (Axis::DescendantOrSelf, Item::Root) => {
+                std::iter::once(Item::Root).chain((0..self.tree.nodes.len()).map(Item::Element)).collect()
+            }
+            (Axis::DescendantOrSelf, Item::Element(node)) => {
+                let mut items = Vec::new();
+                self.descendants(node, &mut items);
+                items
+            }
+            (Axis::DescendantOrSelf, item) => vec![item],
+            _ => Vec::new(),
+        }
+    }
+
+    fn descendants(&self, node: usize, items: &mut Vec<Item>) {
+        items.push(Item::Element(node));
+        for &child in &self.tree.nodes[node].children {
+            self.descendants(child, items);
+        }
+    }
+
+    fn test(&self, test: &Test, item: Item) -> bool {
+        let name = match item {
+            Item::Element(node) => &self.tree.nodes[node].name,
+            Item::Attribute(node, index) => &self.tree.nodes[node].attributes[index].0,
+            Item::Text(_) => return matches!(test, Test::Text | Test::Node),
+            Item::Root => return *test == Test::Node,
+        };
+        match test {
+            Test::Name(expected) if expected.contains(':') => name == expected,
+            Test::Name(expected) => local(name) == expected,
+            Test::Any | Test::Node => true,
+            Test::Text => false,
+        }
+    }
+
+    fn filter(&self, mut items: Vec<Item>, predicates: &[Expr]) -> Vec<Item> {
+        for predicate in predicates {
+            let size = items.len();
+            items = items
+                .into_iter()
+                .enumerate()
+                .filter(|&(index, item)| match self.evaluate(predicate, item, index + 1, size) {
+                    XValue::Number(number) => number == (index + 1) as f64,
+                    value => truthy(&value),
+                })
+                .map(|(_, item)| item)
+                .collect();
+        }
+        items
+    }
+
+    fn evaluate(&self, expr: &Expr, item: Item, position: usize, size: usize) -> XValue {
+        match expr {
+            Expr::Or(operands) => XValue::Boolean(operands.iter().any(|e| truthy(&self.evaluate(e, item, position, size)))),
+            Expr::And(operands) => XValue::Boolean(operands.iter().all(|e| truthy(&self.evaluate(e, item, position, size)))),
+            Expr::Not(inner) => XValue::Boolean(!truthy(&self.evaluate(inner, item, position, size))),
+            Expr::Compare(left, operator, right) => {
+                let left = self.evaluate(left, item, position, size);
+                let right = self.evaluate(right, item, position, size);
+                XValue::Boolean(self.compare(&left, operator, &right))
+            }
+            Expr::Path(path) => XValue::Nodes(self.select_path(path, item)),
+            Expr::Literal(text) => XValue::Text(text.clone()),
+            Expr::Number(number) => XValue::Number(*number),
+            Expr::Position => XValue::Number(position as f64),
+            Expr::Last => XValue::Number(size as f64),
+        }
+    }
+
+    /// Compares like XPath 1.0: node sets match if any of their string values does.
+    fn compare(&self, left: &XValue, operator: &str, right: &XValue) -> bool {
+        let numeric = matches!(left, XValue::Number(_)) || matches!(right, XValue::Number(_)) || !matches!(operator, "=" | "!=");
+        let left = self.strings(left);
+        let right = self.strings(right);
+        left.iter().any(|l| {
+            right.iter().any(|r| {
+                if numeric {
+                    match (l.trim().parse::<f64>(), r.trim().parse::<f64>()) {
+                        (Ok(l), Ok(r)) => compare_ordered(l, operator, r),
+                        _ => false,
+                    }
+                } else {
+                    compare_ordered(l.as_str(), operator, r.as_str())
+                }
+            })
+        })
+    }
+
+    fn strings(&self, value: &XValue) -> Vec<String> {
+        match value {
+            XValue::Nodes(items) => items.iter().map(|&item| self.string_value(item)).collect(),
+            XValue::Text(text) => vec![text.clone()],
+            XValue::Number(number) => vec![number.to_string()],
+            XValue::Boolean(boolean) => vec![boolean.to_string()],
+        }
+    }
+
+    fn string_value(&self, item: Item) -> String {
+        match item {
+            Item::Root => self.string_value(Item::Element(0)),
+            Item::Element(node) => {
+                let element = &self.tree.nodes[node];
+                let mut text = element.text.clone();
+                for &child in &element.children {
+                    text.push_str(&self.string_value(Item::Element(child)));
+                }
+                text
+            }
+            Item::Attribute(node, index) => self.tree.nodes[node].attributes[index].1.clone(),
+            Item::Text(node) => self.tree.nodes[node].text.clone(),
+        }
+    }
+
+    fn to_match(&self, item: Item) -> QueryMatch {
+        match item {
+            Item::Root => {
+                let mut document = serde_json::Map::new();
+                document.insert(self.tree.nodes[0].name.clone(), element_value(self.tree, 0));
+                QueryMatch::from_value(&serde_json::Value::Object(document))
+            }
+            Item::Element(node) => QueryMatch::from_value(&element_value(self.tree, node)),
+            Item::Attribute(..) | Item::Text(_) => QueryMatch::Value(self.string_value(item)),
+        }
+    }
+}
+
+fn truthy(value: &XValue) -> bool {
+    match value {
+        XValue::Nodes(items) => !items.is_empty(),
+        XValue::Text(text) => !text.is_empty(),
+        XValue::Number(number) => *number != 0.0 && !number.is_nan(),
+        XValue::Boolean(boolean) => *boolean,
+    }
+}
+
+fn compare_ordered<T: PartialOrd>(left: T, operator: &str, right: T) -> bool {
+    match operator {
+        "=" => left == right,
+        "!=" => left != right,
+        "<" => left < right,
+        "<=" => left <= right,
+        ">" => left > right,
+        _ => left >= right,
+    }
+}
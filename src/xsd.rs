@@ -0,0 +1,806 @@
+// src/xsd.rs
+//! The XSD subset used by `validation`, see there for the supported constructs.
+//! Circular definitions are rejected when parsing; `import`, `include`, `redefine` and
+//! `override`, patterns the regex crate cannot express and unknown types are reported
+//! as diagnostics.
+use crate::xml_tree::{local, Tree};
+use crate::{function_path, Diagnostic, ZipContainerError, ZipContainerResult};
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
+
+/// The type of an element or attribute.
+#[derive(Clone, Copy, Debug)]
+enum XsdType<'a> {
+    Builtin(&'a str),
+    Simple(usize),
+    Complex(usize),
+    Any,
+}
+
+/// The content model of a complex type with its base types merged in.
+#[derive(Default)]
+struct ComplexModel<'a> {
+    particles: Vec<usize>,
+    attributes: Vec<usize>,
+    any_attribute: bool,
+    simple_content: Option<XsdType<'a>>,
+    mixed: bool,
+}
+
+/// Where matching the children of an element failed and the element names expected there.
+struct Failure {
+    at: usize,
+    expected: Vec<String>,
+}
+
+type Step = Result<Option<usize>, Failure>;
+
+/// A parsed XSD with its global components indexed by name.
+#[derive(Debug)]
+pub struct XsdSchema {
+    tree: Tree,
+    elements: HashMap<String, usize>,
+    complex_types: HashMap<String, usize>,
+    simple_types: HashMap<String, usize>,
+    groups: HashMap<String, usize>,
+    attribute_groups: HashMap<String, usize>,
+    attributes: HashMap<String, usize>,
+    /// The compiled `pattern` facets by node, or why they are not supported.
+    patterns: HashMap<usize, Result<Regex, String>>,
+    /// Messages for schema constructs that are not supported.
+    unsupported: Vec<String>,
+}
+
+impl XsdSchema {
+    pub(crate) fn parse(content: &str) -> ZipContainerResult<XsdSchema> {
+        let invalid = |message: String| ZipContainerError::InvalidData {
+            module_path: function_path!(),
+            message: format!("Invalid XSD: {}", message),
+        };
+        let tree = Tree::parse(content).map_err(|diagnostic| invalid(diagnostic.to_string()))?;
+        if tree.kind(0) != "schema" {
+            return Err(invalid(format!("the root element is '{}', not 'schema'", tree.nodes[0].name)));
+        }
+        let mut schema = XsdSchema {
+            elements: HashMap::new(),
+            complex_types: HashMap::new(),
+            simple_types: HashMap::new(),
+            groups: HashMap::new(),
+            attribute_groups: HashMap::new(),
+            attributes: HashMap::new(),
+            patterns: HashMap::new(),
+            unsupported: Vec::new(),
+            tree,
+        };
+        for &child in &schema.tree.nodes[0].children {
+            let Some(name) = schema.tree.attribute(child, "name") else { continue };
+            let index = match schema.tree.kind(child) {
+                "element" => &mut schema.elements,
+                "complexType" => &mut schema.complex_types,
+                "simpleType" => &mut schema.simple_types,
+                "group" => &mut schema.groups,
+                "attributeGroup" => &mut schema.attribute_groups,
+                "attribute" => &mut schema.attributes,
+                _ => continue,
+            };
+            index.insert(name.to_string(), child);
+        }
+        for (node, element) in schema.tree.nodes.iter().enumerate() {
+            match schema.tree.kind(node) {
+                "pattern" => {
+                    let pattern = schema.tree.attribute(node, "value").unwrap_or_default();
+                    schema.patterns.insert(node, compile_pattern(pattern));
+                }
+                kind @ ("import" | "include" | "redefine" | "override") => {
+                    let location = schema.tree.attribute(node, "schemaLocation").or_else(|| schema.tree.attribute(node, "namespace"));
+                    schema.unsupported.push(format!(
+                        "the schema's xs:{} of '{}' (line {}) is not supported, its components are unknown",
+                        kind,
+                        location.unwrap_or_default(),
+                        element.line
+                    ));
+                }
+                _ => {}
+            }
+        }
+        schema.check_cycles().map_err(invalid)?;
+        Ok(schema)
+    }
+
+    /// Fails if a type, group or attribute group depends on itself other than through an
+    /// element, which would make the validator recurse forever.
+    fn check_cycles(&self) -> Result<(), String> {
+        let mut done = HashSet::new();
+        let components = [&self.complex_types, &self.simple_types, &self.groups, &self.attribute_groups];
+        for &node in components.iter().flat_map(|index| index.values()) {
+            self.visit(node, &mut done, &mut Vec::new())?;
+        }
+        Ok(())
+    }
+
+    fn visit(&self, node: usize, done: &mut HashSet<usize>, stack: &mut Vec<usize>) -> Result<(), String> {
+        if done.contains(&node) {
+            return Ok(());
+        }
+        if let Some(start) = stack.iter().position(|&visiting| visiting == node) {
+            let names: Vec<&str> = stack[start..]
+                .iter()
+                .chain([&node])
+                .map(|&component| self.tree.attribute(component, "name").unwrap_or_default())
+                .collect();
+            return Err(format!("circular definition '{}'", names.join("' -> '")));
+        }
+        stack.push(node);
+        let mut dependencies = Vec::new();
+        self.dependencies(node, &mut dependencies);
+        for dependency in dependencies {
+            self.visit(dependency, done, stack)?;
+        }
+        stack.pop();
+        done.insert(node);
+        Ok(())
+    }
+
+    /// The global components the content of `node` is made of, not looking into elements.
+    fn dependencies(&self, node: usize, dependencies: &mut Vec<usize>) {
+        for &child in &self.tree.nodes[node].children {
+            let mut types = Vec::new();
+            match self.tree.kind(child) {
+                "element" => continue,
+                "group" | "attributeGroup" if self.resolve(child) != child => dependencies.push(self.resolve(child)),
+                "extension" | "restriction" => types.extend(self.tree.attribute(child, "base")),
+                "list" => types.extend(self.tree.attribute(child, "itemType")),
+                "union" => types.extend(self.tree.attribute(child, "memberTypes").unwrap_or_default().split_whitespace()),
+                _ => {}
+            }
+            for name in types {
+                if let XsdType::Complex(component) | XsdType::Simple(component) = self.named_type(name) {
+                    dependencies.push(component);
+                }
+            }
+            self.dependencies(child, dependencies);
+        }
+    }
+
+    /// Validates an XML document and returns the problems found.
+    pub(crate) fn validate(&self, content: &str) -> Vec<Diagnostic> {
+        let document = match Tree::parse(content) {
+            Ok(document) => document,
+            Err(diagnostic) => return vec![diagnostic],
+        };
+        let unsupported = self.unsupported.iter().map(|message| Diagnostic {
+            path: String::new(),
+            message: message.clone(),
+            line: None,
+            column: None,
+        });
+        let mut validator = Validator { schema: self, document: &document, diagnostics: unsupported.collect() };
+        let root_name = local(&document.nodes[0].name);
+        let path = format!("/{}", document.nodes[0].name);
+        match self.elements.get(root_name) {
+            Some(&declaration) => validator.validate_element(0, declaration, &path),
+            None => validator.report(0, &path, format!("no global element declaration for root element '{}'", root_name)),
+        }
+        validator.diagnostics
+    }
+
+    fn named_type<'s>(&'s self, name: &'s str) -> XsdType<'s> {
+        let name = local(name);
+        if let Some(&node) = self.complex_types.get(name) {
+            XsdType::Complex(node)
+        } else if let Some(&node) = self.simple_types.get(name) {
+            XsdType::Simple(node)
+        } else if matches!(name, "anyType" | "anySimpleType") {
+            XsdType::Any
+        } else {
+            XsdType::Builtin(name)
+        }
+    }
+
+    /// The type of an element or attribute declaration: its `type` or its anonymous type.
+    fn declared_type(&self, declaration: usize, default: XsdType<'static>) -> XsdType<'_> {
+        if let Some(name) = self.tree.attribute(declaration, "type") {
+            return self.named_type(name);
+        }
+        for &child in &self.tree.nodes[declaration].children {
+            match self.tree.kind(child) {
+                "complexType" => return XsdType::Complex(child),
+                "simpleType" => return XsdType::Simple(child),
+                _ => {}
+            }
+        }
+        default
+    }
+
+    /// Follows the `ref` of an element, attribute, group or attribute group.
+    fn resolve(&self, node: usize) -> usize {
+        let Some(reference) = self.tree.attribute(node, "ref") else { return node };
+        let index = match self.tree.kind(node) {
+            "element" => &self.elements,
+            "attribute" => &self.attributes,
+            "group" => &self.groups,
+            "attributeGroup" => &self.attribute_groups,
+            _ => return node,
+        };
+        index.get(local(reference)).copied().unwrap_or(node)
+    }
+
+    fn name_of(&self, node: usize) -> &str {
+        let node = self.resolve(node);
+        self.tree.attribute(node, "name").or_else(|| self.tree.attribute(node, "ref")).map(local).unwrap_or_default()
+    }
+
+    fn occurs(&self, node: usize) -> (usize, Option<usize>) {
+        let min = self.tree.attribute(node, "minOccurs").and_then(|v| v.parse().ok()).unwrap_or(1);
+        let max = match self.tree.attribute(node, "maxOccurs") {
+            Some("unbounded") => None,
+            Some(value) => Some(value.parse().unwrap_or(1)),
+            None => Some(1),
+        };
+        (min, max)
+    }
+
+    /// The children of a model group that are particles themselves.
+    fn particles(&self, node: usize) -> impl Iterator<Item = usize> + '_ {
+        self.tree.nodes[node]
+            .children
+            .iter()
+            .copied()
+            .filter(|&child| matches!(self.tree.kind(child), "element" | "sequence" | "choice" | "all" | "group" | "any"))
+    }
+
+    /// The model group a `group` element refers to.
+    fn group_content(&self, node: usize) -> Option<usize> {
+        self.particles(self.resolve(node)).next()
+    }
+
+    fn nullable(&self, particle: usize) -> bool {
+        if self.occurs(particle).0 == 0 {
+            return true;
+        }
+        match self.tree.kind(particle) {
+            "sequence" | "all" => self.particles(particle).all(|item| self.nullable(item)),
+            "choice" => self.particles(particle).any(|item| self.nullable(item)),
+            "group" => self.group_content(particle).is_none_or(|content| self.nullable(content)),
+            _ => false,
+        }
+    }
+
+    /// The element names a particle can start with.
+    fn first_names(&self, particle: usize, names: &mut Vec<String>) {
+        match self.tree.kind(particle) {
+            "element" => names.push(self.name_of(particle).to_string()),
+            "any" => names.push("any element".to_string()),
+            "group" => {
+                if let Some(content) = self.group_content(particle) {
+                    self.first_names(content, names);
+                }
+            }
+            "sequence" => {
+                for item in self.particles(particle) {
+                    self.first_names(item, names);
+                    if !self.nullable(item) {
+                        break;
+                    }
+                }
+            }
+            _ => {
+                for item in self.particles(particle) {
+                    self.first_names(item, names);
+                }
+            }
+        }
+        names.dedup();
+    }
+
+    fn complex_model<'s>(&'s self, node: usize, model: &mut ComplexModel<'s>) {
+        if self.tree.attribute(node, "mixed") == Some("true") {
+            model.mixed = true;
+        }
+        for &child in &self.tree.nodes[node].children {
+            match self.tree.kind(child) {
+                "sequence" | "choice" | "all" | "group" => model.particles.push(child),
+                "attribute" => model.attributes.push(self.resolve(child)),
+                "attributeGroup" => self.complex_model(self.resolve(child), model),
+                "anyAttribute" => model.any_attribute = true,
+                "simpleContent" | "complexContent" => {
+                    if self.tree.attribute(child, "mixed") == Some("true") {
+                        model.mixed = true;
+                    }
+                    for &derivation in &self.tree.nodes[child].children {
+                        let base = self.tree.attribute(derivation, "base").map(|base| self.named_type(base));
+                        let restriction = self.tree.kind(derivation) == "restriction";
+                        match base {
+                            Some(XsdType::Complex(base)) if restriction && self.tree.kind(child) == "complexContent" => {
+                                let mut inherited = ComplexModel::default();
+                                self.complex_model(base, &mut inherited);
+                                model.attributes.extend(inherited.attributes);
+                                model.any_attribute |= inherited.any_attribute;
+                            }
+                            Some(XsdType::Complex(base)) => self.complex_model(base, model),
+                            Some(base) if self.tree.kind(child) == "simpleContent" => {
+                                model.simple_content = Some(match restriction {
+                                    true => XsdType::Simple(derivation),
+                                    false => base,
+                                })
+                            }
+                            _ => {}
+                        }
+                        self.complex_model(derivation, model);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn check_simple(&self, simple_type: XsdType<'_>, value: &str) -> Result<(), String> {
+        match simple_type {
+            XsdType::Builtin(name) => check_builtin(name, value),
+            XsdType::Simple(node) => self.check_derivation(node, value),
+            XsdType::Complex(_) | XsdType::Any => Ok(()),
+        }
+    }
+
+    /// Checks a value against a `simpleType` or the `restriction` inside one.
+    fn check_derivation(&self, node: usize, value: &str) -> Result<(), String> {
+        if self.tree.kind(node) == "restriction" {
+            if let Some(base) = self.tree.attribute(node, "base") {
+                self.check_simple(self.named_type(base), value)?;
+            }
+            return self.check_facets(node, value);
+        }
+        for &child in &self.tree.nodes[node].children {
+            match self.tree.kind(child) {
+                "restriction" => {
+                    let base = match self.tree.attribute(child, "base") {
+                        Some(base) => self.named_type(base),
+                        None => self.declared_type(child, XsdType::Any),
+                    };
+                    self.check_simple(base, value)?;
+                    self.check_facets(child, value)?;
+                }
+                "list" => {
+                    let item_type = match self.tree.attribute(child, "itemType") {
+                        Some(item_type) => self.named_type(item_type),
+                        None => self.declared_type(child, XsdType::Any),
+                    };
+                    for item in value.split_whitespace() {
+                        self.check_simple(item_type, item)?;
+                    }
+                }
+                "union" => {
+                    let mut members: Vec<XsdType<'_>> = self
+                        .tree
+                        .attribute(child, "memberTypes")
+                        .unwrap_or_default()
+                        .split_whitespace()
+                        .map(|name| self.named_type(name))
+                        .collect();
+                    members.extend(self.tree.nodes[child].children.iter().map(|&inline| XsdType::Simple(inline)));
+                    if !members.iter().any(|&member| self.check_simple(member, value).is_ok()) {
+                        return Err(format!("'{}' does not match any member type of the union", value));
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn check_facets(&self, restriction: usize, value: &str) -> Result<(), String> {
+        let mut enumeration = Vec::new();
+        let mut patterns = Vec::new();
+        let length = value.chars().count();
+        let number = value.trim().parse::<f64>().ok();
+        for &facet in &self.tree.nodes[restriction].children {
+            let Some(limit) = self.tree.attribute(facet, "value") else { continue };
+            let numeric = limit.parse::<f64>().ok();
+            let bound = limit.parse::<usize>().ok();
+            match self.tree.kind(facet) {
+                "enumeration" => enumeration.push(limit),
+                "pattern" => patterns.push(facet),
+                "length" if bound.is_some_and(|bound| length != bound) => {
+                    return Err(format!("'{}' must be {} characters long", value, limit));
+                }
+                "minLength" if bound.is_some_and(|bound| length < bound) => {
+                    return Err(format!("'{}' is shorter than {} characters", value, limit));
+                }
+                "maxLength" if bound.is_some_and(|bound| length > bound) => {
+                    return Err(format!("'{}' is longer than {} characters", value, limit));
+                }
+                "minInclusive" if matches!((number, numeric), (Some(n), Some(l)) if n < l) => {
+                    return Err(format!("{} is less than the minimum of {}", value, limit));
+                }
+                "maxInclusive" if matches!((number, numeric), (Some(n), Some(l)) if n > l) => {
+                    return Err(format!("{} is greater than the maximum of {}", value, limit));
+                }
+                "minExclusive" if matches!((number, numeric), (Some(n), Some(l)) if n <= l) => {
+                    return Err(format!("{} must be greater than {}", value, limit));
+                }
+                "maxExclusive" if matches!((number, numeric), (Some(n), Some(l)) if n >= l) => {
+                    return Err(format!("{} must be less than {}", value, limit));
+                }
+                _ => {}
+            }
+        }
+        if !enumeration.is_empty() && !enumeration.contains(&value.trim()) {
+            return Err(format!("'{}' is not one of '{}'", value, enumeration.join("', '")));
+        }
+        // Patterns of one restriction are alternatives
+        let mut regexes = Vec::new();
+        for &facet in &patterns {
+            match &self.patterns[&facet] {
+                Ok(regex) => regexes.push(regex),
+                Err(message) => return Err(message.clone()),
+            }
+        }
+        if !regexes.is_empty() && !regexes.iter().any(|regex| regex.is_match(value)) {
+            let patterns: Vec<&str> = patterns.iter().filter_map(|&facet| self.tree.attribute(facet, "value")).collect();
+            return Err(format!("'{}' does not match the pattern '{}'", value, patterns.join("' or '")));
+        }
+        Ok(())
+    }
+}
+
+fn check_builtin(name: &str, value: &str) -> Result<(), String> {
+    let trimmed = value.trim();
+    let valid = match name {
+        "boolean" => matches!(trimmed, "true" | "false" | "1" | "0"),
+        "integer" | "int" | "long" | "short" | "byte" | "nonNegativeInteger" | "positiveInteger"
+        | "nonPositiveInteger" | "negativeInteger" | "unsignedLong" | "unsignedInt" | "unsignedShort"
+        | "unsignedByte" => match trimmed.trim_start_matches('+').parse::<i128>() {
+            Ok(number) => integer_in_range(name, number),
+            Err(_) => false,
+        },
+        "decimal" => {
+            let digits = trimmed.trim_start_matches(['+', '-']);
+            digits.chars().any(|c| c.is_ascii_digit())
+                && digits.chars().all(|c| c.is_ascii_digit() || c == '.')
+                && digits.matches('.').count() <= 1
+        }
+        "double" | "float" => {
+            matches!(trimmed, "INF" | "-INF" | "NaN")
+                || (trimmed.parse::<f64>().is_ok() && !trimmed.chars().any(|c| c.is_ascii_alphabetic() && c != 'e' && c != 'E'))
+        }
+        "date" => {
+            static DATE: OnceLock<Regex> = OnceLock::new();
+            date_regex(&DATE, r"-?\d{4,}-\d{2}-\d{2}").is_match(trimmed)
+        }
+        "dateTime" => {
+            static DATE_TIME: OnceLock<Regex> = OnceLock::new();
+            date_regex(&DATE_TIME, r"-?\d{4,}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}(\.\d+)?").is_match(trimmed)
+        }
+        "time" => {
+            static TIME: OnceLock<Regex> = OnceLock::new();
+            date_regex(&TIME, r"\d{2}:\d{2}:\d{2}(\.\d+)?").is_match(trimmed)
+        }
+        // Not checked beyond being text
+        "string" | "normalizedString" | "token" | "language" | "Name" | "NCName" | "ID" | "IDREF" | "IDREFS"
+        | "ENTITY" | "ENTITIES" | "NMTOKEN" | "NMTOKENS" | "QName" | "NOTATION" | "anyURI" | "base64Binary"
+        | "hexBinary" | "duration" | "dayTimeDuration" | "yearMonthDuration" | "gYear" | "gYearMonth" | "gMonth"
+        | "gMonthDay" | "gDay" | "dateTimeStamp" | "anyAtomicType" => true,
+        _ => return Err(format!("type '{}' is neither declared in the schema nor a built-in type", name)),
+    };
+    match valid {
+        true => Ok(()),
+        false => Err(format!("'{}' is not a valid {}", value, name)),
+    }
+}
+
+fn integer_in_range(name: &str, number: i128) -> bool {
+    let (min, max): (i128, i128) = match name {
+        "long" => (i64::MIN.into(), i64::MAX.into()),
+        "int" => (i32::MIN.into(), i32::MAX.into()),
+        "short" => (i16::MIN.into(), i16::MAX.into()),
+        "byte" => (i8::MIN.into(), i8::MAX.into()),
+        "nonNegativeInteger" => (0, i128::MAX),
+        "positiveInteger" => (1, i128::MAX),
+        "nonPositiveInteger" => (i128::MIN, 0),
+        "negativeInteger" => (i128::MIN, -1),
+        "unsignedLong" => (0, u64::MAX.into()),
+        "unsignedInt" => (0, u32::MAX.into()),
+        "unsignedShort" => (0, u16::MAX.into()),
+        "unsignedByte" => (0, u8::MAX.into()),
+        _ => (i128::MIN, i128::MAX),
+    };
+    (min..=max).contains(&number)
+}
+
+/// Anchors a date or time pattern, allows a time zone suffix and compiles it once.
+fn date_regex(cell: &'static OnceLock<Regex>, pattern: &str) -> &'static Regex {
+    cell.get_or_init(|| Regex::new(&format!(r"^{}(Z|[+-]\d{{2}}:\d{{2}})?$", pattern)).expect("valid date pattern"))
+}
+
+/// Compiles an XSD pattern facet, which always matches the whole value. Character class
+/// subtraction and the `\i`, `\c` and `\p{Is...}` escapes have no regex crate equivalent.
+fn compile_pattern(pattern: &str) -> Result<Regex, String> {
+    let unsupported = |reason: &str| format!("the pattern '{}' is not supported: {}", pattern, reason);
+    if pattern.contains("-[") {
+        return Err(unsupported("character class subtraction"));
+    }
+    if ["\\i", "\\I", "\\c", "\\C", "\\p{Is", "\\P{Is"].iter().any(|escape| pattern.contains(escape)) {
+        return Err(unsupported("XML name and block escapes"));
+    }
+    Regex::new(&format!("^(?:{})$", pattern)).map_err(|e| unsupported(&e.to_string()))
+}
+
+struct Validator<'a> {
+    schema: &'a XsdSchema,
+    document: &'a Tree,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Validator<'_> {
+    fn report(&mut self, node: usize, path: &str, message: String) {
+        let node = &self.document.nodes[node];
+        self.diagnostics.push(Diagnostic {
+            path: path.to_string(),
+            message,
+            line: Some(node.line),
+            column: Some(node.column),
+        });
+    }
+
+    fn validate_element(&mut self, node: usize, declaration: usize, path: &str) {
+        let schema = self.schema;
+        let element = &self.document.nodes[node];
+        let name = local(&element.name).to_string();
+        if self.document.attribute(node, "xsi:nil") == Some("true") {
+            if schema.tree.attribute(declaration, "nillable") != Some("true") {
+                self.report(node, path, format!("element '{}' is not nillable", name));
+            } else if !element.children.is_empty() || !element.text.trim().is_empty() {
+                self.report(node, path, format!("element '{}' is nil but has content", name));
+            }
+            return;
+        }
+        match schema.declared_type(declaration, XsdType::Any) {
+            XsdType::Any => {}
+            XsdType::Complex(complex_type) => {
+                let mut model = ComplexModel::default();
+                schema.complex_model(complex_type, &mut model);
+                self.validate_attributes(node, &model, path);
+                match model.simple_content {
+                    Some(simple_type) => self.validate_simple_content(node, simple_type, path),
+                    None => {
+                        if !model.mixed && !element.text.trim().is_empty() {
+                            self.report(node, path, format!("element '{}' cannot contain text", name));
+                        }
+                        self.validate_children(node, &model.particles, path);
+                    }
+                }
+            }
+            simple_type => {
+                self.validate_attributes(node, &ComplexModel::default(), path);
+                self.validate_simple_content(node, simple_type, path);
+            }
+        }
+    }
+
+    fn validate_simple_content(&mut self, node: usize, simple_type: XsdType<'_>, path: &str) {
+        let element = &self.document.nodes[node];
+        if let Some(&child) = element.children.first() {
+            let message = format!("element '{}' cannot contain child elements", local(&element.name));
+            return self.report(child, path, message);
+        }
+        if let Err(message) = self.schema.check_simple(simple_type, element.text.trim()) {
+            self.report(node, path, message);
+        }
+    }
+
+    fn validate_attributes(&mut self, node: usize, model: &ComplexModel<'_>, path: &str) {
+        let schema = self.schema;
+        let element = &self.document.nodes[node];
+        let element_name = local(&element.name);
+        for (name, value) in &element.attributes {
+            if name.starts_with("xmlns") || name.starts_with("xsi:") || name.starts_with("xml:") {
+                continue;
+            }
+            let declaration = model.attributes.iter().copied().find(|&attribute| schema.name_of(attribute) == local(name));
+            match declaration {
+                Some(declaration) if schema.tree.attribute(declaration, "use") == Some("prohibited") => {
+                    self.report(node, path, format!("attribute '{}' is not allowed on element '{}'", name, element_name));
+                }
+                Some(declaration) => {
+                    let attribute_type = schema.declared_type(declaration, XsdType::Builtin("string"));
+                    if let Err(message) = schema.check_simple(attribute_type, value) {
+                        self.report(node, path, format!("attribute '{}': {}", name, message));
+                    }
+                }
+                None if !model.any_attribute => {
+                    self.report(node, path, format!("attribute '{}' is not allowed on element '{}'", name, element_name));
+                }
+                None => {}
+            }
+        }
+        for &declaration in &model.attributes {
+            let attribute = schema.name_of(declaration);
+            let present = element.attributes.iter().any(|(name, _)| local(name) == attribute);
+            if !present && schema.tree.attribute(declaration, "use") == Some("required") {
+                self.report(node, path, format!("element '{}' is missing required attribute '{}'", element_name, attribute));
+            }
+        }
+    }
+
+    fn validate_children(&mut self, node: usize, particles: &[usize], path: &str) {
+        let children = self.document.nodes[node].children.clone();
+        let mut assigned = vec![None; children.len()];
+        let mut position = 0;
+        let mut failure = None;
+        for &particle in particles {
+            match self.match_particle(particle, &children, position, &mut assigned) {
+                Ok(Some(next)) => position = next,
+                Ok(None) => {
+                    let mut expected = Vec::new();
+                    self.schema.first_names(particle, &mut expected);
+                    failure = Some(Failure { at: position, expected });
+                    break;
+                }
+                Err(e) => {
+                    failure = Some(e);
+                    break;
+                }
+            }
+        }
+        if failure.is_none() && position < children.len() {
+            failure = Some(Failure { at: position, expected: Vec::new() });
+        }
+        if let Some(failure) = failure {
+            self.report_failure(node, &children, failure, path);
+        }
+        for (index, &child) in children.iter().enumerate() {
+            if let Some(declaration) = assigned[index] {
+                let child_path = self.child_path(node, child, path);
+                self.validate_element(child, declaration, &child_path);
+            }
+        }
+    }
+
+    fn report_failure(&mut self, node: usize, children: &[usize], failure: Failure, path: &str) {
+        let parent = local(&self.document.nodes[node].name).to_string();
+        let expected = match failure.expected.len() {
+            0 => String::new(),
+            1 => format!("'{}'", failure.expected[0]),
+            _ => format!("one of '{}'", failure.expected.join("', '")),
+        };
+        match children.get(failure.at) {
+            Some(&child) => {
+                let mut message = format!("unexpected element '{}' in '{}'", local(&self.document.nodes[child].name), parent);
+                if !expected.is_empty() {
+                    message.push_str(&format!(", expected {}", expected));
+                }
+                let child_path = self.child_path(node, child, path);
+                self.report(child, &child_path, message);
+            }
+            None => self.report(node, path, format!("element '{}' is missing child element {}", parent, expected)),
+        }
+    }
+
+    /// The path of a child, with a 1-based index if it has siblings of the same name.
+    fn child_path(&self, parent: usize, child: usize, path: &str) -> String {
+        let name = &self.document.nodes[child].name;
+        let siblings: Vec<usize> = self.document.nodes[parent]
+            .children
+            .iter()
+            .copied()
+            .filter(|&sibling| &self.document.nodes[sibling].name == name)
+            .collect();
+        match siblings.len() {
+            1 => format!("{}/{}", path, name),
+            _ => {
+                let index = siblings.iter().position(|&sibling| sibling == child).unwrap_or_default() + 1;
+                format!("{}/{}[{}]", path, name, index)
+            }
+        }
+    }
+
+    /// Matches a particle with its occurrence bounds, starting at child `position`.
+    /// `Ok(None)` means the particle cannot start there, `Err` that it started but failed.
+    fn match_particle(&self, particle: usize, children: &[usize], position: usize, assigned: &mut [Option<usize>]) -> Step {
+        let (min, max) = self.schema.occurs(particle);
+        let mut current = position;
+        let mut count = 0;
+        while max.is_none_or(|max| count < max) {
+            match self.match_once(particle, children, current, assigned)? {
+                Some(next) if next > current => {
+                    current = next;
+                    count += 1;
+                }
+                Some(_) => {
+                    count = count.max(min);
+                    break;
+                }
+                None => break,
+            }
+        }
+        if count >= min {
+            Ok(Some(current))
+        } else if current == position {
+            Ok(None)
+        } else {
+            let mut expected = Vec::new();
+            self.schema.first_names(particle, &mut expected);
+            Err(Failure { at: current, expected })
+        }
+    }
+
+    fn match_once(&self, particle: usize, children: &[usize], position: usize, assigned: &mut [Option<usize>]) -> Step {
+        let schema = self.schema;
+        match schema.tree.kind(particle) {
+            "element" => {
+                let matches = children
+                    .get(position)
+                    .is_some_and(|&child| local(&self.document.nodes[child].name) == schema.name_of(particle));
+                if !matches {
+                    return Ok(None);
+                }
+                assigned[position] = Some(schema.resolve(particle));
+                Ok(Some(position + 1))
+            }
+            "any" => Ok((position < children.len()).then_some(position + 1)),
+            "group" => match schema.group_content(particle) {
+                Some(content) => self.match_particle(content, children, position, assigned),
+                None => Ok(Some(position)),
+            },
+            "sequence" => {
+                let mut current = position;
+                for item in schema.particles(particle) {
+                    match self.match_particle(item, children, current, assigned)? {
+                        Some(next) => current = next,
+                        None if current == position => return Ok(None),
+                        None => {
+                            let mut expected = Vec::new();
+                            schema.first_names(item, &mut expected);
+                            return Err(Failure { at: current, expected });
+                        }
+                    }
+                }
+                Ok(Some(current))
+            }
+            "choice" => {
+                let mut nullable = false;
+                for item in schema.particles(particle) {
+                    match self.match_particle(item, children, position, assigned)? {
+                        Some(next) if next > position => return Ok(Some(next)),
+                        Some(_) => nullable = true,
+                        None => {}
+                    }
+                }
+                Ok(nullable.then_some(position))
+            }
+            "all" => {
+                let items: Vec<usize> = schema.particles(particle).collect();
+                let mut matched = vec![false; items.len()];
+                let mut current = position;
+                'next: loop {
+                    for (index, &item) in items.iter().enumerate() {
+                        if matched[index] {
+                            continue;
+                        }
+                        if let Some(next) = self.match_particle(item, children, current, assigned)? {
+                            if next > current {
+                                matched[index] = true;
+                                current = next;
+                                continue 'next;
+                            }
+                        }
+                    }
+                    break;
+                }
+                let mut missing = Vec::new();
+                for (index, &item) in items.iter().enumerate() {
+                    if !matched[index] && !schema.nullable(item) {
+                        schema.first_names(item, &mut missing);
+                    }
+                }
+                match (missing.is_empty(), current == position) {
+                    (true, _) => Ok(Some(current)),
+                    (false, true) => Ok(None),
+                    (false, false) => Err(Failure { at: current, expected: missing }),
+                }
+            }
+            _ => Ok(Some(position)),
+        }
+    }
+}
@@ -1,97 +1,185 @@
 // src/zip_container_trait.rs
 
+use crate::integrity::ExpectedIntegrity;
 use crate::{
-    function_path, io_err, reqwest_err, BufFile,ZipContainerError,
-    ZipContainerResult,
+    detection, encoding, function_path, io_err, ok_or_err, zip_err, BufFile, Definition, DefinitionKind,
+    LoaderRegistry, SourceEncoding, ZipContainerError, ZipContainerResult,
 };
+#[cfg(feature = "json")]
+use crate::references::{self, ReferenceReport, ReferenceRules};
+#[cfg(feature = "json")]
+use crate::localize::{self, LocalizedContainer};
+#[cfg(feature = "json")]
+use crate::QueryMatch;
+use crate::writer;
+#[cfg(all(feature = "json", feature = "xml"))]
+use crate::file_table::{self, FileTableEntry};
+#[cfg(feature = "validation")]
+use crate::{Schema, SchemaSource};
+use crate::profile::{self, ContainerProfile, LayoutEntry};
+use crate::Diagnostic;
+use crate::logging::{LogFuture, Stopwatch};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 use std::future::Future;
 use std::io::Read as StdIoRead;
 use std::pin::Pin;
 use std::string::FromUtf8Error;
-#[cfg(not(target_arch = "wasm32"))]
-use std::{path::Path as StdPath, fs::File as StdFile};
+
+/// Container source reported in errors for containers created from raw bytes.
+pub const IN_MEMORY_SOURCE: &str = "<memory>";
+
+/// Opens the entry `name` of `archive` for reading. Encrypted entries and compression methods
+/// that are not compiled in are told from the entry's metadata, not from `zip` messages.
+fn open_entry<'a, R: std::io::Read + std::io::Seek>(
+    archive: &'a mut zip::ZipArchive<R>,
+    container: &str,
+    name: &str,
+) -> ZipContainerResult<zip::read::ZipFile<'a, R>> {
+    let index = archive.index_for_name(name).ok_or_else(|| ZipContainerError::EntryNotFound {
+        module_path: function_path!(),
+        container: container.to_string(),
+        entry: name.to_string(),
+    })?;
+    let raw = zip_err!(archive.by_index_raw(index), container, name)?;
+    if raw.encrypted() {
+        let (container, entry) = (container.to_string(), Some(name.to_string()));
+        return Err(ZipContainerError::Encrypted { module_path: function_path!(), container, entry });
+    }
+    let compression = raw.compression();
+    if !zip::SUPPORTED_COMPRESSION_METHODS.contains(&compression) {
+        return Err(ZipContainerError::UnsupportedCompression {
+            module_path: function_path!(),
+            container: container.to_string(),
+            entry: Some(name.to_string()),
+            message: format!("compression method {} is not supported", compression),
+        });
+    }
+    drop(raw);
+    zip_err!(archive.by_index(index), container, name)
+}
+
+/// Returns the event of loading `path_or_url` through the loader.
+fn fallback_message(path_or_url: &str) -> String {
+    let scheme = crate::loader_registry::scheme_of(path_or_url)
+        .unwrap_or_else(|| crate::loader_registry::DEFAULT_SCHEME.to_string());
+    format!("'{}' is not in the archive, loading it through the '{}' loader", path_or_url, scheme)
+}
+
+/// Returns the event of a load of `location` through the loader that gave `loaded`, with its duration.
+fn load_message<T>(location: &str, loaded: &ZipContainerResult<T>, size: impl Fn(&T) -> usize, stopwatch: Stopwatch) -> String {
+    match loaded {
+        Ok(loaded) => format!("loaded {} bytes from '{}' in {:.1?}", size(loaded), location, stopwatch.elapsed()),
+        Err(e) => format!("loading '{}' failed after {:.1?}: {}", location, stopwatch.elapsed(), e),
+    }
+}
+
+fn buf_file_size(file: &BufFile) -> usize {
+    file.content.as_ref().map_or(0, Vec::len)
+}
+
+/// Returns the error of a `load_file` whose loader fallback failed with `loader_error`: for a
+/// plain path, which only the archive could hold, the `EntryNotFound` of the archive lookup.
+fn fallback_error(zip_error: ZipContainerError, path_or_url: &str, loader_error: ZipContainerError) -> ZipContainerError {
+    let plain_path = crate::loader_registry::scheme_of(path_or_url).is_none();
+    match zip_error {
+        ZipContainerError::EntryNotFound { .. } if plain_path => zip_error,
+        _ => loader_error,
+    }
+}
 
 #[cfg(not(target_arch = "wasm32"))]
-type ZipContainerFuture<T> = Pin<Box<dyn Future<Output = ZipContainerResult<T>> + Send>>;
+pub type ZipContainerFuture<T> = Pin<Box<dyn Future<Output = ZipContainerResult<T>> + Send>>;
 
 #[cfg(target_arch = "wasm32")]
-type ZipContainerFuture<T> = Pin<Box<dyn Future<Output = ZipContainerResult<T>>>>;
+pub type ZipContainerFuture<T> = Pin<Box<dyn Future<Output = ZipContainerResult<T>>>>;
 
 pub trait AsyncFileLoader {
     fn load_async(&self, path_or_url: &str) -> ZipContainerFuture<Vec<u8>>;
+
+    /// Loads `path_or_url` into a `BufFile`; loaders that know the media type
+    /// of the resource (e.g. `data:` URLs) fill `content_type`.
+    fn load_buf_file_async(&self, path_or_url: &str) -> ZipContainerFuture<BufFile> {
+        let future = self.load_async(path_or_url);
+        let name = path_or_url.to_string();
+        Box::pin(async move { Ok(buf_file_from_bytes(name, future.await?)) })
+    }
+
+    /// Loads `path_or_url` and fails with `ZipContainerError::IntegrityMismatch`
+    /// if the bytes do not match `expected`.
+    fn load_verified_async(&self, path_or_url: &str, expected: &ExpectedIntegrity) -> ZipContainerFuture<Vec<u8>> {
+        let future = self.load_async(path_or_url);
+        let location = path_or_url.to_string();
+        let expected = expected.clone();
+        Box::pin(async move {
+            let data = future.await?;
+            expected.verify(&data, &location)?;
+            Ok(data)
+        })
+    }
 }
 
 // Synchronous FileLoader trait
 pub trait FileLoader {
     fn load(&self, path_or_url: &str) -> ZipContainerResult<Vec<u8>>;
+
+    /// Loads `path_or_url` into a `BufFile`; loaders that know the media type
+    /// of the resource (e.g. `data:` URLs) fill `content_type`.
+    fn load_buf_file(&self, path_or_url: &str) -> ZipContainerResult<BufFile> {
+        Ok(buf_file_from_bytes(path_or_url.to_string(), self.load(path_or_url)?))
+    }
+
+    /// Loads `path_or_url` and fails with `ZipContainerError::IntegrityMismatch`
+    /// if the bytes do not match `expected`.
+    fn load_verified(&self, path_or_url: &str, expected: &ExpectedIntegrity) -> ZipContainerResult<Vec<u8>> {
+        let data = self.load(path_or_url)?;
+        expected.verify(&data, path_or_url)?;
+        Ok(data)
+    }
+
+    /// Returns whether `path_or_url` can be loaded. Loaders that can tell without reading
+    /// the content override this, e.g. with an HTTP `HEAD` request; by default it is loaded.
+    fn exists(&self, path_or_url: &str) -> bool {
+        self.load(path_or_url).is_ok()
+    }
 }
 
-// UnifiedFileLoader available on all targets
+/// Reads the definition file through `load_file` and decodes it, see `encoding::decode`.
+pub(crate) fn read_definition_file<C: ZipContainerTrait>(container: &C) -> ZipContainerResult<(String, SourceEncoding)> {
+    let definition_path = container.definition_path()?;
+    let definition_content = container.load_file(definition_path)?;
+    encoding::decode(&definition_content).map_err(|e| container.definition_parse_error(e.to_string()))
+}
+
+fn buf_file_from_bytes(name: String, content: Vec<u8>) -> BufFile {
+    BufFile {
+        name: Some(name.clone()),
+        path: Some(name),
+        size: Some(content.len() as u64),
+        content: Some(content),
+        ..Default::default()
+    }
+}
+
+// UnifiedFileLoader available on all targets, dispatching through the shared `LoaderRegistry`
 pub struct UnifiedFileLoader;
 
 impl FileLoader for UnifiedFileLoader {
     fn load(&self, path_or_url: &str) -> ZipContainerResult<Vec<u8>> {
-        #[cfg(not(target_arch = "wasm32"))]
-        {
-            if path_or_url.starts_with("http://") || path_or_url.starts_with("https://") {
-                // Synchronous HTTP requests using reqwest::blocking
-                let response = reqwest_err!(reqwest::blocking::get(path_or_url))?;
-                let bytes = reqwest_err!(response.bytes())?;
-                Ok(bytes.to_vec())
-            } else {
-                // Read from the local filesystem
-                let mut file = io_err!(StdFile::open(StdPath::new(path_or_url)))?;
-                let mut buffer = Vec::new();
-                io_err!(file.read_to_end(&mut buffer))?;
-                Ok(buffer)
-            }
-        }
-        #[cfg(target_arch = "wasm32")]
-        {
-            // Return an error indicating that synchronous loading isn't supported in WASM
-            Err(ZipContainerError::UnsupportedOperation {
-                module_path: function_path!(),
-                message: format!("Synchronous file loading is not supported in WASM: {}", path_or_url),
-            })
-        }
+        LoaderRegistry::shared().load(path_or_url)
+    }
+
+    fn exists(&self, path_or_url: &str) -> bool {
+        LoaderRegistry::shared().exists(path_or_url)
     }
 }
 
-// Unified asynchronous file loader
+// Unified asynchronous file loader, dispatching through the shared `LoaderRegistry`
 pub struct UnifiedAsyncFileLoader;
 
 impl AsyncFileLoader for UnifiedAsyncFileLoader {
     fn load_async(&self, path_or_url: &str) -> ZipContainerFuture<Vec<u8>> {
-        let path_or_url = path_or_url.to_string(); // Own the data
-        Box::pin(async move {
-            if path_or_url.starts_with("http://") || path_or_url.starts_with("https://") {
-                // Load from URL using asynchronous HTTP client
-                let response = reqwest_err!(reqwest::get(&path_or_url).await)?;
-                let bytes = reqwest_err!(response.bytes().await)?;
-                Ok(bytes.to_vec())
-            } else {
-                #[cfg(not(target_arch = "wasm32"))]
-                {
-                    // Load from local filesystem asynchronously using tokio::fs
-                    use tokio::fs::File;
-                    use tokio::io::AsyncReadExt;
-
-                    let mut file = io_err!(File::open(StdPath::new(&path_or_url)).await)?;
-                    let mut buffer = Vec::new();
-                    io_err!(file.read_to_end(&mut buffer).await)?;
-                    Ok(buffer)
-                }
-                #[cfg(target_arch = "wasm32")]
-                {
-                    // Return an error since local file access is not available in the browser
-                    Err(ZipContainerError::UnsupportedOperation {
-                        module_path: function_path!(),
-                        message: "Local file access is not supported in this environment"
-                            .to_string(),
-                    })
-                }
-            }
-        })
+        LoaderRegistry::shared().load_async(path_or_url)
     }
 }
 
@@ -112,37 +200,49 @@ pub trait ZipContainerTrait: Clone + Send + Sync + 'static {
     /// Returns the path to the definition file within the ZIP.
     fn definition_path(&self) -> ZipContainerResult<&str>;
 
+    /// Returns where the container was loaded from (path or URL), used in error reports.
+    fn container_source(&self) -> &str {
+        IN_MEMORY_SOURCE
+    }
+
+    /// Reports an event of this container; by default as a `log` record with the
+    /// container source as prefix.
+    fn log_event(&self, level: log::Level, message: &str) {
+        crate::logging::emit(level, self.container_source(), message, None);
+    }
+
     /// Returns a list of file names in the ZIP archive.
     fn get_file_names(&self) -> ZipContainerResult<Vec<String>> {
         let zip_data = self.zip_data()?;
         let reader = std::io::Cursor::new(zip_data);
-        let mut zip = io_err!(zip::ZipArchive::new(reader))?;
+        let mut zip = zip_err!(zip::ZipArchive::new(reader), self.container_source())?;
         let mut file_names = Vec::new();
 
         for i in 0..zip.len() {
-            let file = io_err!(zip.by_index(i))?;
+            let file = zip_err!(zip.by_index_raw(i), self.container_source())?;
             file_names.push(file.name().to_string());
         }
 
+        self.log_event(log::Level::Debug, &format!("listed {} entries", file_names.len()));
         Ok(file_names)
     }
 
-    /// Returns a list of file names in the ZIP archive.
+    /// Returns a list of file names in the ZIP archive, with the content types of `profile`.
     fn get_zip_files(&self) -> ZipContainerResult<Vec<BufFile>> {
         let zip_data = self.zip_data()?;
         let reader = std::io::Cursor::new(zip_data);
-        let mut zip = io_err!(zip::ZipArchive::new(reader))?;
+        let mut zip = zip_err!(zip::ZipArchive::new(reader), self.container_source())?;
         let mut zip_files:Vec<BufFile> = Vec::new();
 
         for i in 0..zip.len() {
-            let file = io_err!(zip.by_index(i))?;
-            zip_files.push({
-                let mut buf_file = BufFile::default();
-                buf_file.name = Some(file.name().to_string());
-                buf_file.size = Some(file.size());
-                buf_file.path = Some(file.mangled_name().display().to_string());
-                buf_file.file_id = Some(i.to_string());
-                buf_file
+            let file = zip_err!(zip.by_index_raw(i), self.container_source())?;
+            zip_files.push(BufFile {
+                name: Some(file.name().to_string()),
+                size: Some(file.size()),
+                path: Some(file.mangled_name().display().to_string()),
+                file_id: Some(i.to_string()),
+                content_type: self.profile().and_then(|profile| profile.content_type(file.name())).map(String::from),
+                ..Default::default()
             });
         }
 
@@ -155,39 +255,83 @@ pub trait ZipContainerTrait: Clone + Send + Sync + 'static {
 
     /// Loads a file from the ZIP data synchronously.
     fn load_file_from_zip(&self, file_path: &str) -> ZipContainerResult<Vec<u8>> {
+        let stopwatch = Stopwatch::start();
         let zip_data = self.zip_data()?;
         let reader = std::io::Cursor::new(zip_data);
-        let mut zip = io_err!(zip::ZipArchive::new(reader))?;
-        let mut file = io_err!(zip.by_name(file_path))?;
+        let mut zip = zip_err!(zip::ZipArchive::new(reader), self.container_source())?;
+        let mut file = open_entry(&mut zip, self.container_source(), file_path)?;
         let mut buffer = Vec::new();
         io_err!(file.read_to_end(&mut buffer))?;
+        let message = format!("read {} bytes from '{}' in {:.1?}", buffer.len(), file_path, stopwatch.elapsed());
+        self.log_event(log::Level::Debug, &message);
         Ok(buffer)
     }
 
-    /// Loads a file either from the ZIP data or from a URL synchronously.
-    #[cfg(not(target_arch = "wasm32"))]
+    /// Reports that `path_or_url` is not part of the archive and is loaded through the loader.
+    fn log_fallback(&self, path_or_url: &str) {
+        self.log_event(log::Level::Debug, &fallback_message(path_or_url));
+    }
+
+    /// Returns where the container was loaded from, the base of relative references to
+    /// files outside the archive.
+    fn origin(&self) -> Option<&str> {
+        None
+    }
+
+    /// Returns `path_or_url` resolved against `origin` if that is a URL, as the loader sees
+    /// references that are not in the archive.
+    fn resolve_external(&self, path_or_url: &str) -> String {
+        match self.origin() {
+            Some(origin) => crate::loader_registry::resolve_against(origin, path_or_url),
+            None => path_or_url.to_string(),
+        }
+    }
+
+    /// Returns the loader used for files that are not part of the ZIP data.
+    fn loader(&self) -> &LoaderRegistry {
+        LoaderRegistry::shared()
+    }
+
+    /// Loads a file either from the ZIP data or through the loader synchronously.
     fn load_file(&self, file_path_or_url: &str) -> ZipContainerResult<Vec<u8>> {
         // Attempt to load from ZIP data
-        if let Ok(data) = self.load_file_from_zip(file_path_or_url) {
-            return Ok(data);
-        }
+        let zip_error = match self.load_file_from_zip(file_path_or_url) {
+            Ok(data) => return Ok(data),
+            Err(e) => e,
+        };
 
-        // Load using the unified file loader
-        let loader = UnifiedFileLoader;
-        loader.load(file_path_or_url)
+        // Load using the container's loader registry
+        let stopwatch = Stopwatch::start();
+        let location = self.resolve_external(file_path_or_url);
+        self.log_fallback(&location);
+        let loaded = self.loader().load(&location);
+        self.log_event(log::Level::Debug, &load_message(&location, &loaded, Vec::len, stopwatch));
+        loaded.map_err(|e| fallback_error(zip_error, file_path_or_url, e))
     }
 
-    #[cfg(target_arch = "wasm32")]
-    fn load_file(&self, _file_path_or_url: &str) -> ZipContainerResult<Vec<u8>> {
-        // Return an error since synchronous file loading is not supported in WASM
-        Err(ZipContainerError::UnsupportedOperation {
-            module_path: function_path!(),
-            message: "Synchronous file loading is not supported in WASM".to_string(),
-        })
+    /// Loads a file either from the ZIP data or through the loader into a `BufFile`.
+    fn load_buf_file(&self, file_path_or_url: &str) -> ZipContainerResult<BufFile> {
+        let zip_error = match self.load_file_from_zip(file_path_or_url) {
+            Ok(data) => return Ok(buf_file_from_bytes(file_path_or_url.to_string(), data)),
+            Err(e) => e,
+        };
+        let stopwatch = Stopwatch::start();
+        let location = self.resolve_external(file_path_or_url);
+        self.log_fallback(&location);
+        let loaded = self.loader().load_buf_file(&location);
+        self.log_event(log::Level::Debug, &load_message(&location, &loaded, buf_file_size, stopwatch));
+        loaded.map_err(|e| fallback_error(zip_error, file_path_or_url, e))
     }
 
     // Asynchronous methods
 
+    /// Like `log_event`, for the asynchronous loads: the returned future also passes the
+    /// event to the `AsyncLogger` of the container, if it has one.
+    fn log_event_async(&self, level: log::Level, message: &str) -> LogFuture {
+        self.log_event(level, message);
+        Box::pin(async {})
+    }
+
     /// Loads a file from the ZIP data asynchronously.
     fn load_file_from_zip_async(&self, file_path: &str) -> ZipContainerFuture<Vec<u8>> {
         let zip_data = match self.zip_data() {
@@ -195,10 +339,11 @@ pub trait ZipContainerTrait: Clone + Send + Sync + 'static {
             Err(e) => return Box::pin(async move { Err(e) }),
         };
         let file_path = file_path.to_string(); // Own the data
+        let container = self.container_source().to_string();
         Box::pin(async move {
             let reader = std::io::Cursor::new(zip_data);
-            let mut zip = io_err!(zip::ZipArchive::new(reader))?;
-            let mut file = io_err!(zip.by_name(&file_path))?;
+            let mut zip = zip_err!(zip::ZipArchive::new(reader), &container)?;
+            let mut file = open_entry(&mut zip, &container, &file_path)?;
             let mut buffer = Vec::new();
             io_err!(file.read_to_end(&mut buffer))?;
             Ok(buffer)
@@ -211,20 +356,369 @@ pub trait ZipContainerTrait: Clone + Send + Sync + 'static {
         let path_or_url = file_path_or_url.to_string(); // Own the data
         Box::pin(async move {
             // Attempt to load from ZIP data
-            if let Ok(data) = self_clone.load_file_from_zip_async(&path_or_url).await {
-                return Ok(data);
-            }
+            let stopwatch = Stopwatch::start();
+            let zip_error = match self_clone.load_file_from_zip_async(&path_or_url).await {
+                Ok(data) => {
+                    let message = format!("read {} bytes from '{}' in {:.1?}", data.len(), path_or_url, stopwatch.elapsed());
+                    self_clone.log_event_async(log::Level::Debug, &message).await;
+                    return Ok(data);
+                }
+                Err(e) => e,
+            };
 
-            // Load using the unified async file loader
-            let loader = UnifiedAsyncFileLoader;
-            loader.load_async(&path_or_url).await
+            // Load using the container's loader registry
+            let stopwatch = Stopwatch::start();
+            let location = self_clone.resolve_external(&path_or_url);
+            self_clone.log_event_async(log::Level::Debug, &fallback_message(&location)).await;
+            let loaded = self_clone.loader().load_async(&location).await;
+            let message = load_message(&location, &loaded, Vec::len, stopwatch);
+            self_clone.log_event_async(log::Level::Debug, &message).await;
+            loaded.map_err(|e| fallback_error(zip_error, &path_or_url, e))
         })
     }
+    /// Loads a file either from the ZIP data or through the loader into a `BufFile` asynchronously.
+    fn load_buf_file_async(&self, file_path_or_url: &str) -> ZipContainerFuture<BufFile> {
+        let self_clone = self.clone();
+        let path_or_url = file_path_or_url.to_string(); // Own the data
+        Box::pin(async move {
+            let stopwatch = Stopwatch::start();
+            let zip_error = match self_clone.load_file_from_zip_async(&path_or_url).await {
+                Ok(data) => {
+                    let message = format!("read {} bytes from '{}' in {:.1?}", data.len(), path_or_url, stopwatch.elapsed());
+                    self_clone.log_event_async(log::Level::Debug, &message).await;
+                    return Ok(buf_file_from_bytes(path_or_url, data));
+                }
+                Err(e) => e,
+            };
+            let stopwatch = Stopwatch::start();
+            let location = self_clone.resolve_external(&path_or_url);
+            self_clone.log_event_async(log::Level::Debug, &fallback_message(&location)).await;
+            let loaded = self_clone.loader().load_buf_file_async(&location).await;
+            let message = load_message(&location, &loaded, buf_file_size, stopwatch);
+            self_clone.log_event_async(log::Level::Debug, &message).await;
+            loaded.map_err(|e| fallback_error(zip_error, &path_or_url, e))
+        })
+    }
+
+    /// Returns the definition file decoded to UTF-8 from the encoding it is stored in.
     fn load_definition_file_str(&self) -> ZipContainerResult<String> {
-        let definition_path = self.definition_path()?;
-        let definition_content = self.load_file(definition_path)?;
-        let definition_content = String::from_utf8(definition_content)?;
-        Ok(definition_content)
+        read_definition_file(self).map(|(content, _)| content)
+    }
+
+    /// Returns the encoding the definition file is stored in.
+    fn definition_encoding(&self) -> ZipContainerResult<SourceEncoding> {
+        read_definition_file(self).map(|(_, encoding)| encoding)
+    }
+
+    /// Encodes the definition for writing it back, in the encoding of the definition file
+    /// or in UTF-8 if there is none yet.
+    fn encode_definition(&self) -> ZipContainerResult<Vec<u8>> {
+        let content = self.load_definition_file_str()?;
+        self.definition_encoding().unwrap_or_default().encode(&content)
+    }
+
+    /// Returns the format of the definition, by default from the extension of `definition_path`.
+    fn definition_kind(&self) -> Option<DefinitionKind> {
+        let definition_path = self.definition_path().ok()?;
+        detection::definition_from_extension(definition_path).map(|definition| definition.kind())
+    }
+
+    /// Returns the definition with its content; the format comes from `definition_kind`
+    /// or, if unknown, from the content.
+    fn definition(&self) -> ZipContainerResult<Definition> {
+        let content = self.load_definition_file_str()?;
+        let kind = self
+            .definition_kind()
+            .or_else(|| detection::sniff_definition(content.as_bytes()).map(|definition| definition.kind()))
+            .ok_or_else(|| self.definition_parse_error("unknown definition format".to_string()))?;
+        Ok(Definition::new(kind, content))
+    }
+
+    /// Deserializes the definition into `T` with the deserializer matching its format.
+    fn definition_as<T: DeserializeOwned>(&self) -> ZipContainerResult<T> {
+        self.definition()?
+            .parse_as()
+            .map_err(|e| self.definition_parse_error(e.to_string()))
+    }
+
+    /// Replaces the definition, e.g. with one produced by `set_definition_from`. Containers
+    /// that cannot hold a replaced definition keep the default, which fails.
+    fn set_definition(&mut self, definition: Definition) -> ZipContainerResult<()> {
+        Err(ZipContainerError::UnsupportedOperation {
+            module_path: function_path!(),
+            message: format!("{}: replacing the {} definition is not supported", self.container_source(), definition.format_name()),
+        })
+    }
+
+    /// Serializes `value` in the format of the current definition and sets it.
+    fn set_definition_from<T: Serialize>(&mut self, value: &T) -> ZipContainerResult<()> {
+        let kind = ok_or_err!(self.definition_kind(), "definition format is unknown")?;
+        let definition = Definition::serialize_from(kind, value)?;
+        self.set_definition(definition)
+    }
+
+    /// Runs an XPath or JSONPath (starting with `$`) expression on the definition, see
+    /// `query` for the supported syntax.
+    #[cfg(feature = "json")]
+    fn query(&self, expression: &str) -> ZipContainerResult<Vec<QueryMatch>> {
+        self.definition()?.query(expression)
+    }
+
+    /// Returns the string of the first match of `expression` that is not a subtree.
+    #[cfg(feature = "json")]
+    fn query_value(&self, expression: &str) -> ZipContainerResult<Option<String>> {
+        self.definition()?.query_value(expression)
+    }
+
+    /// Returns the ZIP data with `files`, pairs of an entry name and its content, written
+    /// into it: existing entries are replaced in place, new ones appended, and a name given
+    /// twice keeps its last content. A `mimetype` entry, from `files` or the archive, is
+    /// written first and uncompressed, as EPUB and ODF require. The archive comment is kept;
+    /// a container without ZIP data starts a new archive.
+    fn write_entries(&self, files: &[(String, Vec<u8>)]) -> ZipContainerResult<Vec<u8>> {
+        let zip_data = match self.zip_data() {
+            Ok(zip_data) => zip_data,
+            Err(ZipContainerError::MissingValue { .. }) => &[],
+            Err(e) => return Err(e),
+        };
+        writer::rewrite_archive(zip_data, self.container_source(), files)
+    }
+
+    /// Returns the definition path an EPUB `META-INF/container.xml` or ODF
+    /// `META-INF/manifest.xml` of the archive names, if that entry is present.
+    #[cfg(feature = "xml")]
+    fn definition_from_pointer(&self) -> ZipContainerResult<Option<String>> {
+        let entries = self.get_file_names()?;
+        for pointer_entry in crate::pointer::POINTER_ENTRIES {
+            let Some(entry) = entries.iter().find(|entry| entry.eq_ignore_ascii_case(pointer_entry)) else { continue };
+            let (content, _) = encoding::decode(&self.load_file_from_zip(entry)?)?;
+            match crate::pointer::pointed_definition(&content) {
+                Some(path) if entries.contains(&path) => {
+                    self.log_event(log::Level::Debug, &format!("'{}' points at the definition '{}'", entry, path));
+                    return Ok(Some(path));
+                }
+                Some(path) => {
+                    let message = format!("'{}' points at '{}', which is not in the archive", entry, path);
+                    self.log_event(log::Level::Warn, &message);
+                }
+                None => {}
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns the expected layout of the container, if known.
+    fn profile(&self) -> Option<&dyn ContainerProfile> {
+        None
+    }
+
+    /// Checks the archive against `profile` and returns the problems found, none if the
+    /// layout matches. Fails with `MissingValue` if there is no profile.
+    fn validate_layout(&self) -> ZipContainerResult<Vec<Diagnostic>> {
+        let profile = ok_or_err!(self.profile(), "no container profile is set")?;
+        self.validate_layout_with(profile)
+    }
+
+    /// Like `validate_layout`, against the given profile.
+    fn validate_layout_with(&self, profile: &dyn ContainerProfile) -> ZipContainerResult<Vec<Diagnostic>> {
+        let reader = std::io::Cursor::new(self.zip_data()?);
+        let mut zip = zip_err!(zip::ZipArchive::new(reader), self.container_source())?;
+        let mut entries = Vec::with_capacity(zip.len());
+        for i in 0..zip.len() {
+            let file = zip_err!(zip.by_index_raw(i), self.container_source())?;
+            let stored = file.compression() == zip::CompressionMethod::Stored;
+            entries.push(LayoutEntry { name: file.name().to_string(), stored });
+        }
+        let mimetype = match profile.mimetype().is_some() && entries.iter().any(|entry| entry.name == "mimetype") {
+            true => Some(String::from_utf8(self.load_file_from_zip("mimetype")?)?),
+            false => None,
+        };
+        let diagnostics = profile::check_layout(profile, &entries, mimetype.as_deref());
+        self.log_event(
+            log::Level::Debug,
+            &format!("checked {} layout: {} problem(s)", profile.name(), diagnostics.len()),
+        );
+        Ok(diagnostics)
+    }
+
+    /// Returns how `check_references` finds and resolves references: those of `profile`,
+    /// by default `ReferenceRules::default()`.
+    #[cfg(feature = "json")]
+    fn reference_rules(&self) -> ReferenceRules {
+        self.profile().and_then(|profile| profile.reference_rules()).unwrap_or_default()
+    }
+
+    /// Compares the file references of the definition with the archive entries, using
+    /// `reference_rules`.
+    #[cfg(feature = "json")]
+    fn check_references(&self) -> ZipContainerResult<ReferenceReport> {
+        self.check_references_with(&self.reference_rules())
+    }
+
+    /// Like `check_references`, with the given rules.
+    #[cfg(feature = "json")]
+    fn check_references_with(&self, rules: &ReferenceRules) -> ZipContainerResult<ReferenceReport> {
+        let definition = self.definition()?;
+        let mut matches = Vec::new();
+        for expression in &rules.expressions {
+            match definition.query(expression) {
+                Ok(found) => matches.extend(found),
+                // An XPath does not apply to a JSON, YAML or TOML definition, and vice versa.
+                Err(ZipContainerError::UnsupportedOperation { .. }) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        let entries: Vec<String> = self.get_file_names()?.into_iter().filter(|entry| !entry.ends_with('/')).collect();
+        let mut report = ReferenceReport::default();
+        let mut referenced = Vec::new();
+        for reference in references::collect_references(matches) {
+            if let Some(entry) = references::resolve(&reference, &entries, &rules.directories) {
+                referenced.push(entry.as_str());
+                report.resolved.push((reference, entry.clone()));
+            } else if rules.probe_external {
+                match self.loader().exists(&self.resolve_external(&reference)) {
+                    true => report.external.push(reference),
+                    false => report.dangling.push(reference),
+                }
+            } else if references::is_url(&self.resolve_external(&reference)) {
+                report.external.push(reference);
+            } else {
+                report.dangling.push(reference);
+            }
+        }
+        let definition_path = self.definition_path().unwrap_or_default();
+        report.orphans = entries
+            .iter()
+            .filter(|entry| *entry != definition_path && !referenced.contains(&entry.as_str()))
+            .filter(|entry| !rules.ignored.contains(entry))
+            .cloned()
+            .collect();
+        self.log_event(
+            log::Level::Debug,
+            &format!(
+                "checked references: {} resolved, {} dangling, {} external, {} orphan(s)",
+                report.resolved.len(),
+                report.dangling.len(),
+                report.external.len(),
+                report.orphans.len()
+            ),
+        );
+        Ok(report)
+    }
+
+    /// Downloads every external reference found by `check_references` into `folder` of the
+    /// archive and rewrites the definition to point at the copies. Returns the new ZIP data
+    /// and definition; the container itself is unchanged. Fails if a download fails.
+    #[cfg(feature = "json")]
+    fn localize_references(&self, folder: &str) -> ZipContainerResult<LocalizedContainer> {
+        let rules = self.reference_rules();
+        let report = self.check_references_with(&rules)?;
+        let definition = self.definition()?;
+        let mut entries = self.get_file_names()?;
+        let mut files = Vec::new();
+        let mut localized = Vec::new();
+        for reference in report.external {
+            let data = self.loader().load(&self.resolve_external(&reference))?;
+            let entry = localize::entry_name(folder, &reference, &entries);
+            self.log_event(log::Level::Info, &format!("localized '{}' as '{}' ({} bytes)", reference, entry, data.len()));
+            entries.push(entry.clone());
+            files.push((entry.clone(), data));
+            localized.push((reference, entry));
+        }
+        let content = localize::rewrite_references(&definition, &rules.expressions, &localized)?;
+        let definition = Definition::new(definition.kind(), content);
+        if localized.is_empty() {
+            return Ok(LocalizedContainer { zip_data: self.zip_data()?.to_vec(), definition, localized });
+        }
+        files.push((self.definition_path()?.to_string(), self.definition_encoding()?.encode(definition.as_ref())?));
+        let zip_data = writer::rewrite_archive(self.zip_data()?, self.container_source(), &files)?;
+        Ok(LocalizedContainer { zip_data, definition, localized })
+    }
+
+    /// Reads the GLDF file table of the definition, each file resolved to its archive entry.
+    #[cfg(all(feature = "json", feature = "xml"))]
+    fn file_table(&self) -> ZipContainerResult<Vec<FileTableEntry>> {
+        let entries = self.get_file_names()?;
+        file_table::read(&self.definition()?, &entries, &self.reference_rules().directories)
+    }
+
+    /// Like `get_zip_files`, but files listed in the GLDF file table get its ID as `file_id`
+    /// and its content type.
+    #[cfg(all(feature = "json", feature = "xml"))]
+    fn get_zip_files_with_ids(&self) -> ZipContainerResult<Vec<BufFile>> {
+        let mut files = self.get_zip_files()?;
+        file_table::apply(&self.file_table()?, &mut files);
+        Ok(files)
+    }
+
+    /// Loads the file with the ID `file_id` in the GLDF file table, from the archive or, for
+    /// URLs and files outside the archive, through the loader.
+    #[cfg(all(feature = "json", feature = "xml"))]
+    fn load_file_by_id(&self, file_id: &str) -> ZipContainerResult<Vec<u8>> {
+        let table = self.file_table()?;
+        let file = table.iter().find(|file| file.id == file_id).ok_or_else(|| ZipContainerError::EntryNotFound {
+            module_path: function_path!(),
+            container: self.container_source().to_string(),
+            entry: file_id.to_string(),
+        })?;
+        match &file.entry {
+            Some(entry) => self.load_file_from_zip(entry),
+            None => self.load_file(&file.reference),
+        }
+    }
+
+    /// Loads a schema from an entry of the container, a path or URL, or inline content.
+    /// A relative location is resolved against `origin`, like the other external references.
+    #[cfg(feature = "validation")]
+    fn load_schema(&self, source: &SchemaSource) -> ZipContainerResult<Schema> {
+        let content = match source {
+            SchemaSource::Entry(entry) => self.load_file_from_zip(entry)?,
+            SchemaSource::Location(path_or_url) => {
+                let stopwatch = Stopwatch::start();
+                let location = self.resolve_external(path_or_url);
+                let loaded = self.loader().load(&location);
+                self.log_event(log::Level::Debug, &load_message(&location, &loaded, Vec::len, stopwatch));
+                loaded?
+            }
+            SchemaSource::Inline(content) => return Schema::parse(content),
+        };
+        Schema::parse(&String::from_utf8(content)?)
+    }
+
+    /// Validates the definition against the schema from `source` and returns the problems
+    /// found, none if it is valid.
+    #[cfg(feature = "validation")]
+    fn validate_definition(&self, source: &SchemaSource) -> ZipContainerResult<Vec<Diagnostic>> {
+        let schema = self.load_schema(source)?;
+        let diagnostics = schema.validate(&self.definition()?)?;
+        self.log_event(log::Level::Debug, &format!("validated definition: {} problem(s)", diagnostics.len()));
+        Ok(diagnostics)
+    }
+
+    /// Like `validate_definition`, but fails with `ZipContainerError::ValidationFailed` if
+    /// the definition does not match the schema.
+    #[cfg(feature = "validation")]
+    fn ensure_valid_definition(&self, source: &SchemaSource) -> ZipContainerResult<()> {
+        let diagnostics = self.validate_definition(source)?;
+        if diagnostics.is_empty() {
+            return Ok(());
+        }
+        Err(ZipContainerError::ValidationFailed {
+            module_path: function_path!(),
+            container: self.container_source().to_string(),
+            entry: self.definition_path().unwrap_or_default().to_string(),
+            diagnostics,
+        })
+    }
+
+    #[doc(hidden)]
+    fn definition_parse_error(&self, message: String) -> ZipContainerError {
+        ZipContainerError::DefinitionParse {
+            module_path: function_path!(),
+            container: self.container_source().to_string(),
+            entry: self.definition_path().unwrap_or_default().to_string(),
+            message,
+        }
     }
     fn process_files(&mut self) -> ZipContainerResult<()> {
         let zip_files = self.get_zip_files()?;